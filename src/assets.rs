@@ -0,0 +1,531 @@
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+
+use num::{CheckedAdd, CheckedSub, Zero};
+
+use crate::support::{DispatchResult, Encode, Hooks};
+
+pub trait Config: crate::types::Config {
+	/// The type used to identify an asset.
+	type AssetId: Ord + Copy + Debug + Encode;
+	/// The maximum length in bytes of the name and symbol of an asset.
+	const STRING_LIMIT: usize;
+}
+
+/// Everything we record about an asset when it is created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetDetails<AccountId, Balance> {
+	/// The account which created the asset, and which can set its metadata.
+	pub owner: AccountId,
+	/// The account which can mint, burn and freeze the asset.
+	pub admin: AccountId,
+	/// The smallest balance an account can hold of this asset, other than zero.
+	pub min_balance: Balance,
+	/// The total amount of this asset held by all accounts.
+	pub supply: Balance,
+	/// Whether transfers of this asset are stopped for everyone.
+	pub is_frozen: bool,
+}
+
+/// The `AssetDetails` stored by a runtime configured with `T`.
+pub type AssetDetailsOf<T> =
+	AssetDetails<<T as crate::types::Config>::AccountId, <T as crate::types::Config>::Balance>;
+
+/// The holding of a single account in a single asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssetAccount<Balance> {
+	/// The amount of the asset held by the account.
+	pub balance: Balance,
+	/// Whether the account is stopped from transferring the asset.
+	pub is_frozen: bool,
+}
+
+/// The key under which the holding of an account in an asset is stored.
+pub type AssetAccountKey<T> = (<T as Config>::AssetId, <T as crate::types::Config>::AccountId);
+
+/// The human readable description of an asset.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AssetMetadata {
+	/// The name of the asset, at most `Config::STRING_LIMIT` bytes long.
+	pub name: Vec<u8>,
+	/// The ticker symbol of the asset, at most `Config::STRING_LIMIT` bytes long.
+	pub symbol: Vec<u8>,
+	/// The number of decimals a user interface should use to display balances.
+	pub decimals: u8,
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// `owner` created `asset_id`, to be managed by `admin`.
+	Created { asset_id: T::AssetId, owner: T::AccountId, admin: T::AccountId },
+	/// `amount` of `asset_id` was created in the account of `owner`.
+	Issued { asset_id: T::AssetId, owner: T::AccountId, amount: T::Balance },
+	/// `amount` of `asset_id` was destroyed from the account of `owner`.
+	Burned { asset_id: T::AssetId, owner: T::AccountId, amount: T::Balance },
+	/// `amount` of `asset_id` moved from `from` to `to`.
+	Transferred { asset_id: T::AssetId, from: T::AccountId, to: T::AccountId, amount: T::Balance },
+	/// `who` can no longer transfer `asset_id`.
+	Frozen { asset_id: T::AssetId, who: T::AccountId },
+	/// `who` can transfer `asset_id` again.
+	Thawed { asset_id: T::AssetId, who: T::AccountId },
+	/// Nobody can transfer `asset_id` any more.
+	AssetFrozen { asset_id: T::AssetId },
+	/// Everybody can transfer `asset_id` again.
+	AssetThawed { asset_id: T::AssetId },
+	/// The metadata of `asset_id` was set.
+	MetadataSet { asset_id: T::AssetId, name: Vec<u8>, symbol: Vec<u8>, decimals: u8 },
+}
+
+/// This is the Assets Module.
+/// It keeps track of any number of fungible assets living alongside the native currency.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The details of each asset.
+	pub assets: BTreeMap<T::AssetId, AssetDetailsOf<T>>,
+	/// The holdings of each account, keyed by asset and then by account.
+	pub accounts: BTreeMap<AssetAccountKey<T>, AssetAccount<T::Balance>>,
+	/// The metadata of each asset which has some.
+	pub metadata: BTreeMap<T::AssetId, AssetMetadata>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	Create { asset_id: T::AssetId, admin: T::AccountId, min_balance: T::Balance },
+	Mint { asset_id: T::AssetId, beneficiary: T::AccountId, amount: T::Balance },
+	Burn { asset_id: T::AssetId, who: T::AccountId, amount: T::Balance },
+	Transfer { asset_id: T::AssetId, to: T::AccountId, amount: T::Balance },
+	Freeze { asset_id: T::AssetId, who: T::AccountId },
+	Thaw { asset_id: T::AssetId, who: T::AccountId },
+	FreezeAsset(T::AssetId),
+	ThawAsset(T::AssetId),
+	SetMetadata { asset_id: T::AssetId, name: Vec<u8>, symbol: Vec<u8>, decimals: u8 },
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::Create { asset_id, admin, min_balance } => {
+				(0u8, asset_id, admin, min_balance).encode_to(dest)
+			},
+			EntryPoint::Mint { asset_id, beneficiary, amount } => {
+				(1u8, asset_id, beneficiary, amount).encode_to(dest)
+			},
+			EntryPoint::Burn { asset_id, who, amount } => {
+				(2u8, asset_id, who, amount).encode_to(dest)
+			},
+			EntryPoint::Transfer { asset_id, to, amount } => {
+				(3u8, asset_id, to, amount).encode_to(dest)
+			},
+			EntryPoint::Freeze { asset_id, who } => (4u8, asset_id, who).encode_to(dest),
+			EntryPoint::Thaw { asset_id, who } => (5u8, asset_id, who).encode_to(dest),
+			EntryPoint::FreezeAsset(asset_id) => (6u8, asset_id).encode_to(dest),
+			EntryPoint::ThawAsset(asset_id) => (7u8, asset_id).encode_to(dest),
+			EntryPoint::SetMetadata { asset_id, name, symbol, decimals } => {
+				(8u8, asset_id, name, symbol, decimals).encode_to(dest)
+			},
+		}
+	}
+}
+
+/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate underlying
+/// function we want to execute.
+impl<T: Config> crate::support::Dispatch for Pallet<T> {
+	type Caller = T::AccountId;
+	type Call = EntryPoint<T>;
+
+	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult {
+		match call {
+			EntryPoint::Create { asset_id, admin, min_balance } => {
+				self.create(caller, asset_id, admin, min_balance)?
+			},
+			EntryPoint::Mint { asset_id, beneficiary, amount } => {
+				self.mint(caller, asset_id, beneficiary, amount)?
+			},
+			EntryPoint::Burn { asset_id, who, amount } => {
+				self.burn(caller, asset_id, who, amount)?
+			},
+			EntryPoint::Transfer { asset_id, to, amount } => {
+				self.transfer(caller, asset_id, to, amount)?
+			},
+			EntryPoint::Freeze { asset_id, who } => self.freeze(caller, asset_id, who)?,
+			EntryPoint::Thaw { asset_id, who } => self.thaw(caller, asset_id, who)?,
+			EntryPoint::FreezeAsset(asset_id) => self.freeze_asset(caller, asset_id)?,
+			EntryPoint::ThawAsset(asset_id) => self.thaw_asset(caller, asset_id)?,
+			EntryPoint::SetMetadata { asset_id, name, symbol, decimals } => {
+				self.set_metadata(caller, asset_id, name, symbol, decimals)?
+			},
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Assets Module.
+	pub fn new() -> Self {
+		Self {
+			assets: BTreeMap::new(),
+			accounts: BTreeMap::new(),
+			metadata: BTreeMap::new(),
+			events: Vec::new(),
+		}
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
+	}
+
+	/// Get the details (if any) of an asset.
+	pub fn asset(&self, asset_id: T::AssetId) -> Option<&AssetDetailsOf<T>> {
+		self.assets.get(&asset_id)
+	}
+
+	/// Get the amount of `asset_id` held by `who`.
+	pub fn balance(&self, asset_id: T::AssetId, who: &T::AccountId) -> T::Balance {
+		self.accounts
+			.get(&(asset_id, who.clone()))
+			.map(|account| account.balance)
+			.unwrap_or(T::Balance::zero())
+	}
+
+	/// Get the total amount of `asset_id` held by all accounts.
+	pub fn total_supply(&self, asset_id: T::AssetId) -> T::Balance {
+		self.asset(asset_id).map(|details| details.supply).unwrap_or(T::Balance::zero())
+	}
+
+	/// Create a new asset owned by the `caller` and managed by `admin`. Accounts can only hold
+	/// zero, or at least `min_balance` of the asset. This function will return an error if the
+	/// asset already exists.
+	pub fn create(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		admin: T::AccountId,
+		min_balance: T::Balance,
+	) -> DispatchResult {
+		if self.assets.contains_key(&asset_id) {
+			return Err("This asset already exists")
+		}
+		if min_balance.is_zero() {
+			return Err("Minimum balance must not be zero")
+		}
+		let details = AssetDetails {
+			owner: caller.clone(),
+			admin: admin.clone(),
+			min_balance,
+			supply: T::Balance::zero(),
+			is_frozen: false,
+		};
+		self.assets.insert(asset_id, details);
+		self.deposit_event(Event::Created { asset_id, owner: caller, admin });
+		Ok(())
+	}
+
+	/// Create `amount` of `asset_id` in the account of `beneficiary`. Only the admin of the asset
+	/// can do this.
+	pub fn mint(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		beneficiary: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let details = self.ensure_admin(&caller, asset_id)?;
+		let supply = details.supply.checked_add(&amount).ok_or("Overflow")?;
+		let mut account = self.account(asset_id, &beneficiary);
+		account.balance = account.balance.checked_add(&amount).ok_or("Overflow")?;
+		self.ensure_min_balance(asset_id, account.balance)?;
+
+		self.set_supply(asset_id, supply);
+		self.accounts.insert((asset_id, beneficiary.clone()), account);
+		self.deposit_event(Event::Issued { asset_id, owner: beneficiary, amount });
+		Ok(())
+	}
+
+	/// Destroy `amount` of `asset_id` from the account of `who`. Only the admin of the asset can
+	/// do this.
+	pub fn burn(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let details = self.ensure_admin(&caller, asset_id)?;
+		let supply = details.supply.checked_sub(&amount).ok_or("Not enough funds.")?;
+		let mut account = self.account(asset_id, &who);
+		account.balance = account.balance.checked_sub(&amount).ok_or("Not enough funds.")?;
+		self.ensure_min_balance(asset_id, account.balance)?;
+
+		self.set_supply(asset_id, supply);
+		self.write_account(asset_id, &who, account);
+		self.deposit_event(Event::Burned { asset_id, owner: who, amount });
+		Ok(())
+	}
+
+	/// Move `amount` of `asset_id` from the `caller` to `to`. This function will return an error
+	/// if the asset or the caller's account is frozen, or if either account would be left with
+	/// less than the minimum balance.
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let details = self.asset(asset_id).ok_or("Asset does not exist")?;
+		let mut from_account = self.account(asset_id, &caller);
+		if details.is_frozen || from_account.is_frozen {
+			return Err("Asset is frozen")
+		}
+		from_account.balance =
+			from_account.balance.checked_sub(&amount).ok_or("Not enough funds.")?;
+		self.ensure_min_balance(asset_id, from_account.balance)?;
+		if caller != to {
+			let mut to_account = self.account(asset_id, &to);
+			to_account.balance = to_account.balance.checked_add(&amount).ok_or("Overflow")?;
+			self.ensure_min_balance(asset_id, to_account.balance)?;
+
+			self.write_account(asset_id, &caller, from_account);
+			self.write_account(asset_id, &to, to_account);
+		}
+		self.deposit_event(Event::Transferred { asset_id, from: caller, to, amount });
+		Ok(())
+	}
+
+	/// Stop `who` from transferring `asset_id`. Only the admin of the asset can do this.
+	pub fn freeze(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		who: T::AccountId,
+	) -> DispatchResult {
+		self.set_account_frozen(caller, asset_id, &who, true)?;
+		self.deposit_event(Event::Frozen { asset_id, who });
+		Ok(())
+	}
+
+	/// Allow `who` to transfer `asset_id` again. Only the admin of the asset can do this.
+	pub fn thaw(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		who: T::AccountId,
+	) -> DispatchResult {
+		self.set_account_frozen(caller, asset_id, &who, false)?;
+		self.deposit_event(Event::Thawed { asset_id, who });
+		Ok(())
+	}
+
+	/// Stop every account from transferring `asset_id`. Only the admin of the asset can do this.
+	pub fn freeze_asset(&mut self, caller: T::AccountId, asset_id: T::AssetId) -> DispatchResult {
+		self.ensure_admin(&caller, asset_id)?;
+		self.assets.entry(asset_id).and_modify(|details| details.is_frozen = true);
+		self.deposit_event(Event::AssetFrozen { asset_id });
+		Ok(())
+	}
+
+	/// Allow every account to transfer `asset_id` again. Only the admin of the asset can do this.
+	pub fn thaw_asset(&mut self, caller: T::AccountId, asset_id: T::AssetId) -> DispatchResult {
+		self.ensure_admin(&caller, asset_id)?;
+		self.assets.entry(asset_id).and_modify(|details| details.is_frozen = false);
+		self.deposit_event(Event::AssetThawed { asset_id });
+		Ok(())
+	}
+
+	/// Set the name, symbol and decimals of `asset_id`. Only the owner of the asset can do this.
+	/// This function will return an error if the name or symbol are longer than
+	/// `T::STRING_LIMIT`.
+	pub fn set_metadata(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		name: Vec<u8>,
+		symbol: Vec<u8>,
+		decimals: u8,
+	) -> DispatchResult {
+		let details = self.asset(asset_id).ok_or("Asset does not exist")?;
+		if details.owner != caller {
+			return Err("Caller is not the owner of the asset")
+		}
+		if name.len() > T::STRING_LIMIT || symbol.len() > T::STRING_LIMIT {
+			return Err("Name or symbol is too long")
+		}
+		let metadata = AssetMetadata { name: name.clone(), symbol: symbol.clone(), decimals };
+		self.metadata.insert(asset_id, metadata);
+		self.deposit_event(Event::MetadataSet { asset_id, name, symbol, decimals });
+		Ok(())
+	}
+
+	/// Get the holding of `who` in `asset_id`, which is empty if it does not exist yet.
+	fn account(&self, asset_id: T::AssetId, who: &T::AccountId) -> AssetAccount<T::Balance> {
+		self.accounts
+			.get(&(asset_id, who.clone()))
+			.copied()
+			.unwrap_or(AssetAccount { balance: T::Balance::zero(), is_frozen: false })
+	}
+
+	/// Store the holding of `who` in `asset_id`, removing it once it is empty and not frozen.
+	fn write_account(
+		&mut self,
+		asset_id: T::AssetId,
+		who: &T::AccountId,
+		account: AssetAccount<T::Balance>,
+	) {
+		if account.balance.is_zero() && !account.is_frozen {
+			self.accounts.remove(&(asset_id, who.clone()));
+		} else {
+			self.accounts.insert((asset_id, who.clone()), account);
+		}
+	}
+
+	fn set_supply(&mut self, asset_id: T::AssetId, supply: T::Balance) {
+		self.assets.entry(asset_id).and_modify(|details| details.supply = supply);
+	}
+
+	fn set_account_frozen(
+		&mut self,
+		caller: T::AccountId,
+		asset_id: T::AssetId,
+		who: &T::AccountId,
+		is_frozen: bool,
+	) -> DispatchResult {
+		self.ensure_admin(&caller, asset_id)?;
+		let mut account = self.account(asset_id, who);
+		account.is_frozen = is_frozen;
+		self.write_account(asset_id, who, account);
+		Ok(())
+	}
+
+	/// Check that `caller` is the admin of `asset_id`, returning the details of the asset.
+	fn ensure_admin(
+		&self,
+		caller: &T::AccountId,
+		asset_id: T::AssetId,
+	) -> Result<AssetDetailsOf<T>, &'static str> {
+		let details = self.asset(asset_id).ok_or("Asset does not exist")?;
+		if &details.admin != caller {
+			return Err("Caller is not the admin of the asset")
+		}
+		Ok(details.clone())
+	}
+
+	/// Check that an account holding `balance` of `asset_id` would be allowed to exist.
+	fn ensure_min_balance(&self, asset_id: T::AssetId, balance: T::Balance) -> DispatchResult {
+		let min_balance = self.asset(asset_id).ok_or("Asset does not exist")?.min_balance;
+		if !balance.is_zero() && balance < min_balance {
+			return Err("Balance would be below the minimum balance")
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, _n: T::BlockNumber) {
+		self.events.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	pub struct Test;
+
+	impl super::Config for Test {
+		type AssetId = u32;
+		const STRING_LIMIT: usize = 8;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	#[test]
+	fn create_mint_and_burn() {
+		let mut assets = super::Pallet::<Test>::new();
+		assert_eq!(assets.create("alice", 1, "bob", 0), Err("Minimum balance must not be zero"));
+		assert_eq!(assets.create("alice", 1, "bob", 5), Ok(()));
+		assert_eq!(assets.create("charlie", 1, "charlie", 5), Err("This asset already exists"));
+
+		assert_eq!(
+			assets.mint("alice", 1, "charlie", 100),
+			Err("Caller is not the admin of the asset")
+		);
+		assert_eq!(assets.mint("bob", 2, "charlie", 100), Err("Asset does not exist"));
+		assert_eq!(
+			assets.mint("bob", 1, "charlie", 4),
+			Err("Balance would be below the minimum balance")
+		);
+		assert_eq!(assets.mint("bob", 1, "charlie", 100), Ok(()));
+		assert_eq!(assets.mint("bob", 1, "alice", 50), Ok(()));
+		assert_eq!(assets.total_supply(1), 150);
+
+		assert_eq!(assets.burn("bob", 1, "charlie", 101), Err("Not enough funds."));
+		assert_eq!(assets.burn("bob", 1, "charlie", 100), Ok(()));
+		assert_eq!(assets.balance(1, &"charlie"), 0);
+		assert_eq!(assets.total_supply(1), 50);
+		// Empty accounts are removed from storage.
+		assert!(!assets.accounts.contains_key(&(1, "charlie")));
+	}
+
+	#[test]
+	fn transfer_and_freeze() {
+		let mut assets = super::Pallet::<Test>::new();
+		assets.create("alice", 1, "alice", 10).unwrap();
+		assets.create("alice", 2, "alice", 1).unwrap();
+		assets.mint("alice", 1, "bob", 100).unwrap();
+		assets.mint("alice", 2, "bob", 7).unwrap();
+
+		// Balances of different assets are independent.
+		assert_eq!(
+			assets.transfer("bob", 1, "charlie", 95),
+			Err("Balance would be below the minimum balance")
+		);
+		assert_eq!(
+			assets.transfer("bob", 1, "charlie", 5),
+			Err("Balance would be below the minimum balance")
+		);
+		assert_eq!(assets.transfer("bob", 1, "charlie", 60), Ok(()));
+		assert_eq!(assets.balance(1, &"bob"), 40);
+		assert_eq!(assets.balance(1, &"charlie"), 60);
+		assert_eq!(assets.balance(2, &"bob"), 7);
+		assert_eq!(assets.total_supply(1), 100);
+
+		assert_eq!(assets.freeze("bob", 1, "charlie"), Err("Caller is not the admin of the asset"));
+		assert_eq!(assets.freeze("alice", 1, "charlie"), Ok(()));
+		assert_eq!(assets.transfer("charlie", 1, "bob", 10), Err("Asset is frozen"));
+		// Frozen accounts can still receive.
+		assert_eq!(assets.transfer("bob", 1, "charlie", 10), Ok(()));
+		assets.thaw("alice", 1, "charlie").unwrap();
+		assert_eq!(assets.transfer("charlie", 1, "bob", 10), Ok(()));
+
+		assets.freeze_asset("alice", 2).unwrap();
+		assert_eq!(assets.transfer("bob", 2, "charlie", 1), Err("Asset is frozen"));
+		assets.thaw_asset("alice", 2).unwrap();
+		assert_eq!(assets.transfer("bob", 2, "charlie", 1), Ok(()));
+	}
+
+	#[test]
+	fn asset_metadata() {
+		let mut assets = super::Pallet::<Test>::new();
+		assets.create("alice", 1, "bob", 1).unwrap();
+		let set = |assets: &mut super::Pallet<Test>, who, name: &[u8]| {
+			assets.set_metadata(who, 1, name.to_vec(), b"LOY".to_vec(), 2)
+		};
+		assert_eq!(
+			set(&mut assets, "bob", b"Loyalty"),
+			Err("Caller is not the owner of the asset")
+		);
+		assert_eq!(set(&mut assets, "alice", b"Loyalty points"), Err("Name or symbol is too long"));
+		assert_eq!(set(&mut assets, "alice", b"Loyalty"), Ok(()));
+		assert_eq!(assets.metadata[&1].symbol, b"LOY".to_vec());
+		assert_eq!(assets.metadata[&1].decimals, 2);
+	}
+}
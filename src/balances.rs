@@ -2,22 +2,94 @@ use std::collections::BTreeMap;
 
 use num::{CheckedAdd, CheckedSub, Zero};
 
-use crate::{support::DispatchResult, Config};
+use crate::{
+	support::{
+		BalanceStatus, Currency, DispatchResult, Encode, Hooks, LockIdentifier, LockableCurrency,
+		NegativeImbalance, ReservableCurrency, WithdrawReasons,
+	},
+};
+
+pub trait Config: crate::types::Config {
+	/// The maximum number of recipients of a single `EntryPoint::TransferMany` call.
+	const MAX_TRANSFERS_PER_BATCH: usize;
+}
+
+/// The balance of a single account, split between the funds it can spend and the funds it holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountData<Balance> {
+	/// The funds which can be transferred.
+	pub free: Balance,
+	/// The funds set aside with `ReservableCurrency::reserve`, which can not be spent until they
+	/// are unreserved.
+	pub reserved: Balance,
+}
+
+/// A lock on the free balance of an account. See `LockableCurrency`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceLock<Balance> {
+	/// The amount which can not be withdrawn.
+	pub amount: Balance,
+	/// The reasons for which it can not be withdrawn.
+	pub reasons: WithdrawReasons,
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// `amount` moved from the free balance of `from` to the free balance of `to`.
+	Transferred { from: T::AccountId, to: T::AccountId, amount: T::Balance },
+	/// `from` paid a total of `total` to `recipients` accounts in a single call.
+	BatchTransferred { from: T::AccountId, recipients: u32, total: T::Balance },
+}
 
 //use crate::types::*;
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pallet<T: Config> {
 	// we do this to specify which type from which trait, 
 	// because what if there is another trait that the type implements that has the same type Name
-	balances: BTreeMap<<T as Config>::AccountId, T::Balance>, 
+	accounts: BTreeMap<<T as crate::types::Config>::AccountId, AccountData<T::Balance>>,
+	/// The locks on the free balance of each account, by lock identifier.
+	locks: BTreeMap<T::AccountId, BTreeMap<LockIdentifier, BalanceLock<T::Balance>>>,
+	/// The sum of the free and reserved balances of every account.
+	total_issuance: T::Balance,
+	/// The amount each spender may still transfer out of an owner's account, keyed by owner and
+	/// then by spender.
+	allowances: BTreeMap<(T::AccountId, T::AccountId), T::Balance>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
 }
 
 // A public enum which describes the calls we want to expose to the dispatcher.
 // We should expect that the caller of each call will be provided by the dispatcher,
 // and not included as a parameter of the call.
+#[derive(Debug, Clone)]
 pub enum EntryPoint<T: Config> {
 	//Transfer(T::AccountId,T::Balance)
 	Transfer { to: T::AccountId, amount: T::Balance },
+	Approve { spender: T::AccountId, amount: T::Balance },
+	IncreaseAllowance { spender: T::AccountId, amount: T::Balance },
+	DecreaseAllowance { spender: T::AccountId, amount: T::Balance },
+	TransferFrom { owner: T::AccountId, to: T::AccountId, amount: T::Balance },
+	TransferMany(Vec<(T::AccountId, T::Balance)>),
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::Transfer { to, amount } => (0u8, to, amount).encode_to(dest),
+			EntryPoint::Approve { spender, amount } => (1u8, spender, amount).encode_to(dest),
+			EntryPoint::IncreaseAllowance { spender, amount } => {
+				(2u8, spender, amount).encode_to(dest)
+			},
+			EntryPoint::DecreaseAllowance { spender, amount } => {
+				(3u8, spender, amount).encode_to(dest)
+			},
+			EntryPoint::TransferFrom { owner, to, amount } => {
+				(4u8, owner, to, amount).encode_to(dest)
+			},
+			EntryPoint::TransferMany(transfers) => (5u8, transfers).encode_to(dest),
+		}
+	}
 }
 
 /// Implementation of the dispatch logic, mapping from `BalancesCall` to the appropriate underlying
@@ -34,6 +106,17 @@ impl<T: Config> crate::support::Dispatch for Pallet<T> {
 	) -> crate::support::DispatchResult {
 		match call {
 			EntryPoint::Transfer { to, amount } => self.transfer(&caller, &to, amount)?,
+			EntryPoint::Approve { spender, amount } => self.approve(&caller, &spender, amount),
+			EntryPoint::IncreaseAllowance { spender, amount } => {
+				self.increase_allowance(&caller, &spender, amount)?
+			},
+			EntryPoint::DecreaseAllowance { spender, amount } => {
+				self.decrease_allowance(&caller, &spender, amount)?
+			},
+			EntryPoint::TransferFrom { owner, to, amount } => {
+				self.transfer_from(&caller, &owner, &to, amount)?
+			},
+			EntryPoint::TransferMany(transfers) => self.transfer_many(&caller, transfers)?,
 		}
 		Ok(())
 	}
@@ -41,13 +124,58 @@ impl<T: Config> crate::support::Dispatch for Pallet<T> {
 
 impl<T: Config> Pallet<T> {
 	pub fn new() -> Self {
-		Self { balances: BTreeMap::new() }
+		Self {
+			accounts: BTreeMap::new(),
+			locks: BTreeMap::new(),
+			total_issuance: T::Balance::zero(),
+			allowances: BTreeMap::new(),
+			events: Vec::new(),
+		}
+	}
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
 	}
+	/// Get the free and reserved balance of an account.
+	pub fn account(&self, who: &T::AccountId) -> AccountData<T::Balance> {
+		self.accounts
+			.get(who)
+			.copied()
+			.unwrap_or(AccountData { free: T::Balance::zero(), reserved: T::Balance::zero() })
+	}
+	/// Set the free balance of an account, creating or destroying funds as needed.
 	pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
-		self.balances.insert(who.clone(), amount);
+		let mut account = self.account(who);
+		self.total_issuance = self.total_issuance - account.free + amount;
+		account.free = amount;
+		self.accounts.insert(who.clone(), account);
 	}
+	/// Get the free balance of an account, which is what it can spend.
 	pub fn balance(&self, who: &T::AccountId) -> T::Balance {
-		*self.balances.get(who).unwrap_or(&T::Balance::zero())
+		self.account(who).free
+	}
+	/// Get the amount of the free balance of an account which is locked for any of `reasons`.
+	pub fn frozen_balance(&self, who: &T::AccountId, reasons: WithdrawReasons) -> T::Balance {
+		self.locks
+			.get(who)
+			.into_iter()
+			.flat_map(|locks| locks.values())
+			.filter(|lock| lock.reasons.intersects(reasons))
+			.map(|lock| lock.amount)
+			.max()
+			.unwrap_or(T::Balance::zero())
+	}
+	/// Check that `who` can withdraw funds for `reasons`, leaving it with a free balance of
+	/// `new_free`.
+	fn ensure_can_withdraw(
+		&self,
+		who: &T::AccountId,
+		reasons: WithdrawReasons,
+		new_free: T::Balance,
+	) -> DispatchResult {
+		if new_free < self.frozen_balance(who, reasons) {
+			return Err("Funds are locked.")
+		}
+		Ok(())
 	}
 	pub fn transfer(
 		&mut self,
@@ -60,17 +188,310 @@ impl<T: Config> Pallet<T> {
 
 		let new_caller_balance = caller_balance.checked_sub(&amount).ok_or("Not enough funds.")?;
 		let new_to_balance = to_balance.checked_add(&amount).ok_or("Overflow")?;
+		self.ensure_can_withdraw(from, WithdrawReasons::TRANSFER, new_caller_balance)?;
+		if from == to {
+			// Both balances were read before either is written, so writing them would create
+			// `amount` out of nothing.
+			return Ok(())
+		}
+
+		self.set_balance(from, new_caller_balance);
+		self.set_balance(to, new_to_balance);
+
+		self.deposit_event(Event::Transferred { from: from.clone(), to: to.clone(), amount });
+		Ok(())
+	}
+	/// Pay every `(recipient, amount)` pair in `transfers` out of the free balance of `from`.
+	/// Either every transfer is applied, or none of them is. This function will return an error
+	/// if there are no transfers or more than `T::MAX_TRANSFERS_PER_BATCH` of them, or if `from`
+	/// can not afford the total.
+	pub fn transfer_many(
+		&mut self,
+		from: &T::AccountId,
+		transfers: Vec<(T::AccountId, T::Balance)>,
+	) -> DispatchResult {
+		if transfers.is_empty() {
+			return Err("No transfers given.")
+		}
+		if transfers.len() > T::MAX_TRANSFERS_PER_BATCH {
+			return Err("Too many transfers.")
+		}
+		let total = transfers
+			.iter()
+			.try_fold(T::Balance::zero(), |total, (_, amount)| total.checked_add(amount))
+			.ok_or("Overflow")?;
+		let mut from_account = self.account(from);
+		from_account.free = from_account.free.checked_sub(&total).ok_or("Not enough funds.")?;
+		self.ensure_can_withdraw(from, WithdrawReasons::TRANSFER, from_account.free)?;
+
+		// Apply the transfers to a copy of the accounts involved, so that nothing is written unless
+		// every transfer succeeds.
+		let mut changed = BTreeMap::new();
+		changed.insert(from.clone(), from_account);
+		for (to, amount) in &transfers {
+			let account = changed.entry(to.clone()).or_insert_with(|| self.account(to));
+			account.free = account.free.checked_add(amount).ok_or("Overflow")?;
+		}
+		self.accounts.extend(changed);
+
+		for (to, amount) in transfers.iter().cloned() {
+			self.deposit_event(Event::Transferred { from: from.clone(), to, amount });
+		}
+		let recipients = transfers.len() as u32;
+		self.deposit_event(Event::BatchTransferred { from: from.clone(), recipients, total });
+		Ok(())
+	}
+	/// Get the amount `spender` may still transfer out of the account of `owner`.
+	pub fn allowance(&self, owner: &T::AccountId, spender: &T::AccountId) -> T::Balance {
+		self.allowances
+			.get(&(owner.clone(), spender.clone()))
+			.copied()
+			.unwrap_or(T::Balance::zero())
+	}
+	/// Allow `spender` to transfer up to `amount` out of the account of `owner`, replacing any
+	/// previous allowance.
+	pub fn approve(&mut self, owner: &T::AccountId, spender: &T::AccountId, amount: T::Balance) {
+		let key = (owner.clone(), spender.clone());
+		if amount.is_zero() {
+			self.allowances.remove(&key);
+		} else {
+			self.allowances.insert(key, amount);
+		}
+	}
+	/// Add `amount` to the allowance of `spender` over the account of `owner`.
+	pub fn increase_allowance(
+		&mut self,
+		owner: &T::AccountId,
+		spender: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let allowance = self.allowance(owner, spender).checked_add(&amount).ok_or("Overflow")?;
+		self.approve(owner, spender, allowance);
+		Ok(())
+	}
+	/// Take `amount` off the allowance of `spender` over the account of `owner`.
+	pub fn decrease_allowance(
+		&mut self,
+		owner: &T::AccountId,
+		spender: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let allowance =
+			self.allowance(owner, spender).checked_sub(&amount).ok_or("Not enough allowance.")?;
+		self.approve(owner, spender, allowance);
+		Ok(())
+	}
+	/// Transfer `amount` from `owner` to `to` on behalf of `spender`, using up that much of the
+	/// allowance `owner` gave to `spender`.
+	pub fn transfer_from(
+		&mut self,
+		spender: &T::AccountId,
+		owner: &T::AccountId,
+		to: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let allowance =
+			self.allowance(owner, spender).checked_sub(&amount).ok_or("Not enough allowance.")?;
+		self.transfer(owner, to, amount)?;
+		self.approve(owner, spender, allowance);
+		Ok(())
+	}
+}
+
+impl<T: Config> Currency<T::AccountId> for Pallet<T> {
+	type Balance = T::Balance;
+
+	fn free_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.balance(who)
+	}
+
+	fn total_balance(&self, who: &T::AccountId) -> T::Balance {
+		let account = self.account(who);
+		account.free + account.reserved
+	}
+
+	fn total_issuance(&self) -> T::Balance {
+		self.total_issuance
+	}
+
+	fn transfer(
+		&mut self,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		Pallet::transfer(self, from, to, amount)
+	}
+
+	fn withdraw(
+		&mut self,
+		who: &T::AccountId,
+		amount: T::Balance,
+		reasons: WithdrawReasons,
+	) -> Result<NegativeImbalance<T::Balance>, &'static str> {
+		let mut account = self.account(who);
+		account.free = account.free.checked_sub(&amount).ok_or("Not enough funds.")?;
+		self.ensure_can_withdraw(who, reasons, account.free)?;
+		self.accounts.insert(who.clone(), account);
+
+		Ok(NegativeImbalance::new(amount))
+	}
+
+	fn deposit_creating(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		let mut account = self.account(who);
+		account.free = account.free.checked_add(&amount).ok_or("Overflow")?;
+		self.total_issuance = self.total_issuance.checked_add(&amount).ok_or("Overflow")?;
+		self.accounts.insert(who.clone(), account);
+
+		Ok(())
+	}
+
+	fn resolve_creating(&mut self, who: &T::AccountId, imbalance: NegativeImbalance<T::Balance>) {
+		// The funds were already counted in the total issuance, so this can not overflow.
+		let mut account = self.account(who);
+		account.free += imbalance.peek();
+		self.accounts.insert(who.clone(), account);
+	}
+
+	fn burn(&mut self, imbalance: NegativeImbalance<T::Balance>) {
+		self.total_issuance = self.total_issuance - imbalance.peek();
+	}
+
+	fn slash(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+		let mut account = self.account(who);
+		let from_free = amount.min(account.free);
+		let from_reserved = (amount - from_free).min(account.reserved);
+		account.free = account.free - from_free;
+		account.reserved = account.reserved - from_reserved;
+		self.total_issuance = self.total_issuance - from_free - from_reserved;
+		self.accounts.insert(who.clone(), account);
+
+		amount - from_free - from_reserved
+	}
+}
 
-		self.balances.insert(from.clone(), new_caller_balance);
-		self.balances.insert(to.clone(), new_to_balance);
+impl<T: Config> ReservableCurrency<T::AccountId> for Pallet<T> {
+	fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.account(who).reserved
+	}
+
+	fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		let mut account = self.account(who);
+		account.free = account.free.checked_sub(&amount).ok_or("Not enough funds.")?;
+		account.reserved = account.reserved.checked_add(&amount).ok_or("Overflow")?;
+		self.ensure_can_withdraw(who, WithdrawReasons::RESERVE, account.free)?;
+		self.accounts.insert(who.clone(), account);
 
 		Ok(())
 	}
+
+	fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+		let mut account = self.account(who);
+		let actual = amount.min(account.reserved);
+		account.reserved = account.reserved - actual;
+		account.free += actual;
+		self.accounts.insert(who.clone(), account);
+
+		amount - actual
+	}
+
+	fn slash_reserved(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+		let mut account = self.account(who);
+		let actual = amount.min(account.reserved);
+		account.reserved = account.reserved - actual;
+		self.total_issuance = self.total_issuance - actual;
+		self.accounts.insert(who.clone(), account);
+
+		amount - actual
+	}
+
+	fn repatriate_reserved(
+		&mut self,
+		slashed: &T::AccountId,
+		beneficiary: &T::AccountId,
+		amount: T::Balance,
+		status: BalanceStatus,
+	) -> DispatchResult {
+		let mut from = self.account(slashed);
+		from.reserved = from.reserved.checked_sub(&amount).ok_or("Not enough reserved funds.")?;
+		if slashed == beneficiary {
+			// Moving funds to our own reserved balance is a no-op, and to our own free balance is
+			// the same as unreserving them.
+			if status == BalanceStatus::Free {
+				self.unreserve(slashed, amount);
+			}
+			return Ok(())
+		}
+
+		let mut to = self.account(beneficiary);
+		match status {
+			BalanceStatus::Free => to.free = to.free.checked_add(&amount).ok_or("Overflow")?,
+			BalanceStatus::Reserved => {
+				to.reserved = to.reserved.checked_add(&amount).ok_or("Overflow")?
+			},
+		}
+
+		self.accounts.insert(slashed.clone(), from);
+		self.accounts.insert(beneficiary.clone(), to);
+
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, _n: T::BlockNumber) {
+		self.events.clear();
+	}
+}
+
+impl<T: Config> LockableCurrency<T::AccountId> for Pallet<T> {
+	fn set_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &T::AccountId,
+		amount: T::Balance,
+		reasons: WithdrawReasons,
+	) {
+		let lock = BalanceLock { amount, reasons };
+		self.locks.entry(who.clone()).or_default().insert(id, lock);
+	}
+
+	fn extend_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &T::AccountId,
+		amount: T::Balance,
+		reasons: WithdrawReasons,
+	) {
+		let locks = self.locks.entry(who.clone()).or_default();
+		let lock = match locks.get(&id) {
+			Some(lock) => BalanceLock {
+				amount: lock.amount.max(amount),
+				reasons: lock.reasons.union(reasons),
+			},
+			None => BalanceLock { amount, reasons },
+		};
+		locks.insert(id, lock);
+	}
+
+	fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+		if let Some(locks) = self.locks.get_mut(who) {
+			locks.remove(&id);
+			if locks.is_empty() {
+				self.locks.remove(who);
+			}
+		}
+	}
 }
 
 #[cfg(test)]
 mod test {
 	use crate::types::*;
+
+	impl super::Config for TestConfig {
+		const MAX_TRANSFERS_PER_BATCH: usize = 100;
+	}
+
 	#[test]
 	fn init_balances() {
 		let mut balances: super::Pallet<TestConfig> = crate::balances::Pallet::new();
@@ -92,4 +513,190 @@ mod test {
 		assert_eq!(balances.balance(&"alice".to_owned()), 50);
 		assert_eq!(balances.balance(&"bob".to_owned()), 150);
 	}
+	#[test]
+	fn transfer_to_self() {
+		use crate::support::{Currency, LockableCurrency, WithdrawReasons};
+		let mut balances: super::Pallet<TestConfig> = crate::balances::Pallet::new();
+		let alice = "alice".to_owned();
+		balances.set_balance(&alice, 100);
+		let mut transfer = |amount| Currency::transfer(&mut balances, &alice, &alice, amount);
+		assert_eq!(transfer(101), Err("Not enough funds."));
+		assert_eq!(transfer(50), Ok(()));
+		assert_eq!(balances.balance(&alice), 100);
+		assert_eq!(balances.total_issuance(), 100);
+		// The funds still have to be transferable.
+		balances.set_lock(*b"testlock", &alice, 60, WithdrawReasons::TRANSFER);
+		assert_eq!(balances.transfer(&alice, &alice, 50), Err("Funds are locked."));
+		assert_eq!(balances.total_issuance(), 100);
+	}
+	#[test]
+	fn transfer_many() {
+		use super::{Config, Event};
+		let mut balances: super::Pallet<TestConfig> = crate::balances::Pallet::new();
+		let alice = "alice".to_owned();
+		let bob = "bob".to_owned();
+		let charlie = "charlie".to_owned();
+		balances.set_balance(&alice, 100);
+		assert_eq!(balances.transfer_many(&alice, vec![]), Err("No transfers given."));
+		let too_many = vec![(bob.clone(), 0); TestConfig::MAX_TRANSFERS_PER_BATCH + 1];
+		assert_eq!(balances.transfer_many(&alice, too_many), Err("Too many transfers."));
+		assert_eq!(
+			balances.transfer_many(&alice, vec![(bob.clone(), u128::MAX), (charlie.clone(), 1)]),
+			Err("Overflow")
+		);
+		// Nothing is paid out unless everything can be.
+		assert_eq!(
+			balances.transfer_many(&alice, vec![(bob.clone(), 60), (charlie.clone(), 41)]),
+			Err("Not enough funds.")
+		);
+		assert_eq!(balances.balance(&bob), 0);
+		assert!(balances.events.is_empty());
+
+		let transfers = vec![(bob.clone(), 30), (charlie.clone(), 20), (bob.clone(), 10)];
+		assert_eq!(balances.transfer_many(&alice, transfers), Ok(()));
+		assert_eq!(balances.balance(&alice), 40);
+		assert_eq!(balances.balance(&bob), 40);
+		assert_eq!(balances.balance(&charlie), 20);
+		assert_eq!(balances.events.len(), 4);
+		assert!(matches!(
+			balances.events.last(),
+			Some(Event::BatchTransferred { recipients: 3, total: 60, .. })
+		));
+	}
+	#[test]
+	fn allowances() {
+		let mut balances: super::Pallet<TestConfig> = crate::balances::Pallet::new();
+		let alice = "alice".to_owned();
+		let bob = "bob".to_owned();
+		let charlie = "charlie".to_owned();
+		balances.set_balance(&alice, 100);
+		assert_eq!(balances.transfer_from(&bob, &alice, &charlie, 1), Err("Not enough allowance."));
+
+		balances.approve(&alice, &bob, 30);
+		assert_eq!(balances.increase_allowance(&alice, &bob, 20), Ok(()));
+		assert_eq!(balances.decrease_allowance(&alice, &bob, 51), Err("Not enough allowance."));
+		assert_eq!(balances.decrease_allowance(&alice, &bob, 10), Ok(()));
+		assert_eq!(balances.allowance(&alice, &bob), 40);
+
+		assert_eq!(balances.transfer_from(&bob, &alice, &charlie, 25), Ok(()));
+		assert_eq!(balances.allowance(&alice, &bob), 15);
+		assert_eq!(balances.balance(&alice), 75);
+		assert_eq!(balances.balance(&charlie), 25);
+		assert_eq!(balances.balance(&bob), 0);
+		// A failed transfer does not use up the allowance.
+		balances.set_balance(&alice, 10);
+		assert_eq!(balances.transfer_from(&bob, &alice, &charlie, 15), Err("Not enough funds."));
+		assert_eq!(balances.allowance(&alice, &bob), 15);
+	}
+	#[test]
+	fn reserve_and_unreserve() {
+		use crate::support::{BalanceStatus, ReservableCurrency};
+		let mut balances: super::Pallet<TestConfig> = crate::balances::Pallet::new();
+		let alice = "alice".to_owned();
+		let bob = "bob".to_owned();
+		balances.set_balance(&alice, 100);
+		assert_eq!(balances.reserve(&alice, 101), Err("Not enough funds."));
+		assert_eq!(balances.reserve(&alice, 60), Ok(()));
+		assert_eq!(balances.balance(&alice), 40);
+		assert_eq!(balances.reserved_balance(&alice), 60);
+		// Reserved funds can not be transferred.
+		assert_eq!(balances.transfer(&alice, &bob, 50), Err("Not enough funds."));
+
+		assert_eq!(
+			balances.repatriate_reserved(&alice, &bob, 61, BalanceStatus::Reserved),
+			Err("Not enough reserved funds.")
+		);
+		assert_eq!(balances.repatriate_reserved(&alice, &bob, 20, BalanceStatus::Reserved), Ok(()));
+		assert_eq!(balances.reserved_balance(&bob), 20);
+		assert_eq!(balances.repatriate_reserved(&alice, &bob, 10, BalanceStatus::Free), Ok(()));
+		assert_eq!(balances.account(&bob), super::AccountData { free: 10, reserved: 20 });
+
+		// Only what is actually reserved can be unreserved.
+		assert_eq!(balances.unreserve(&alice, 50), 20);
+		assert_eq!(balances.balance(&alice), 70);
+		assert_eq!(balances.reserved_balance(&alice), 0);
+	}
+	#[test]
+	fn balance_locks() {
+		use crate::support::{LockableCurrency, ReservableCurrency, WithdrawReasons};
+		let mut balances: super::Pallet<TestConfig> = crate::balances::Pallet::new();
+		let alice = "alice".to_owned();
+		let bob = "bob".to_owned();
+		balances.set_balance(&alice, 100);
+		balances.set_lock(*b"staking ", &alice, 60, WithdrawReasons::TRANSFER);
+		assert_eq!(balances.transfer(&alice, &bob, 41), Err("Funds are locked."));
+		assert_eq!(balances.transfer(&alice, &bob, 40), Ok(()));
+		// Locked funds stay in the account.
+		assert_eq!(balances.balance(&alice), 60);
+		// The lock only applies to transfers.
+		assert_eq!(balances.reserve(&alice, 10), Ok(()));
+		balances.unreserve(&alice, 10);
+
+		// Locks overlap rather than add up.
+		balances.set_lock(*b"vesting ", &alice, 30, WithdrawReasons::ALL);
+		assert_eq!(balances.frozen_balance(&alice, WithdrawReasons::TRANSFER), 60);
+		assert_eq!(balances.frozen_balance(&alice, WithdrawReasons::RESERVE), 30);
+		assert_eq!(balances.reserve(&alice, 31), Err("Funds are locked."));
+
+		// Extending a lock never shrinks it.
+		balances.extend_lock(*b"vesting ", &alice, 20, WithdrawReasons::TRANSFER);
+		balances.remove_lock(*b"staking ", &alice);
+		assert_eq!(balances.frozen_balance(&alice, WithdrawReasons::TRANSFER), 30);
+		assert_eq!(balances.transfer(&alice, &bob, 31), Err("Funds are locked."));
+		balances.remove_lock(*b"vesting ", &alice);
+		assert_eq!(balances.transfer(&alice, &bob, 60), Ok(()));
+	}
+	#[test]
+	fn slash_reserved() {
+		use crate::support::{Currency, ReservableCurrency};
+		let mut balances: super::Pallet<TestConfig> = crate::balances::Pallet::new();
+		let alice = "alice".to_owned();
+		balances.set_balance(&alice, 100);
+		balances.reserve(&alice, 30).unwrap();
+		assert_eq!(balances.total_balance(&alice), 100);
+		// Slashed funds are gone for good, and we can not slash more than is reserved.
+		assert_eq!(balances.slash_reserved(&alice, 20), 0);
+		assert_eq!(balances.slash_reserved(&alice, 20), 10);
+		assert_eq!(balances.account(&alice), super::AccountData { free: 70, reserved: 0 });
+		assert_eq!(balances.total_balance(&alice), 70);
+		assert_eq!(balances.total_issuance(), 70);
+	}
+	#[test]
+	fn slash() {
+		use crate::support::{Currency, LockableCurrency, ReservableCurrency, WithdrawReasons};
+		let mut balances: super::Pallet<TestConfig> = crate::balances::Pallet::new();
+		let alice = "alice".to_owned();
+		balances.set_balance(&alice, 100);
+		balances.reserve(&alice, 30).unwrap();
+		balances.set_lock(*b"staking ", &alice, 50, WithdrawReasons::ALL);
+		// Slashing ignores locks, and takes from the reserved balance once the free one is empty.
+		assert_eq!(balances.slash(&alice, 80), 0);
+		assert_eq!(balances.account(&alice), super::AccountData { free: 0, reserved: 20 });
+		assert_eq!(balances.slash(&alice, 30), 10);
+		assert_eq!(balances.account(&alice), super::AccountData { free: 0, reserved: 0 });
+		assert_eq!(balances.total_issuance(), 0);
+	}
+	#[test]
+	fn withdraw_and_deposit() {
+		use crate::support::{Currency, LockableCurrency, WithdrawReasons};
+		let mut balances: super::Pallet<TestConfig> = crate::balances::Pallet::new();
+		let alice = "alice".to_owned();
+		let bob = "bob".to_owned();
+		balances.set_balance(&alice, 100);
+		assert_eq!(balances.deposit_creating(&bob, 50), Ok(()));
+		assert_eq!(balances.total_issuance(), 150);
+
+		balances.set_lock(*b"staking ", &alice, 80, WithdrawReasons::FEE);
+		assert_eq!(balances.withdraw(&alice, 21, WithdrawReasons::FEE), Err("Funds are locked."));
+		let imbalance = balances.withdraw(&alice, 30, WithdrawReasons::TRANSFER).unwrap();
+		assert_eq!(balances.free_balance(&alice), 70);
+		// Withdrawn funds still exist until they are burned.
+		assert_eq!(balances.total_issuance(), 150);
+
+		let (to_bob, to_burn) = imbalance.split(20);
+		balances.resolve_creating(&bob, to_bob);
+		balances.burn(to_burn);
+		assert_eq!(balances.free_balance(&bob), 70);
+		assert_eq!(balances.total_issuance(), 140);
+	}
 }
@@ -0,0 +1,777 @@
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+
+use num::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, One, Zero};
+
+use crate::support::{
+	ensure_root, ensure_signed, percent_of, Currency, DispatchResult, Encode, Hooks,
+	LockIdentifier, LockableCurrency, RawOrigin, ReservableCurrency, ScheduleCall,
+	WithdrawReasons,
+};
+
+/// The identifier of the balance lock holding the funds used to vote.
+pub const DEMOCRACY_ID: LockIdentifier = *b"democrac";
+
+/// The index of a referendum, in the order they were proposed.
+pub type ReferendumIndex = u32;
+
+pub trait Config: crate::types::Config {
+	/// The outer call type of the runtime, which a proposal dispatches as `Root` once it passes.
+	type RuntimeCall: Clone + Debug + Encode;
+	/// The currency used for proposal deposits and to weigh votes.
+	type Currency: ReservableCurrency<Self::AccountId, Balance = Self::Balance>
+		+ LockableCurrency<Self::AccountId, Balance = Self::Balance>;
+	/// Where the call of a passed proposal is scheduled to be enacted.
+	type Scheduler: ScheduleCall<Self::BlockNumber, Self::RuntimeCall, RawOrigin<Self::AccountId>>;
+	/// The amount reserved from the proposer until the referendum on its proposal ends.
+	const MINIMUM_DEPOSIT: Self::Balance;
+	/// How many blocks a referendum is open for votes.
+	const VOTING_PERIOD: Self::BlockNumber;
+	/// How many blocks after a referendum passed its call is dispatched. If the scheduler has no
+	/// room left in that block, the call is dispatched in the first of the next
+	/// `ENACTMENT_PERIOD` blocks which has some.
+	const ENACTMENT_PERIOD: Self::BlockNumber;
+	/// How many blocks the funds of a vote with `Locked1x` conviction stay locked after the
+	/// referendum ends. Each level of conviction above doubles it.
+	const VOTE_LOCKING_PERIOD: Self::BlockNumber;
+	/// The percentage of the total issuance which must back the votes in a referendum for it to
+	/// pass, until `Root` sets another one.
+	const DEFAULT_MINIMUM_TURNOUT_PERCENT: u32;
+}
+
+/// How long a voter agrees to keep their funds locked after a referendum ends, if it ends the
+/// way they voted. The longer the lock, the more their vote counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conviction {
+	/// Counts for a tenth of the funds, which are not locked after the referendum.
+	None,
+	/// Counts once, locked for one period.
+	Locked1x,
+	/// Counts twice, locked for two periods.
+	Locked2x,
+	/// Counts three times, locked for four periods.
+	Locked3x,
+	/// Counts four times, locked for eight periods.
+	Locked4x,
+	/// Counts five times, locked for sixteen periods.
+	Locked5x,
+	/// Counts six times, locked for thirty-two periods.
+	Locked6x,
+}
+
+impl Conviction {
+	/// The number of votes `balance` is worth with this conviction.
+	pub fn votes<Balance>(self, balance: Balance) -> Option<Balance>
+	where
+		Balance: CheckedMul + CheckedDiv + From<u32>,
+	{
+		match self {
+			Conviction::None => balance.checked_div(&Balance::from(10)),
+			conviction => balance.checked_mul(&Balance::from(conviction as u32)),
+		}
+	}
+
+	/// The number of `VOTE_LOCKING_PERIOD`s the funds stay locked after the referendum.
+	pub fn lock_periods(self) -> u32 {
+		match self {
+			Conviction::None => 0,
+			conviction => 1 << (conviction as u32 - 1),
+		}
+	}
+}
+
+impl Encode for Conviction {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(*self as u8).encode_to(dest)
+	}
+}
+
+/// A vote for or against a referendum, backed by `balance`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vote<Balance> {
+	/// Whether the vote is for the proposal.
+	pub aye: bool,
+	/// How long the funds stay locked after the referendum, which multiplies the vote.
+	pub conviction: Conviction,
+	/// The funds backing the vote, which are locked while the referendum is ongoing.
+	pub balance: Balance,
+}
+
+/// The `Vote` of a runtime configured with `T`.
+pub type VoteOf<T> = Vote<<T as crate::types::Config>::Balance>;
+
+/// The votes counted for and against a referendum, weighted by conviction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tally<Balance> {
+	pub ayes: Balance,
+	pub nays: Balance,
+	/// The funds backing the votes, without conviction.
+	pub turnout: Balance,
+}
+
+/// A referendum which is open for votes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferendumStatus<AccountId, Balance, BlockNumber, Call> {
+	/// The account which proposed the referendum.
+	pub proposer: AccountId,
+	/// The call dispatched as `Root` if the referendum passes.
+	pub call: Call,
+	/// The deposit reserved from the proposer.
+	pub deposit: Balance,
+	/// The block at which voting ends and the votes are counted.
+	pub end: BlockNumber,
+	/// The votes counted so far.
+	pub tally: Tally<Balance>,
+}
+
+/// The `ReferendumStatus` stored by a runtime configured with `T`.
+pub type ReferendumStatusOf<T> = ReferendumStatus<
+	<T as crate::types::Config>::AccountId,
+	<T as crate::types::Config>::Balance,
+	<T as crate::types::Config>::BlockNumber,
+	<T as Config>::RuntimeCall,
+>;
+
+/// Everything we know about a referendum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferendumInfo<Status, BlockNumber> {
+	/// The referendum is open for votes.
+	Ongoing(Status),
+	/// Voting ended at block `end`, and the proposal was `approved` or not.
+	Finished { approved: bool, end: BlockNumber },
+}
+
+/// The `ReferendumInfo` stored by a runtime configured with `T`.
+pub type ReferendumInfoOf<T> =
+	ReferendumInfo<ReferendumStatusOf<T>, <T as crate::types::Config>::BlockNumber>;
+
+/// Funds which stay locked after a referendum ended, because of the conviction of a vote in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorLock<BlockNumber, Balance> {
+	/// The block from which the funds are not locked anymore.
+	pub until: BlockNumber,
+	/// The amount locked.
+	pub amount: Balance,
+}
+
+/// The `PriorLock` stored by a runtime configured with `T`.
+pub type PriorLockOf<T> =
+	PriorLock<<T as crate::types::Config>::BlockNumber, <T as crate::types::Config>::Balance>;
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// `proposer` started the referendum `index`.
+	Proposed { index: ReferendumIndex, proposer: T::AccountId },
+	/// `voter` voted in the referendum `index`.
+	Voted { index: ReferendumIndex, voter: T::AccountId, vote: VoteOf<T> },
+	/// The referendum `index` passed, and its call is scheduled to be dispatched at `enactment`.
+	Passed { index: ReferendumIndex, enactment: T::BlockNumber },
+	/// The referendum `index` did not pass.
+	NotPassed { index: ReferendumIndex },
+	/// The referendum `index` passed, but its call could not be scheduled.
+	EnactmentFailed { index: ReferendumIndex, error: &'static str },
+	/// The referendum `index` was cancelled before voting ended.
+	Cancelled { index: ReferendumIndex },
+	/// `Root` changed the minimum turnout of referenda to `percent` of the total issuance.
+	MinimumTurnoutSet { percent: u32 },
+}
+
+/// This is the Democracy Module.
+/// It lets token holders decide, by referendum, which calls are dispatched as `Root`.
+///
+/// Any account can propose a call with a deposit, which starts a referendum. Votes are weighted by
+/// the funds backing them and by conviction. If the ayes outweigh the nays when voting ends, and
+/// enough of the total issuance voted, the call is scheduled to be dispatched as `Root` after
+/// `T::ENACTMENT_PERIOD`.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The number of referenda started so far, which is also the index of the next one.
+	pub referendum_count: ReferendumIndex,
+	/// Every referendum, ongoing or finished.
+	pub referenda: BTreeMap<ReferendumIndex, ReferendumInfoOf<T>>,
+	/// The vote of each account in each ongoing referendum.
+	pub votes: BTreeMap<(ReferendumIndex, T::AccountId), VoteOf<T>>,
+	/// The funds of each account which stay locked after the referenda it voted in ended.
+	pub prior_locks: BTreeMap<T::AccountId, Vec<PriorLockOf<T>>>,
+	/// The percentage of the total issuance which must back the votes in a referendum for it to
+	/// pass.
+	pub minimum_turnout_percent: u32,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+	/// The number of the block currently being executed, as given to us in `on_initialize`.
+	block_number: T::BlockNumber,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	Propose(Box<T::RuntimeCall>),
+	Vote { index: ReferendumIndex, vote: VoteOf<T> },
+	RemoveVote(ReferendumIndex),
+	Unlock(T::AccountId),
+	CancelReferendum(ReferendumIndex),
+	SetMinimumTurnout(u32),
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::Propose(call) => (0u8, call).encode_to(dest),
+			EntryPoint::Vote { index, vote } => {
+				let Vote { aye, conviction, balance } = vote;
+				(1u8, index, aye, conviction, balance).encode_to(dest)
+			},
+			EntryPoint::RemoveVote(index) => (2u8, index).encode_to(dest),
+			EntryPoint::Unlock(target) => (3u8, target).encode_to(dest),
+			EntryPoint::CancelReferendum(index) => (4u8, index).encode_to(dest),
+			EntryPoint::SetMinimumTurnout(percent) => (5u8, percent).encode_to(dest),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Democracy Module.
+	pub fn new() -> Self {
+		Self {
+			referendum_count: 0,
+			referenda: BTreeMap::new(),
+			votes: BTreeMap::new(),
+			prior_locks: BTreeMap::new(),
+			minimum_turnout_percent: T::DEFAULT_MINIMUM_TURNOUT_PERCENT,
+			events: Vec::new(),
+			block_number: T::BlockNumber::zero(),
+		}
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
+	}
+
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	///
+	/// Every call must be signed, except `CancelReferendum` and `SetMinimumTurnout` which must be
+	/// dispatched as `Root`.
+	pub fn dispatch(
+		&mut self,
+		currency: &mut T::Currency,
+		origin: RawOrigin<T::AccountId>,
+		call: EntryPoint<T>,
+	) -> DispatchResult {
+		match call {
+			EntryPoint::Propose(call) => self.propose(currency, ensure_signed(origin)?, *call)?,
+			EntryPoint::Vote { index, vote } => {
+				self.vote(currency, ensure_signed(origin)?, index, vote)?
+			},
+			EntryPoint::RemoveVote(index) => {
+				self.remove_vote(currency, ensure_signed(origin)?, index)?
+			},
+			EntryPoint::Unlock(target) => {
+				ensure_signed(origin)?;
+				self.unlock(currency, target)?
+			},
+			EntryPoint::CancelReferendum(index) => {
+				ensure_root(origin)?;
+				self.cancel_referendum(currency, index)?
+			},
+			EntryPoint::SetMinimumTurnout(percent) => {
+				ensure_root(origin)?;
+				self.set_minimum_turnout(percent)?
+			},
+		}
+		Ok(())
+	}
+
+	/// Start a referendum on dispatching `call` as `Root`, reserving `T::MINIMUM_DEPOSIT` from
+	/// the `caller` until voting ends.
+	pub fn propose(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		call: T::RuntimeCall,
+	) -> DispatchResult {
+		let end = self.block_number.checked_add(&T::VOTING_PERIOD).ok_or("Overflow")?;
+		let index = self.referendum_count;
+		let next_index = index.checked_add(1).ok_or("Overflow")?;
+		currency.reserve(&caller, T::MINIMUM_DEPOSIT)?;
+
+		let zero = T::Balance::zero();
+		let tally = Tally { ayes: zero, nays: zero, turnout: zero };
+		let status = ReferendumStatus {
+			proposer: caller.clone(),
+			call,
+			deposit: T::MINIMUM_DEPOSIT,
+			end,
+			tally,
+		};
+		self.referenda.insert(index, ReferendumInfo::Ongoing(status));
+		self.referendum_count = next_index;
+		self.deposit_event(Event::Proposed { index, proposer: caller });
+		Ok(())
+	}
+
+	/// Vote in the ongoing referendum `index`, replacing the previous vote of the `caller` in it.
+	/// The funds backing the vote are locked until voting ends, and longer with conviction.
+	pub fn vote(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		index: ReferendumIndex,
+		vote: VoteOf<T>,
+	) -> DispatchResult {
+		if vote.balance > currency.free_balance(&caller) {
+			return Err("Not enough funds to back the vote")
+		}
+		let previous = self.votes.get(&(index, caller.clone())).copied();
+		let status = self.ongoing_mut(index)?;
+		let mut tally = status.tally;
+		if let Some(previous) = previous {
+			Self::remove_from_tally(&mut tally, &previous)?;
+		}
+		Self::add_to_tally(&mut tally, &vote)?;
+		status.tally = tally;
+
+		self.votes.insert((index, caller.clone()), vote);
+		self.update_lock(currency, &caller);
+		self.deposit_event(Event::Voted { index, voter: caller, vote });
+		Ok(())
+	}
+
+	/// Remove the vote of the `caller` from the ongoing referendum `index`.
+	pub fn remove_vote(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		index: ReferendumIndex,
+	) -> DispatchResult {
+		let vote = *self.votes.get(&(index, caller.clone())).ok_or("Vote does not exist")?;
+		let status = self.ongoing_mut(index)?;
+		Self::remove_from_tally(&mut status.tally, &vote)?;
+		self.votes.remove(&(index, caller.clone()));
+		self.update_lock(currency, &caller);
+		Ok(())
+	}
+
+	/// Release the funds of `target` which no longer need to be locked. Anyone can do this for
+	/// anyone else.
+	pub fn unlock(&mut self, currency: &mut T::Currency, target: T::AccountId) -> DispatchResult {
+		self.update_lock(currency, &target);
+		Ok(())
+	}
+
+	/// Cancel the ongoing referendum `index`. Its deposit is returned, and the funds of its voters
+	/// are not locked any longer because of it.
+	pub fn cancel_referendum(
+		&mut self,
+		currency: &mut T::Currency,
+		index: ReferendumIndex,
+	) -> DispatchResult {
+		let status = self.ongoing_mut(index)?.clone();
+		self.referenda.remove(&index);
+		currency.unreserve(&status.proposer, status.deposit);
+		for (voter, _) in self.take_votes(index) {
+			self.update_lock(currency, &voter);
+		}
+		self.deposit_event(Event::Cancelled { index });
+		Ok(())
+	}
+
+	/// Set the percentage of the total issuance which must back the votes in a referendum for it to
+	/// pass. It applies to the referenda which are still ongoing.
+	pub fn set_minimum_turnout(&mut self, percent: u32) -> DispatchResult {
+		if percent > 100 {
+			return Err("Percentage must not be more than 100")
+		}
+		self.minimum_turnout_percent = percent;
+		self.deposit_event(Event::MinimumTurnoutSet { percent });
+		Ok(())
+	}
+
+	/// Count the votes of the referenda whose voting ends at block `n`, and schedule the calls
+	/// of those which passed, as described on `T::ENACTMENT_PERIOD`. The runtime calls this at the
+	/// start of every block.
+	pub fn end_referenda(
+		&mut self,
+		currency: &mut T::Currency,
+		scheduler: &mut T::Scheduler,
+		n: T::BlockNumber,
+	) {
+		let ending: Vec<_> = self
+			.referenda
+			.iter()
+			.filter(|(_, info)| matches!(info, ReferendumInfo::Ongoing(status) if status.end <= n))
+			.map(|(index, _)| *index)
+			.collect();
+		let minimum_turnout = percent_of(currency.total_issuance(), self.minimum_turnout_percent);
+		for index in ending {
+			let Some(ReferendumInfo::Ongoing(status)) = self.referenda.remove(&index) else {
+				continue
+			};
+			let approved = status.tally.ayes > status.tally.nays &&
+				minimum_turnout.is_some_and(|minimum| status.tally.turnout >= minimum);
+			self.referenda.insert(index, ReferendumInfo::Finished { approved, end: n });
+			currency.unreserve(&status.proposer, status.deposit);
+
+			// Voters on the winning side keep their funds locked for as long as they committed to.
+			for (voter, vote) in self.take_votes(index) {
+				if vote.aye == approved {
+					if let Some(until) = Self::locked_until(n, vote.conviction) {
+						let lock = PriorLock { until, amount: vote.balance };
+						self.prior_locks.entry(voter.clone()).or_default().push(lock);
+					}
+				}
+				self.update_lock(currency, &voter);
+			}
+
+			if !approved {
+				self.deposit_event(Event::NotPassed { index });
+				continue
+			}
+			match Self::schedule_enactment(scheduler, n, status.call) {
+				Ok(enactment) => self.deposit_event(Event::Passed { index, enactment }),
+				Err(error) => self.deposit_event(Event::EnactmentFailed { index, error }),
+			}
+		}
+	}
+
+	/// Schedule `call` to be dispatched as `Root` in the first block from `n + T::ENACTMENT_PERIOD`
+	/// which has room for it, and return that block. Gives up after `T::ENACTMENT_PERIOD` more
+	/// blocks, with the error of the last attempt.
+	fn schedule_enactment(
+		scheduler: &mut T::Scheduler,
+		n: T::BlockNumber,
+		call: T::RuntimeCall,
+	) -> Result<T::BlockNumber, &'static str> {
+		let mut when = n.checked_add(&T::ENACTMENT_PERIOD).ok_or("Overflow")?;
+		let last = when.checked_add(&T::ENACTMENT_PERIOD).ok_or("Overflow")?;
+		loop {
+			match scheduler.schedule_call(when, call.clone(), RawOrigin::Root) {
+				Ok(()) => return Ok(when),
+				Err(error) if when >= last => return Err(error),
+				Err(_) => when = when.checked_add(&T::BlockNumber::one()).ok_or("Overflow")?,
+			}
+		}
+	}
+
+	/// Get the status of the ongoing referendum `index`.
+	fn ongoing_mut(
+		&mut self,
+		index: ReferendumIndex,
+	) -> Result<&mut ReferendumStatusOf<T>, &'static str> {
+		match self.referenda.get_mut(&index) {
+			Some(ReferendumInfo::Ongoing(status)) => Ok(status),
+			_ => Err("Referendum is not ongoing"),
+		}
+	}
+
+	/// Remove every vote in the referendum `index`, and return them.
+	fn take_votes(&mut self, index: ReferendumIndex) -> Vec<(T::AccountId, VoteOf<T>)> {
+		let keys: Vec<_> = self.votes.keys().filter(|(i, _)| *i == index).cloned().collect();
+		keys.into_iter()
+			.filter_map(|key| self.votes.remove(&key).map(|vote| (key.1, vote)))
+			.collect()
+	}
+
+	/// The block until which the funds of a vote with `conviction` stay locked, when the
+	/// referendum ended at block `end`. `None` if they are not locked after the referendum.
+	fn locked_until(end: T::BlockNumber, conviction: Conviction) -> Option<T::BlockNumber> {
+		let periods = conviction.lock_periods();
+		if periods == 0 {
+			return None
+		}
+		// Stop at the last block number we can represent rather than overflow.
+		let until = (0..periods)
+			.fold(end, |until, _| until.checked_add(&T::VOTE_LOCKING_PERIOD).unwrap_or(until));
+		Some(until)
+	}
+
+	fn add_to_tally(tally: &mut Tally<T::Balance>, vote: &VoteOf<T>) -> DispatchResult {
+		let votes = vote.conviction.votes(vote.balance).ok_or("Overflow")?;
+		let side = if vote.aye { &mut tally.ayes } else { &mut tally.nays };
+		*side = side.checked_add(&votes).ok_or("Overflow")?;
+		tally.turnout = tally.turnout.checked_add(&vote.balance).ok_or("Overflow")?;
+		Ok(())
+	}
+
+	fn remove_from_tally(tally: &mut Tally<T::Balance>, vote: &VoteOf<T>) -> DispatchResult {
+		let votes = vote.conviction.votes(vote.balance).ok_or("Overflow")?;
+		let side = if vote.aye { &mut tally.ayes } else { &mut tally.nays };
+		*side = side.checked_sub(&votes).ok_or("Underflow")?;
+		tally.turnout = tally.turnout.checked_sub(&vote.balance).ok_or("Underflow")?;
+		Ok(())
+	}
+
+	/// Make the democracy lock on the funds of `who` match the largest amount backing one of its
+	/// votes in an ongoing referendum, or still locked after a finished one.
+	fn update_lock(&mut self, currency: &mut T::Currency, who: &T::AccountId) {
+		let now = self.block_number;
+		if let Some(locks) = self.prior_locks.get_mut(who) {
+			locks.retain(|lock| lock.until > now);
+			if locks.is_empty() {
+				self.prior_locks.remove(who);
+			}
+		}
+		let voting =
+			self.votes.iter().filter(|((_, voter), _)| voter == who).map(|(_, v)| v.balance);
+		let prior = self.prior_locks.get(who).into_iter().flatten().map(|lock| lock.amount);
+		match voting.chain(prior).max() {
+			Some(amount) if !amount.is_zero() => {
+				let reasons = WithdrawReasons::TRANSFER.union(WithdrawReasons::RESERVE);
+				currency.set_lock(DEMOCRACY_ID, who, amount, reasons);
+			},
+			_ => currency.remove_lock(DEMOCRACY_ID, who),
+		}
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, n: T::BlockNumber) {
+		self.block_number = n;
+		self.events.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Conviction, EntryPoint, Event, ReferendumInfo, Vote};
+	use crate::{
+		balances,
+		support::{Currency, DispatchResult, Hooks, RawOrigin, ReservableCurrency, ScheduleCall},
+	};
+
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub struct Test;
+
+	impl super::Config for Test {
+		type RuntimeCall = balances::EntryPoint<Test>;
+		type Currency = balances::Pallet<Test>;
+		type Scheduler = TestScheduler;
+		const MINIMUM_DEPOSIT: u128 = 10;
+		const VOTING_PERIOD: u32 = 10;
+		const ENACTMENT_PERIOD: u32 = 5;
+		const VOTE_LOCKING_PERIOD: u32 = 10;
+		const DEFAULT_MINIMUM_TURNOUT_PERCENT: u32 = 20;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	impl balances::Config for Test {
+		const MAX_TRANSFERS_PER_BATCH: usize = 100;
+	}
+
+	/// A scheduler which only remembers what it was asked to schedule, and has no room left in the
+	/// blocks in `full`.
+	#[derive(Debug, Default)]
+	pub struct TestScheduler {
+		scheduled: Vec<(u32, balances::EntryPoint<Test>, RawOrigin<&'static str>)>,
+		full: Vec<u32>,
+	}
+
+	impl ScheduleCall<u32, balances::EntryPoint<Test>, RawOrigin<&'static str>> for TestScheduler {
+		fn schedule_call(
+			&mut self,
+			when: u32,
+			call: balances::EntryPoint<Test>,
+			origin: RawOrigin<&'static str>,
+		) -> DispatchResult {
+			if self.full.contains(&when) {
+				return Err("Agenda for this block is full")
+			}
+			self.scheduled.push((when, call, origin));
+			Ok(())
+		}
+	}
+
+	fn new_test() -> (super::Pallet<Test>, balances::Pallet<Test>) {
+		let mut balances = balances::Pallet::new();
+		for who in ["alice", "bob", "charlie"] {
+			balances.set_balance(&who, 100);
+		}
+		let mut democracy = super::Pallet::new();
+		democracy.on_initialize(1);
+		(democracy, balances)
+	}
+
+	fn vote(aye: bool, conviction: Conviction, balance: u128) -> Vote<u128> {
+		Vote { aye, conviction, balance }
+	}
+
+	#[test]
+	fn conviction_votes() {
+		assert_eq!(Conviction::None.votes(100u128), Some(10));
+		assert_eq!(Conviction::Locked1x.votes(100u128), Some(100));
+		assert_eq!(Conviction::Locked6x.votes(100u128), Some(600));
+		assert_eq!(Conviction::None.lock_periods(), 0);
+		assert_eq!(Conviction::Locked3x.lock_periods(), 4);
+		assert_eq!(Conviction::Locked6x.lock_periods(), 32);
+	}
+
+	#[test]
+	fn passed_proposal_is_enacted_as_root() {
+		let (mut democracy, mut balances) = new_test();
+		let mut scheduler = TestScheduler::default();
+		let call = balances::EntryPoint::Transfer { to: "dave", amount: 1 };
+
+		assert_eq!(democracy.propose(&mut balances, "alice", call.clone()), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 10);
+
+		// 100 ayes against 90 + 9 nays.
+		assert_eq!(
+			democracy.vote(&mut balances, "bob", 0, vote(true, Conviction::Locked2x, 50)),
+			Ok(())
+		);
+		assert_eq!(
+			democracy.vote(&mut balances, "charlie", 0, vote(false, Conviction::Locked1x, 90)),
+			Ok(())
+		);
+		assert_eq!(
+			democracy.vote(&mut balances, "alice", 0, vote(false, Conviction::None, 90)),
+			Ok(())
+		);
+		assert_eq!(
+			democracy.vote(&mut balances, "alice", 1, vote(true, Conviction::None, 1)),
+			Err("Referendum is not ongoing")
+		);
+		assert_eq!(balances.transfer(&"bob", &"dave", 51), Err("Funds are locked."));
+
+		democracy.on_initialize(11);
+		democracy.end_referenda(&mut balances, &mut scheduler, 11);
+		assert_eq!(democracy.events, vec![Event::Passed { index: 0, enactment: 16 }]);
+		assert!(matches!(
+			scheduler.scheduled.as_slice(),
+			[(16, balances::EntryPoint::Transfer { to: "dave", amount: 1 }, RawOrigin::Root)]
+		));
+		assert!(matches!(
+			democracy.referenda.get(&0),
+			Some(ReferendumInfo::Finished { approved: true, end: 11 })
+		));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		// A finished referendum can not be cancelled, and is kept.
+		assert_eq!(democracy.cancel_referendum(&mut balances, 0), Err("Referendum is not ongoing"));
+		assert!(democracy.referenda.contains_key(&0));
+
+		// Only bob voted with the outcome, so only bob's funds stay locked, for two periods.
+		assert_eq!(balances.transfer(&"charlie", &"dave", 90), Ok(()));
+		assert_eq!(balances.transfer(&"bob", &"dave", 51), Err("Funds are locked."));
+		democracy.on_initialize(31);
+		assert_eq!(democracy.unlock(&mut balances, "bob"), Ok(()));
+		assert_eq!(balances.transfer(&"bob", &"dave", 51), Ok(()));
+	}
+
+	#[test]
+	fn enactment_moves_to_the_next_block_with_room() {
+		// Block 16 is full, so the call is enacted at block 17. Once every block up to
+		// `ENACTMENT_PERIOD` blocks later is full, the enactment fails.
+		let cases = [
+			(vec![16], Event::Passed { index: 0, enactment: 17 }),
+			(
+				(16..=21).collect(),
+				Event::EnactmentFailed { index: 0, error: "Agenda for this block is full" },
+			),
+		];
+		for (full, event) in cases {
+			let (mut democracy, mut balances) = new_test();
+			let mut scheduler = TestScheduler { full, ..Default::default() };
+			let call = balances::EntryPoint::Transfer { to: "dave", amount: 1 };
+			assert_eq!(democracy.propose(&mut balances, "alice", call), Ok(()));
+			assert_eq!(
+				democracy.vote(&mut balances, "bob", 0, vote(true, Conviction::Locked1x, 60)),
+				Ok(())
+			);
+			democracy.on_initialize(11);
+			democracy.end_referenda(&mut balances, &mut scheduler, 11);
+			assert_eq!(democracy.events, vec![event]);
+		}
+	}
+
+	#[test]
+	fn minimum_turnout() {
+		let (mut democracy, mut balances) = new_test();
+		let mut scheduler = TestScheduler::default();
+		let call = balances::EntryPoint::Transfer { to: "dave", amount: 1 };
+		assert_eq!(democracy.propose(&mut balances, "alice", call.clone()), Ok(()));
+		assert_eq!(democracy.propose(&mut balances, "alice", call), Ok(()));
+
+		// 20% of the 300 issued must vote, and conviction does not count.
+		let aye = vote(true, Conviction::Locked6x, 59);
+		assert_eq!(democracy.vote(&mut balances, "bob", 0, aye), Ok(()));
+		assert_eq!(democracy.vote(&mut balances, "bob", 1, aye), Ok(()));
+		assert_eq!(
+			democracy.vote(&mut balances, "charlie", 1, vote(false, Conviction::None, 1)),
+			Ok(())
+		);
+
+		// Only `Root` can change the minimum turnout.
+		let set = |percent| EntryPoint::SetMinimumTurnout(percent);
+		assert_eq!(
+			democracy.dispatch(&mut balances, RawOrigin::Signed("alice"), set(10)),
+			Err("Bad origin: expected root")
+		);
+		assert_eq!(
+			democracy.dispatch(&mut balances, RawOrigin::Root, set(101)),
+			Err("Percentage must not be more than 100")
+		);
+		assert_eq!(democracy.minimum_turnout_percent, 20);
+
+		democracy.on_initialize(11);
+		democracy.end_referenda(&mut balances, &mut scheduler, 11);
+		assert_eq!(
+			democracy.events,
+			vec![Event::NotPassed { index: 0 }, Event::Passed { index: 1, enactment: 16 }]
+		);
+
+		// With a lower minimum, the same votes are enough.
+		let (mut democracy, mut balances) = new_test();
+		let call = balances::EntryPoint::Transfer { to: "dave", amount: 1 };
+		assert_eq!(democracy.dispatch(&mut balances, RawOrigin::Root, set(10)), Ok(()));
+		assert_eq!(democracy.events, vec![Event::MinimumTurnoutSet { percent: 10 }]);
+		assert_eq!(democracy.propose(&mut balances, "alice", call), Ok(()));
+		assert_eq!(democracy.vote(&mut balances, "bob", 0, aye), Ok(()));
+		democracy.on_initialize(11);
+		democracy.end_referenda(&mut balances, &mut scheduler, 11);
+		assert_eq!(democracy.events, vec![Event::Passed { index: 0, enactment: 16 }]);
+	}
+
+	#[test]
+	fn cancel_referendum() {
+		let (mut democracy, mut balances) = new_test();
+		let call = balances::EntryPoint::Transfer { to: "dave", amount: 1 };
+		assert_eq!(democracy.propose(&mut balances, "alice", call), Ok(()));
+		assert_eq!(
+			democracy.vote(&mut balances, "bob", 0, vote(true, Conviction::Locked1x, 50)),
+			Ok(())
+		);
+
+		// Changing a vote replaces it in the tally.
+		assert_eq!(
+			democracy.vote(&mut balances, "bob", 0, vote(false, Conviction::Locked1x, 40)),
+			Ok(())
+		);
+		let Some(ReferendumInfo::Ongoing(status)) = democracy.referenda.get(&0) else {
+			panic!("referendum is ongoing")
+		};
+		assert_eq!((status.tally.ayes, status.tally.nays, status.tally.turnout), (0, 40, 40));
+
+		let cancel = || EntryPoint::CancelReferendum(0);
+		assert_eq!(
+			democracy.dispatch(&mut balances, RawOrigin::Signed("alice"), cancel()),
+			Err("Bad origin: expected root")
+		);
+		assert_eq!(democracy.dispatch(&mut balances, RawOrigin::Root, cancel()), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(balances.free_balance(&"alice"), 100);
+		assert_eq!(balances.transfer(&"bob", &"dave", 100), Ok(()));
+		assert_eq!(
+			democracy.dispatch(&mut balances, RawOrigin::Root, cancel()),
+			Err("Referendum is not ongoing")
+		);
+	}
+}
@@ -0,0 +1,755 @@
+use std::collections::BTreeMap;
+
+use num::{CheckedAdd, CheckedMul, Zero};
+
+use crate::support::{
+	ensure_root, ensure_signed, BalanceStatus, DispatchResult, Encode, Hooks, RawOrigin,
+	ReservableCurrency,
+};
+
+/// The index of a registrar, in the order they were added.
+pub type RegistrarIndex = u32;
+
+pub trait Config: crate::types::Config {
+	/// The currency used to hold identity deposits and pay registrar fees.
+	type Currency: ReservableCurrency<Self::AccountId, Balance = Self::Balance>;
+	/// The amount reserved from an account for as long as it has an identity.
+	const BASIC_DEPOSIT: Self::Balance;
+	/// The amount reserved from an account for each of its sub-accounts.
+	const SUB_ACCOUNT_DEPOSIT: Self::Balance;
+	/// The maximum length in bytes of each field of an identity, and of the name of a sub-account.
+	const MAX_FIELD_LENGTH: usize;
+	/// The maximum number of sub-accounts of a single account.
+	const MAX_SUB_ACCOUNTS: usize;
+	/// The maximum number of registrars.
+	const MAX_REGISTRARS: usize;
+}
+
+/// The information an account gives about itself. Each field is at most
+/// `Config::MAX_FIELD_LENGTH` bytes long, and may be empty.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IdentityInfo {
+	/// The name a user interface should show for the account.
+	pub display: Vec<u8>,
+	pub email: Vec<u8>,
+	pub web: Vec<u8>,
+}
+
+impl Encode for IdentityInfo {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(&self.display, &self.email, &self.web).encode_to(dest)
+	}
+}
+
+/// What a registrar found out about an identity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement<Balance> {
+	/// A judgement was requested, and `Balance` is reserved to pay the registrar's fee.
+	FeePaid(Balance),
+	/// The information looks right, but was not checked thoroughly.
+	Reasonable,
+	/// The information was checked, and is right.
+	KnownGood,
+	/// The information is wrong.
+	Erroneous,
+}
+
+impl<Balance> Judgement<Balance> {
+	/// Whether the judgement stays when the identity is changed. A pending request stays, and so
+	/// does a wrong identity, so that it can not be cleared by changing it.
+	fn is_sticky(&self) -> bool {
+		matches!(self, Judgement::FeePaid(_) | Judgement::Erroneous)
+	}
+}
+
+impl<Balance: Encode> Encode for Judgement<Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Judgement::FeePaid(fee) => (0u8, fee).encode_to(dest),
+			Judgement::Reasonable => 1u8.encode_to(dest),
+			Judgement::KnownGood => 2u8.encode_to(dest),
+			Judgement::Erroneous => 3u8.encode_to(dest),
+		}
+	}
+}
+
+/// The identity of an account, and the judgements of the registrars about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registration<Balance> {
+	pub info: IdentityInfo,
+	/// The deposit reserved from the account for this identity.
+	pub deposit: Balance,
+	/// At most one judgement per registrar, in the order they were requested or given.
+	pub judgements: Vec<(RegistrarIndex, Judgement<Balance>)>,
+}
+
+impl<Balance> Registration<Balance> {
+	/// Whether a registrar found the identity to be right, and none found it to be wrong.
+	pub fn is_verified(&self) -> bool {
+		let mut judgements = self.judgements.iter().map(|(_, judgement)| judgement);
+		judgements.clone().any(|j| matches!(j, Judgement::Reasonable | Judgement::KnownGood)) &&
+			!judgements.any(|j| matches!(j, Judgement::Erroneous))
+	}
+}
+
+/// An account which can judge identities, for a fee.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistrarInfo<AccountId, Balance> {
+	pub account: AccountId,
+	/// The fee paid to the registrar for each judgement it is asked for.
+	pub fee: Balance,
+}
+
+/// The `Registration` stored by a runtime configured with `T`.
+pub type RegistrationOf<T> = Registration<<T as crate::types::Config>::Balance>;
+
+/// The `RegistrarInfo` stored by a runtime configured with `T`.
+pub type RegistrarInfoOf<T> =
+	RegistrarInfo<<T as crate::types::Config>::AccountId, <T as crate::types::Config>::Balance>;
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// `who` set or changed its identity.
+	IdentitySet { who: T::AccountId },
+	/// `who` cleared its identity, and got its deposits back.
+	IdentityCleared { who: T::AccountId },
+	/// The identity of `who` was removed by `Root`, and its deposits slashed.
+	IdentityKilled { who: T::AccountId },
+	/// `who` asked the registrar `registrar_index` for a judgement.
+	JudgementRequested { who: T::AccountId, registrar_index: RegistrarIndex },
+	/// `who` withdrew its request for a judgement from the registrar `registrar_index`.
+	JudgementUnrequested { who: T::AccountId, registrar_index: RegistrarIndex },
+	/// The registrar `registrar_index` judged the identity of `target`.
+	JudgementGiven { target: T::AccountId, registrar_index: RegistrarIndex },
+	/// `Root` added the registrar `registrar_index`.
+	RegistrarAdded { registrar_index: RegistrarIndex },
+	/// `main` linked the sub-account `sub` to its identity.
+	SubIdentityAdded { sub: T::AccountId, main: T::AccountId },
+	/// `sub` stopped being a sub-account of `main`.
+	SubIdentityRemoved { sub: T::AccountId, main: T::AccountId },
+}
+
+/// This is the Identity Module.
+/// It lets accounts describe themselves, and registrars added by `Root` check these descriptions
+/// for a fee, so that user interfaces can show verified names rather than raw accounts.
+///
+/// An account with an identity can also link sub-accounts to it, each with its own name.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The identity of each account which has one.
+	pub identities: BTreeMap<T::AccountId, RegistrationOf<T>>,
+	/// The registrars, indexed by `RegistrarIndex`.
+	pub registrars: Vec<RegistrarInfoOf<T>>,
+	/// The account each sub-account belongs to, and the name of the sub-account.
+	pub super_of: BTreeMap<T::AccountId, (T::AccountId, Vec<u8>)>,
+	/// The sub-accounts of each account which has some.
+	pub subs_of: BTreeMap<T::AccountId, Vec<T::AccountId>>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	AddRegistrar(T::AccountId),
+	SetFee { registrar_index: RegistrarIndex, fee: T::Balance },
+	SetIdentity(IdentityInfo),
+	ClearIdentity,
+	RequestJudgement { registrar_index: RegistrarIndex, max_fee: T::Balance },
+	CancelRequest(RegistrarIndex),
+	ProvideJudgement {
+		registrar_index: RegistrarIndex,
+		target: T::AccountId,
+		judgement: Judgement<T::Balance>,
+	},
+	AddSub { sub: T::AccountId, name: Vec<u8> },
+	RemoveSub(T::AccountId),
+	QuitSub,
+	KillIdentity(T::AccountId),
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::AddRegistrar(account) => (0u8, account).encode_to(dest),
+			EntryPoint::SetFee { registrar_index, fee } => {
+				(1u8, registrar_index, fee).encode_to(dest)
+			},
+			EntryPoint::SetIdentity(info) => (2u8, info).encode_to(dest),
+			EntryPoint::ClearIdentity => 3u8.encode_to(dest),
+			EntryPoint::RequestJudgement { registrar_index, max_fee } => {
+				(4u8, registrar_index, max_fee).encode_to(dest)
+			},
+			EntryPoint::CancelRequest(registrar_index) => (5u8, registrar_index).encode_to(dest),
+			EntryPoint::ProvideJudgement { registrar_index, target, judgement } => {
+				(6u8, registrar_index, target, judgement).encode_to(dest)
+			},
+			EntryPoint::AddSub { sub, name } => (7u8, sub, name).encode_to(dest),
+			EntryPoint::RemoveSub(sub) => (8u8, sub).encode_to(dest),
+			EntryPoint::QuitSub => 9u8.encode_to(dest),
+			EntryPoint::KillIdentity(target) => (10u8, target).encode_to(dest),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Identity Module.
+	pub fn new() -> Self {
+		Self {
+			identities: BTreeMap::new(),
+			registrars: Vec::new(),
+			super_of: BTreeMap::new(),
+			subs_of: BTreeMap::new(),
+			events: Vec::new(),
+		}
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
+	}
+
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	///
+	/// Only `Root` can add registrars and kill identities. Every other call must be signed.
+	pub fn dispatch(
+		&mut self,
+		currency: &mut T::Currency,
+		origin: RawOrigin<T::AccountId>,
+		call: EntryPoint<T>,
+	) -> DispatchResult {
+		match call {
+			EntryPoint::AddRegistrar(account) => {
+				ensure_root(origin)?;
+				self.add_registrar(account)?
+			},
+			EntryPoint::SetFee { registrar_index, fee } => {
+				self.set_fee(ensure_signed(origin)?, registrar_index, fee)?
+			},
+			EntryPoint::SetIdentity(info) => {
+				self.set_identity(currency, ensure_signed(origin)?, info)?
+			},
+			EntryPoint::ClearIdentity => self.clear_identity(currency, ensure_signed(origin)?)?,
+			EntryPoint::RequestJudgement { registrar_index, max_fee } => {
+				self.request_judgement(currency, ensure_signed(origin)?, registrar_index, max_fee)?
+			},
+			EntryPoint::CancelRequest(registrar_index) => {
+				self.cancel_request(currency, ensure_signed(origin)?, registrar_index)?
+			},
+			EntryPoint::ProvideJudgement { registrar_index, target, judgement } => {
+				let caller = ensure_signed(origin)?;
+				self.provide_judgement(currency, caller, registrar_index, target, judgement)?
+			},
+			EntryPoint::AddSub { sub, name } => {
+				self.add_sub(currency, ensure_signed(origin)?, sub, name)?
+			},
+			EntryPoint::RemoveSub(sub) => self.remove_sub(currency, ensure_signed(origin)?, sub)?,
+			EntryPoint::QuitSub => self.quit_sub(currency, ensure_signed(origin)?)?,
+			EntryPoint::KillIdentity(target) => {
+				ensure_root(origin)?;
+				self.kill_identity(currency, target)?
+			},
+		}
+		Ok(())
+	}
+
+	/// Get the name a user interface should show for `who`, if its identity was verified by a
+	/// registrar. The name of a sub-account is the name of its parent followed by its own, like
+	/// `alice/savings`.
+	pub fn display_name(&self, who: &T::AccountId) -> Option<String> {
+		if let Some((parent, name)) = self.super_of.get(who) {
+			let parent_name = self.display_name(parent)?;
+			return Some(format!("{}/{}", parent_name, String::from_utf8_lossy(name)))
+		}
+		let registration = self.identities.get(who).filter(|r| r.is_verified())?;
+		Some(String::from_utf8_lossy(&registration.info.display).into_owned())
+	}
+
+	/// Add `account` as a registrar, which charges no fee until it sets one.
+	pub fn add_registrar(&mut self, account: T::AccountId) -> DispatchResult {
+		if self.registrars.len() >= T::MAX_REGISTRARS {
+			return Err("Too many registrars")
+		}
+		let registrar_index = self.registrars.len() as RegistrarIndex;
+		self.registrars.push(RegistrarInfo { account, fee: T::Balance::zero() });
+		self.deposit_event(Event::RegistrarAdded { registrar_index });
+		Ok(())
+	}
+
+	/// Set the fee of the registrar `registrar_index`, which must be the `caller`.
+	pub fn set_fee(
+		&mut self,
+		caller: T::AccountId,
+		registrar_index: RegistrarIndex,
+		fee: T::Balance,
+	) -> DispatchResult {
+		let registrar = self.registrar_mut(registrar_index, &caller)?;
+		registrar.fee = fee;
+		Ok(())
+	}
+
+	/// Set the identity of the `caller`, reserving `T::BASIC_DEPOSIT` if it had none. Changing an
+	/// identity drops the judgements given about it, except for `Erroneous` ones.
+	pub fn set_identity(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		info: IdentityInfo,
+	) -> DispatchResult {
+		let fields = [&info.display, &info.email, &info.web];
+		if fields.iter().any(|field| field.len() > T::MAX_FIELD_LENGTH) {
+			return Err("Identity field is too long")
+		}
+		if self.super_of.contains_key(&caller) {
+			return Err("Account is a sub-account")
+		}
+
+		match self.identities.get_mut(&caller) {
+			Some(registration) => {
+				registration.info = info;
+				registration.judgements.retain(|(_, judgement)| judgement.is_sticky());
+			},
+			None => {
+				currency.reserve(&caller, T::BASIC_DEPOSIT)?;
+				let registration =
+					Registration { info, deposit: T::BASIC_DEPOSIT, judgements: Vec::new() };
+				self.identities.insert(caller.clone(), registration);
+			},
+		}
+		self.deposit_event(Event::IdentitySet { who: caller });
+		Ok(())
+	}
+
+	/// Remove the identity and the sub-accounts of the `caller`, and give back its deposits and the
+	/// fees of its pending judgement requests.
+	pub fn clear_identity(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+	) -> DispatchResult {
+		let (deposit, fees) = self.take_identity(&caller)?;
+		currency.unreserve(&caller, deposit);
+		currency.unreserve(&caller, fees);
+		self.deposit_event(Event::IdentityCleared { who: caller });
+		Ok(())
+	}
+
+	/// Remove the identity and the sub-accounts of `target`, slashing its deposits. The fees of its
+	/// pending judgement requests are given back.
+	pub fn kill_identity(
+		&mut self,
+		currency: &mut T::Currency,
+		target: T::AccountId,
+	) -> DispatchResult {
+		let (deposit, fees) = self.take_identity(&target)?;
+		currency.slash_reserved(&target, deposit);
+		currency.unreserve(&target, fees);
+		self.deposit_event(Event::IdentityKilled { who: target });
+		Ok(())
+	}
+
+	/// Ask the registrar `registrar_index` to judge the identity of the `caller`, reserving its fee
+	/// until the judgement is given. The request fails if the fee is more than `max_fee`, so that
+	/// the registrar can not raise it in the meantime.
+	pub fn request_judgement(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		registrar_index: RegistrarIndex,
+		max_fee: T::Balance,
+	) -> DispatchResult {
+		let registrar = self
+			.registrars
+			.get(registrar_index as usize)
+			.ok_or("Registrar does not exist")?;
+		if registrar.fee > max_fee {
+			return Err("Registrar fee is higher than the maximum")
+		}
+		let registration = self.identities.get_mut(&caller).ok_or("Account has no identity")?;
+		let existing =
+			registration.judgements.iter().position(|(index, _)| *index == registrar_index);
+		if existing.is_some_and(|position| registration.judgements[position].1.is_sticky()) {
+			return Err("Judgement can not be requested again")
+		}
+		currency.reserve(&caller, registrar.fee)?;
+
+		let judgement = Judgement::FeePaid(registrar.fee);
+		match existing {
+			Some(position) => registration.judgements[position].1 = judgement,
+			None => registration.judgements.push((registrar_index, judgement)),
+		}
+		self.deposit_event(Event::JudgementRequested { who: caller, registrar_index });
+		Ok(())
+	}
+
+	/// Withdraw the request of the `caller` for a judgement from the registrar `registrar_index`,
+	/// and get its fee back.
+	pub fn cancel_request(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		registrar_index: RegistrarIndex,
+	) -> DispatchResult {
+		let registration = self.identities.get_mut(&caller).ok_or("Account has no identity")?;
+		let position = registration
+			.judgements
+			.iter()
+			.position(|(index, judgement)| {
+				*index == registrar_index && matches!(judgement, Judgement::FeePaid(_))
+			})
+			.ok_or("No pending judgement request")?;
+		if let (_, Judgement::FeePaid(fee)) = registration.judgements.remove(position) {
+			currency.unreserve(&caller, fee);
+		}
+		self.deposit_event(Event::JudgementUnrequested { who: caller, registrar_index });
+		Ok(())
+	}
+
+	/// Give the `judgement` of the registrar `registrar_index`, which must be the `caller`, about
+	/// the identity of `target`. If `target` requested it, the fee it reserved is paid to the
+	/// registrar.
+	pub fn provide_judgement(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		registrar_index: RegistrarIndex,
+		target: T::AccountId,
+		judgement: Judgement<T::Balance>,
+	) -> DispatchResult {
+		self.registrar_mut(registrar_index, &caller)?;
+		if matches!(judgement, Judgement::FeePaid(_)) {
+			return Err("Invalid judgement")
+		}
+		let registration = self.identities.get_mut(&target).ok_or("Account has no identity")?;
+		match registration.judgements.iter_mut().find(|(index, _)| *index == registrar_index) {
+			Some((_, existing)) => {
+				if let Judgement::FeePaid(fee) = *existing {
+					currency.repatriate_reserved(&target, &caller, fee, BalanceStatus::Free)?;
+				}
+				*existing = judgement;
+			},
+			None => registration.judgements.push((registrar_index, judgement)),
+		}
+		self.deposit_event(Event::JudgementGiven { target, registrar_index });
+		Ok(())
+	}
+
+	/// Link `sub` to the identity of the `caller` under `name`, reserving
+	/// `T::SUB_ACCOUNT_DEPOSIT` from the `caller`.
+	pub fn add_sub(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		sub: T::AccountId,
+		name: Vec<u8>,
+	) -> DispatchResult {
+		if name.len() > T::MAX_FIELD_LENGTH {
+			return Err("Identity field is too long")
+		}
+		if !self.identities.contains_key(&caller) {
+			return Err("Account has no identity")
+		}
+		if sub == caller || self.identities.contains_key(&sub) {
+			return Err("Sub-account already has an identity")
+		}
+		if self.super_of.contains_key(&sub) {
+			return Err("Account is already a sub-account")
+		}
+		if self.subs_of.get(&caller).map(Vec::len).unwrap_or(0) >= T::MAX_SUB_ACCOUNTS {
+			return Err("Too many sub-accounts")
+		}
+		currency.reserve(&caller, T::SUB_ACCOUNT_DEPOSIT)?;
+
+		self.subs_of.entry(caller.clone()).or_default().push(sub.clone());
+		self.super_of.insert(sub.clone(), (caller.clone(), name));
+		self.deposit_event(Event::SubIdentityAdded { sub, main: caller });
+		Ok(())
+	}
+
+	/// Unlink the sub-account `sub` from the identity of the `caller`, and give back its deposit.
+	pub fn remove_sub(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		sub: T::AccountId,
+	) -> DispatchResult {
+		match self.super_of.get(&sub) {
+			Some((main, _)) if *main == caller => {},
+			_ => return Err("Account is not a sub-account of the caller"),
+		}
+		self.unlink_sub(&caller, &sub);
+		currency.unreserve(&caller, T::SUB_ACCOUNT_DEPOSIT);
+		self.deposit_event(Event::SubIdentityRemoved { sub, main: caller });
+		Ok(())
+	}
+
+	/// Unlink the `caller` from the identity it is a sub-account of. The deposit of the
+	/// sub-account goes to the `caller`.
+	pub fn quit_sub(&mut self, currency: &mut T::Currency, caller: T::AccountId) -> DispatchResult {
+		let (main, _) =
+			self.super_of.get(&caller).cloned().ok_or("Account is not a sub-account")?;
+		currency.repatriate_reserved(
+			&main,
+			&caller,
+			T::SUB_ACCOUNT_DEPOSIT,
+			BalanceStatus::Free,
+		)?;
+		self.unlink_sub(&main, &caller);
+		self.deposit_event(Event::SubIdentityRemoved { sub: caller, main });
+		Ok(())
+	}
+
+	fn unlink_sub(&mut self, main: &T::AccountId, sub: &T::AccountId) {
+		self.super_of.remove(sub);
+		if let Some(subs) = self.subs_of.get_mut(main) {
+			subs.retain(|s| s != sub);
+			if subs.is_empty() {
+				self.subs_of.remove(main);
+			}
+		}
+	}
+
+	/// Get the registrar `registrar_index`, checking that its account is `caller`.
+	fn registrar_mut(
+		&mut self,
+		registrar_index: RegistrarIndex,
+		caller: &T::AccountId,
+	) -> Result<&mut RegistrarInfoOf<T>, &'static str> {
+		let registrar = self
+			.registrars
+			.get_mut(registrar_index as usize)
+			.ok_or("Registrar does not exist")?;
+		if registrar.account != *caller {
+			return Err("Caller is not the registrar")
+		}
+		Ok(registrar)
+	}
+
+	/// Remove the identity and the sub-accounts of `who`. Returns the deposits reserved for them,
+	/// and the fees reserved for pending judgement requests.
+	fn take_identity(
+		&mut self,
+		who: &T::AccountId,
+	) -> Result<(T::Balance, T::Balance), &'static str> {
+		let registration = self.identities.get(who).ok_or("Account has no identity")?;
+		let sub_count = self.subs_of.get(who).map(Vec::len).unwrap_or(0) as u32;
+		let deposit = T::SUB_ACCOUNT_DEPOSIT
+			.checked_mul(&T::Balance::from(sub_count))
+			.and_then(|subs| subs.checked_add(&registration.deposit))
+			.ok_or("Overflow")?;
+		let mut fees = T::Balance::zero();
+		for (_, judgement) in &registration.judgements {
+			if let Judgement::FeePaid(fee) = judgement {
+				fees = fees.checked_add(fee).ok_or("Overflow")?;
+			}
+		}
+
+		self.identities.remove(who);
+		for sub in self.subs_of.remove(who).unwrap_or_default() {
+			self.super_of.remove(&sub);
+		}
+		Ok((deposit, fees))
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, _n: T::BlockNumber) {
+		self.events.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{EntryPoint, Event, IdentityInfo, Judgement};
+	use crate::{
+		mock::MockCurrency,
+		support::{RawOrigin, ReservableCurrency},
+	};
+
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub struct Test;
+
+	impl super::Config for Test {
+		type Currency = MockCurrency<&'static str>;
+		const BASIC_DEPOSIT: u128 = 10;
+		const SUB_ACCOUNT_DEPOSIT: u128 = 2;
+		const MAX_FIELD_LENGTH: usize = 8;
+		const MAX_SUB_ACCOUNTS: usize = 2;
+		const MAX_REGISTRARS: usize = 2;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	fn new_test() -> (super::Pallet<Test>, MockCurrency<&'static str>) {
+		let mut balances = MockCurrency::new();
+		for who in ["alice", "bob", "registrar"] {
+			balances.set_balance(&who, 100);
+		}
+		let mut identity = super::Pallet::new();
+		assert_eq!(
+			identity.dispatch(
+				&mut balances,
+				RawOrigin::Signed("alice"),
+				EntryPoint::AddRegistrar("registrar")
+			),
+			Err("Bad origin: expected root")
+		);
+		assert_eq!(
+			identity.dispatch(
+				&mut balances,
+				RawOrigin::Root,
+				EntryPoint::AddRegistrar("registrar")
+			),
+			Ok(())
+		);
+		assert_eq!(identity.set_fee("registrar", 0, 5), Ok(()));
+		(identity, balances)
+	}
+
+	fn info(display: &str) -> IdentityInfo {
+		IdentityInfo { display: display.into(), email: b"a@b.c".to_vec(), web: Vec::new() }
+	}
+
+	#[test]
+	fn judgements_verify_identities() {
+		let (mut identity, mut balances) = new_test();
+
+		assert_eq!(
+			identity.set_identity(&mut balances, "alice", info("Alice in Wonderland")),
+			Err("Identity field is too long")
+		);
+		assert_eq!(identity.set_identity(&mut balances, "alice", info("Alice")), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 10);
+		assert_eq!(identity.display_name(&"alice"), None);
+
+		assert_eq!(
+			identity.request_judgement(&mut balances, "alice", 0, 4),
+			Err("Registrar fee is higher than the maximum")
+		);
+		assert_eq!(identity.request_judgement(&mut balances, "alice", 0, 5), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 15);
+		assert_eq!(
+			identity.provide_judgement(&mut balances, "bob", 0, "alice", Judgement::KnownGood),
+			Err("Caller is not the registrar")
+		);
+		assert_eq!(
+			identity.provide_judgement(
+				&mut balances,
+				"registrar",
+				0,
+				"alice",
+				Judgement::KnownGood
+			),
+			Ok(())
+		);
+		// The fee was paid to the registrar.
+		assert_eq!(balances.reserved_balance(&"alice"), 10);
+		assert_eq!(balances.balance(&"registrar"), 105);
+		assert_eq!(identity.display_name(&"alice"), Some("Alice".to_string()));
+
+		// Changing the identity drops the judgement, but an erroneous one sticks.
+		assert_eq!(identity.set_identity(&mut balances, "alice", info("Alicia")), Ok(()));
+		assert_eq!(identity.display_name(&"alice"), None);
+		assert_eq!(
+			identity.provide_judgement(
+				&mut balances,
+				"registrar",
+				0,
+				"alice",
+				Judgement::Erroneous
+			),
+			Ok(())
+		);
+		assert_eq!(identity.set_identity(&mut balances, "alice", info("Alice")), Ok(()));
+		assert_eq!(
+			identity.request_judgement(&mut balances, "alice", 0, 5),
+			Err("Judgement can not be requested again")
+		);
+
+		// No other registrar can outweigh it.
+		assert_eq!(identity.add_registrar("bob"), Ok(()));
+		let judgement = Judgement::KnownGood;
+		assert_eq!(identity.provide_judgement(&mut balances, "bob", 1, "alice", judgement), Ok(()));
+		assert_eq!(identity.identities[&"alice"].judgements.len(), 2);
+		assert_eq!(identity.display_name(&"alice"), None);
+	}
+
+	#[test]
+	fn killed_identities_lose_their_deposits() {
+		let (mut identity, mut balances) = new_test();
+		assert_eq!(identity.set_identity(&mut balances, "alice", info("Alice")), Ok(()));
+		assert_eq!(identity.add_sub(&mut balances, "alice", "bob", b"savings".to_vec()), Ok(()));
+		assert_eq!(identity.request_judgement(&mut balances, "alice", 0, 5), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 10 + 2 + 5);
+
+		let kill = || EntryPoint::KillIdentity("alice");
+		assert_eq!(
+			identity.dispatch(&mut balances, RawOrigin::Signed("bob"), kill()),
+			Err("Bad origin: expected root")
+		);
+		assert_eq!(identity.dispatch(&mut balances, RawOrigin::Root, kill()), Ok(()));
+		assert_eq!(identity.events.last(), Some(&Event::IdentityKilled { who: "alice" }));
+
+		// The deposits are gone, but the fee of the pending request comes back.
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(balances.balance(&"alice"), 100 - 10 - 2);
+		assert!(identity.identities.is_empty() && identity.super_of.is_empty());
+		assert_eq!(
+			identity.dispatch(&mut balances, RawOrigin::Root, kill()),
+			Err("Account has no identity")
+		);
+	}
+
+	#[test]
+	fn sub_accounts() {
+		let (mut identity, mut balances) = new_test();
+
+		assert_eq!(
+			identity.add_sub(&mut balances, "alice", "bob", b"savings".to_vec()),
+			Err("Account has no identity")
+		);
+		assert_eq!(identity.set_identity(&mut balances, "alice", info("Alice")), Ok(()));
+		assert_eq!(identity.add_sub(&mut balances, "alice", "bob", b"savings".to_vec()), Ok(()));
+		assert_eq!(identity.add_sub(&mut balances, "alice", "charlie", b"cold".to_vec()), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 14);
+		assert_eq!(
+			identity.set_identity(&mut balances, "bob", info("Bob")),
+			Err("Account is a sub-account")
+		);
+
+		// A sub-account is named after its verified parent.
+		assert_eq!(identity.display_name(&"bob"), None);
+		assert_eq!(
+			identity.provide_judgement(
+				&mut balances,
+				"registrar",
+				0,
+				"alice",
+				Judgement::Reasonable
+			),
+			Ok(())
+		);
+		assert_eq!(identity.display_name(&"bob"), Some("Alice/savings".to_string()));
+
+		// Quitting gives the deposit to the sub-account.
+		assert_eq!(identity.quit_sub(&mut balances, "bob"), Ok(()));
+		assert_eq!(balances.balance(&"bob"), 102);
+		assert_eq!(identity.display_name(&"bob"), None);
+		assert_eq!(identity.subs_of[&"alice"].len(), 1);
+		assert_eq!(identity.quit_sub(&mut balances, "bob"), Err("Account is not a sub-account"));
+
+		// Clearing the identity gives back the remaining deposits, and unlinks the sub-accounts.
+		assert_eq!(identity.clear_identity(&mut balances, "alice"), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(balances.balance(&"alice"), 98);
+		assert!(identity.super_of.is_empty());
+	}
+}
@@ -1,31 +1,150 @@
+mod assets;
 mod balances;
+mod democracy;
+mod hashing;
+mod identity;
+mod merkle;
+#[cfg(test)]
+mod mock;
+mod multisig;
+mod nfts;
 mod proof_of_existence;
+mod proxy;
+mod scheduler;
+mod staking;
 mod support;
 mod system;
+mod timestamp;
+mod treasury;
 mod types;
+mod utility;
+mod vesting;
 
-use crate::support::Dispatch;
+use crate::{
+	hashing::Hasher,
+	support::{
+		ensure_signed, ChargeFee, Currency, Dispatch, Hooks, OnTimestampSet, Transactional,
+		WithdrawReasons,
+	},
+};
 use std::fmt::Debug;
 use types::*;
 
+/// The flat fee paid by every signed extrinsic, whether or not its call succeeds.
+const TRANSACTION_FEE: Balance = 2;
+/// The percentage of each transaction fee which goes to the treasury. The rest is burned.
+const TREASURY_FEE_PERCENT: Balance = 50;
+
 // This is our main Runtime.
 // It accumulates all of the different pallets we want to use.
-#[derive(Debug)]
+// It is never cloned, but the derived `Clone` of the calls of each pallet requires it.
+#[derive(Debug, Clone)]
 pub struct Runtime {
 	system: system::Pallet<Self>,
+	timestamp: timestamp::Pallet<Self>,
 	balances: balances::Pallet<Self>,
-	proof: proof_of_existence::Pallet<Self>
+	proof: proof_of_existence::Pallet<Self>,
+	assets: assets::Pallet<Self>,
+	vesting: vesting::Pallet<Self>,
+	utility: utility::Pallet<Self>,
+	multisig: multisig::Pallet<Self>,
+	proxy: proxy::Pallet<Self>,
+	scheduler: scheduler::Pallet<Self>,
+	democracy: democracy::Pallet<Self>,
+	treasury: treasury::Pallet<Self>,
+	staking: staking::Pallet<Self>,
+	identity: identity::Pallet<Self>,
+	nfts: nfts::Pallet<Self>,
+	/// The open transactions, innermost last. See `Transactional`.
+	transactions: Vec<Snapshot>,
+}
+
+/// Declares `Snapshot`, which holds a copy of each pallet a call can change, as it was before the
+/// innermost open transaction first touched it.
+macro_rules! snapshot {
+	($($pallet:ident: $type:ty),+ $(,)?) => {
+		#[derive(Debug, Clone, Default)]
+		struct Snapshot {
+			$($pallet: Option<$type>,)+
+		}
+
+		impl Snapshot {
+			/// Hand the copies of this committed transaction to the transaction around it, which
+			/// keeps its own older copy of a pallet if it has one.
+			fn merge_into(self, outer: &mut Snapshot) {
+				$(if outer.$pallet.is_none() {
+					outer.$pallet = self.$pallet;
+				})+
+			}
+
+			/// Put every pallet touched by a rolled back transaction back the way it was.
+			fn restore(self, runtime: &mut Runtime) {
+				$(if let Some(pallet) = self.$pallet {
+					runtime.$pallet = pallet;
+				})+
+			}
+		}
+	};
+}
+
+snapshot! {
+	balances: balances::Pallet<Runtime>,
+	proof: proof_of_existence::Pallet<Runtime>,
+	assets: assets::Pallet<Runtime>,
+	vesting: vesting::Pallet<Runtime>,
+	utility: utility::Pallet<Runtime>,
+	multisig: multisig::Pallet<Runtime>,
+	proxy: proxy::Pallet<Runtime>,
+	scheduler: scheduler::Pallet<Runtime>,
+	democracy: democracy::Pallet<Runtime>,
+	treasury: treasury::Pallet<Runtime>,
+	staking: staking::Pallet<Runtime>,
+	identity: identity::Pallet<Runtime>,
+	nfts: nfts::Pallet<Runtime>,
+}
+
+/// Copy each of the given pallets of `$runtime` into the innermost open transaction, unless it
+/// already holds a copy, so that the transaction can roll them back. Every pallet a call can
+/// change must be touched before it is handed out.
+macro_rules! touch {
+	($runtime:expr, $($pallet:ident),+) => {
+		if let Some(snapshot) = $runtime.transactions.last_mut() {
+			$(if snapshot.$pallet.is_none() {
+				snapshot.$pallet = Some($runtime.$pallet.clone());
+			})+
+		}
+	};
 }
 
 // These are all the calls which are exposed to the world.
 // Note that it is just an accumulation of the calls exposed by each module.
+#[derive(Debug, Clone)]
 pub enum RuntimeCall {
 	// BalancesTransfer { to: types::AccountId, amount: types::Balance },
 	/// makes use of and outer and inner enum generic over ```T:Config```
 	Balances(balances::EntryPoint<Runtime>),
 	ProofOfExistence(proof_of_existence::EntryPoint<Runtime>),
+	Assets(assets::EntryPoint<Runtime>),
+	Vesting(vesting::EntryPoint<Runtime>),
+	Utility(utility::EntryPoint<Runtime>),
+	Multisig(multisig::EntryPoint<Runtime>),
+	Proxy(proxy::EntryPoint<Runtime>),
+	Scheduler(scheduler::EntryPoint<Runtime>),
+	Democracy(democracy::EntryPoint<Runtime>),
+	Treasury(treasury::EntryPoint<Runtime>),
+	Staking(staking::EntryPoint<Runtime>),
+	Identity(identity::EntryPoint<Runtime>),
+	Nfts(nfts::EntryPoint<Runtime>),
+
+}
 
+// These are all the inherents a block author can put in a block.
+// Unlike the calls above, they have no caller.
+#[derive(Debug, Clone)]
+pub enum RuntimeInherent {
+	Timestamp(timestamp::EntryPoint<Runtime>),
 }
+
 impl Signer<String> for Runtime {
 	
 }
@@ -37,12 +156,274 @@ impl Config for Runtime {
 	type Balance = u128;
 }
 
+impl balances::Config for Runtime {
+	const MAX_TRANSFERS_PER_BATCH: usize = 100;
+}
+
+impl timestamp::Config for Runtime {
+	type Moment = Moment;
+	const MINIMUM_PERIOD: Moment = 3_000;
+}
+
 impl proof_of_existence::Config for Runtime {
+	type Hashing = hashing::Sha256;
 	type Content = types::Content;
+	const MAX_MEMO_LENGTH: usize = 256;
+	const MAX_CLAIMS_PER_ACCOUNT: usize = 1_000;
+	type Currency = balances::Pallet<Self>;
+	const CLAIM_DEPOSIT: Balance = 5;
+	type Moment = Moment;
+}
+
+impl assets::Config for Runtime {
+	type AssetId = u32;
+	const STRING_LIMIT: usize = 50;
+}
+
+impl vesting::Config for Runtime {
+	type Currency = balances::Pallet<Self>;
+	type BlockNumberToBalance = support::ConvertInto;
+	const MIN_VESTED_TRANSFER: Balance = 10;
+	type Moment = Moment;
+}
+
+impl utility::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	const MAX_CALLS: usize = 100;
+	const MAX_NESTING_DEPTH: u32 = 4;
+}
+
+impl AsMut<utility::Pallet<Runtime>> for Runtime {
+	fn as_mut(&mut self) -> &mut utility::Pallet<Runtime> {
+		touch!(self, utility);
+		&mut self.utility
+	}
+}
+
+impl multisig::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	type Hashing = hashing::Sha256;
+	type Currency = balances::Pallet<Self>;
+	type AccountIdFromHash = types::AccountIdFromHash;
+	const DEPOSIT: Balance = 10;
+	const MAX_SIGNATORIES: usize = 16;
+}
+
+impl AsMut<multisig::Pallet<Runtime>> for Runtime {
+	fn as_mut(&mut self) -> &mut multisig::Pallet<Runtime> {
+		touch!(self, multisig);
+		&mut self.multisig
+	}
+}
+
+/// The kinds of proxy an account can register with the proxy pallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyType {
+	/// Allows every call.
+	Any,
+	/// Allows every call which can not move funds out of the account.
+	NonTransfer,
+	/// Only allows calls to the proof of existence pallet.
+	ProofOfExistenceOnly,
+}
+
+impl support::Encode for ProxyType {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(*self as u8).encode_to(dest)
+	}
+}
+
+impl support::InstanceFilter<RuntimeCall> for ProxyType {
+	fn filter(&self, call: &RuntimeCall) -> bool {
+		// A batch is only allowed if every call in it is.
+		if let RuntimeCall::Utility(
+			utility::EntryPoint::Batch(calls) |
+			utility::EntryPoint::BatchAll(calls) |
+			utility::EntryPoint::ForceBatch(calls),
+		) = call
+		{
+			return calls.iter().all(|call| self.filter(call))
+		}
+		match self {
+			ProxyType::Any => true,
+			// Giving away a claim, an item or a collection is a transfer too, and so is minting an
+			// item, which can go to any account, or burning one. Minting a claim is fine, since the
+			// item and its deposit stay with the account. Reserving or locking funds in a proposal,
+			// a vote or a bond, or paying a registrar for a judgement, is a transfer as well. So is
+			// reserving the deposit of an identity or of a sub-account, which the sub-account can
+			// take by quitting. Proxy, multisig and scheduler calls are not allowed either, so that
+			// the proxy can not register itself with more rights, move funds from a shared
+			// account, or schedule a transfer to be made later.
+			ProxyType::NonTransfer => !matches!(
+				call,
+				RuntimeCall::Balances(_) |
+					RuntimeCall::ProofOfExistence(
+						proof_of_existence::EntryPoint::TransferClaim { .. } |
+							proof_of_existence::EntryPoint::OfferClaim { .. }
+					) | RuntimeCall::Assets(assets::EntryPoint::Transfer { .. }) |
+					RuntimeCall::Vesting(vesting::EntryPoint::VestedTransfer { .. }) |
+					RuntimeCall::Nfts(
+						nfts::EntryPoint::Mint { .. } |
+							nfts::EntryPoint::Transfer { .. } |
+							nfts::EntryPoint::ApproveTransfer { .. } |
+							nfts::EntryPoint::TransferOwnership { .. } |
+							nfts::EntryPoint::Burn { .. }
+					) |
+					RuntimeCall::Multisig(_) |
+					RuntimeCall::Proxy(_) |
+					RuntimeCall::Scheduler(_) |
+					RuntimeCall::Democracy(
+						democracy::EntryPoint::Propose(_) | democracy::EntryPoint::Vote { .. }
+					) | RuntimeCall::Treasury(treasury::EntryPoint::ProposeSpend { .. }) |
+					RuntimeCall::Staking(
+						staking::EntryPoint::Bond(_) | staking::EntryPoint::BondExtra(_)
+					) | RuntimeCall::Identity(
+						identity::EntryPoint::SetIdentity(_) |
+							identity::EntryPoint::AddSub { .. } |
+							identity::EntryPoint::RequestJudgement { .. }
+					)
+			),
+			ProxyType::ProofOfExistenceOnly => matches!(call, RuntimeCall::ProofOfExistence(_)),
+		}
+	}
+}
+
+impl proxy::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	type ProxyType = ProxyType;
+	type Hashing = hashing::Sha256;
+	type Currency = balances::Pallet<Self>;
+	const PROXY_DEPOSIT: Balance = 10;
+	const MAX_PROXIES: usize = 32;
+	const MAX_PENDING: usize = 32;
+}
+
+impl AsMut<proxy::Pallet<Runtime>> for Runtime {
+	fn as_mut(&mut self) -> &mut proxy::Pallet<Runtime> {
+		touch!(self, proxy);
+		&mut self.proxy
+	}
+}
+
+impl scheduler::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	type Currency = balances::Pallet<Self>;
+	const SCHEDULE_DEPOSIT: Balance = 10;
+	const MAXIMUM_WEIGHT: scheduler::Weight = 1_000;
+	const MAX_SIGNED_WEIGHT: scheduler::Weight = 750;
+	const TASK_WEIGHT: scheduler::Weight = 10;
+	const MAX_SCHEDULED_PER_BLOCK: usize = 50;
+	const MAX_SIGNED_PER_BLOCK: usize = 40;
+}
+
+impl AsMut<scheduler::Pallet<Runtime>> for Runtime {
+	fn as_mut(&mut self) -> &mut scheduler::Pallet<Runtime> {
+		touch!(self, scheduler);
+		&mut self.scheduler
+	}
+}
+
+impl democracy::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	type Currency = balances::Pallet<Self>;
+	type Scheduler = scheduler::Pallet<Self>;
+	const MINIMUM_DEPOSIT: Balance = 10;
+	const VOTING_PERIOD: BlockNumber = 10;
+	const ENACTMENT_PERIOD: BlockNumber = 5;
+	const VOTE_LOCKING_PERIOD: BlockNumber = 10;
+	const DEFAULT_MINIMUM_TURNOUT_PERCENT: u32 = 25;
+}
+
+impl treasury::Config for Runtime {
+	type Hashing = hashing::Sha256;
+	type AccountIdFromHash = types::AccountIdFromHash;
+	type Currency = balances::Pallet<Self>;
+	const PROPOSAL_BOND_PERCENT: u32 = 5;
+	const PROPOSAL_BOND_MINIMUM: Balance = 10;
+	const SPEND_PERIOD: BlockNumber = 20;
+	const BURN_PERCENT: u32 = 1;
+	const MAX_APPROVALS: usize = 100;
+}
+
+impl staking::Config for Runtime {
+	type Currency = balances::Pallet<Self>;
+	const ERA_LENGTH: BlockNumber = 10;
+	const VALIDATOR_COUNT: usize = 4;
+	const MAX_NOMINATIONS: usize = 16;
+	const MIN_BOND: Balance = 10;
+	const BONDING_DURATION: staking::EraIndex = 3;
+	const MAX_UNLOCKING_CHUNKS: usize = 32;
+	const ERA_REWARD: Balance = 100;
+}
+
+impl identity::Config for Runtime {
+	type Currency = balances::Pallet<Self>;
+	const BASIC_DEPOSIT: Balance = 10;
+	const SUB_ACCOUNT_DEPOSIT: Balance = 2;
+	const MAX_FIELD_LENGTH: usize = 32;
+	const MAX_SUB_ACCOUNTS: usize = 16;
+	const MAX_REGISTRARS: usize = 20;
+}
+
+impl nfts::Config for Runtime {
+	type CollectionId = u32;
+	type ItemId = u32;
+	type Content = Content;
+	type Claims = proof_of_existence::Pallet<Self>;
+	type Currency = balances::Pallet<Self>;
+	const COLLECTION_DEPOSIT: Balance = 10;
+	const ITEM_DEPOSIT: Balance = 1;
+	const METADATA_DEPOSIT: Balance = 1;
+	const ATTRIBUTE_DEPOSIT: Balance = 1;
+	const STRING_LIMIT: usize = 50;
+	const MAX_ATTRIBUTES: usize = 16;
+	const MAX_APPROVALS: usize = 4;
+}
+
+impl AsMut<balances::Pallet<Runtime>> for Runtime {
+	fn as_mut(&mut self) -> &mut balances::Pallet<Runtime> {
+		touch!(self, balances);
+		&mut self.balances
+	}
+}
+
+// Calls are encoded as the index of the pallet followed by the encoded call of that pallet, so that
+// they can be hashed, for example by the multisig pallet.
+impl support::Encode for RuntimeCall {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			RuntimeCall::Balances(call) => (0u8, call).encode_to(dest),
+			RuntimeCall::ProofOfExistence(call) => (1u8, call).encode_to(dest),
+			RuntimeCall::Assets(call) => (2u8, call).encode_to(dest),
+			RuntimeCall::Vesting(call) => (3u8, call).encode_to(dest),
+			RuntimeCall::Utility(call) => (4u8, call).encode_to(dest),
+			RuntimeCall::Multisig(call) => (5u8, call).encode_to(dest),
+			RuntimeCall::Proxy(call) => (6u8, call).encode_to(dest),
+			RuntimeCall::Scheduler(call) => (7u8, call).encode_to(dest),
+			RuntimeCall::Democracy(call) => (8u8, call).encode_to(dest),
+			RuntimeCall::Treasury(call) => (9u8, call).encode_to(dest),
+			RuntimeCall::Staking(call) => (10u8, call).encode_to(dest),
+			RuntimeCall::Identity(call) => (11u8, call).encode_to(dest),
+			RuntimeCall::Nfts(call) => (12u8, call).encode_to(dest),
+		}
+	}
+}
+
+// Charge the fee of a signed extrinsic, or of a call scheduled by an account, to `who`. A share of
+// it goes to the treasury, and the rest is burned.
+impl support::ChargeFee<AccountId> for Runtime {
+	fn charge_fee(&mut self, who: &AccountId) -> support::DispatchResult {
+		touch!(self, balances);
+		let fee = self.balances.withdraw(who, TRANSACTION_FEE, WithdrawReasons::FEE)?;
+		let (to_treasury, to_burn) = fee.split(TRANSACTION_FEE * TREASURY_FEE_PERCENT / 100);
+		self.balances.resolve_creating(&treasury::Pallet::<Self>::account_id(), to_treasury);
+		self.balances.burn(to_burn);
+		Ok(())
+	}
 }
 
 impl crate::support::Dispatch for Runtime {
-	type Caller = self::AccountId;
+	type Caller = RuntimeOrigin;
 	type Call = RuntimeCall;
 	// Dispatch a call on behalf of a caller. Increments the caller's nonce.
 	//
@@ -51,41 +432,196 @@ impl crate::support::Dispatch for Runtime {
 	// to determine who we are executing the call on behalf of.
 	fn dispatch(
 		&mut self,
-		caller: Self::Caller,
+		origin: Self::Caller,
 		runtime_call: Self::Call,
 	) -> support::DispatchResult {
+		// Utility and scheduler calls pass the origin on to the calls they dispatch, and democracy,
+		// treasury, staking and identity have calls which must be dispatched as `Root`. Every
+		// other call must be signed.
+		//
+		// Pallets reached through `AsMut` are touched there, every other pallet a call can change
+		// is touched here.
 		match runtime_call {
 			RuntimeCall::Balances(call) => {
-				self.balances.dispatch(caller, call)?;
+				touch!(self, balances);
+				self.balances.dispatch(ensure_signed(origin)?, call)?;
 				Ok(())
 			},
 			RuntimeCall::ProofOfExistence(call) => {
-				self.proof.dispatch(caller, call)
+				touch!(self, proof, balances);
+				self.proof.dispatch(&mut self.balances, ensure_signed(origin)?, call)
+			},
+			RuntimeCall::Assets(call) => {
+				touch!(self, assets);
+				self.assets.dispatch(ensure_signed(origin)?, call)
 			},
+			RuntimeCall::Vesting(call) => {
+				touch!(self, vesting, balances);
+				self.vesting.dispatch(&mut self.balances, ensure_signed(origin)?, call)
+			},
+			RuntimeCall::Utility(call) => utility::Pallet::dispatch(self, origin, call),
+			RuntimeCall::Multisig(call) => {
+				let now = multisig::Timepoint {
+					height: self.system.block_number(),
+					index: self.system.extrinsic_index(),
+				};
+				multisig::Pallet::dispatch(self, now, ensure_signed(origin)?, call)
+			},
+			RuntimeCall::Proxy(call) => proxy::Pallet::dispatch(self, ensure_signed(origin)?, call),
+			RuntimeCall::Scheduler(call) => {
+				touch!(self, scheduler, balances);
+				self.scheduler.dispatch(&mut self.balances, origin, call)
+			},
+			RuntimeCall::Democracy(call) => {
+				touch!(self, democracy, balances);
+				self.democracy.dispatch(&mut self.balances, origin, call)
+			},
+			RuntimeCall::Treasury(call) => {
+				touch!(self, treasury, balances);
+				self.treasury.dispatch(&mut self.balances, origin, call)
+			},
+			RuntimeCall::Staking(call) => {
+				touch!(self, staking, balances);
+				self.staking.dispatch(&mut self.balances, origin, call)
+			},
+			RuntimeCall::Identity(call) => {
+				touch!(self, identity, balances);
+				self.identity.dispatch(&mut self.balances, origin, call)
+			},
+			RuntimeCall::Nfts(call) => {
+				touch!(self, nfts, balances);
+				self.nfts.dispatch(&mut self.balances, &self.proof, ensure_signed(origin)?, call)
+			},
+		}
+	}
+}
+
+// Rather than copying the whole runtime at the start of a transaction, we copy each pallet the
+// first time the transaction touches it.
+impl Transactional for Runtime {
+	fn start_transaction(&mut self) {
+		self.transactions.push(Snapshot::default());
+	}
+
+	fn commit_transaction(&mut self) {
+		let snapshot = self.transactions.pop().expect("a transaction is open");
+		if let Some(outer) = self.transactions.last_mut() {
+			snapshot.merge_into(outer);
 		}
 	}
+
+	fn rollback_transaction(&mut self) {
+		let snapshot = self.transactions.pop().expect("a transaction is open");
+		snapshot.restore(self);
+	}
 }
 
 impl Runtime {
 	/// Creates a new instance of the main Runtime, by creating a new instance of each pallet.
 	fn new() -> Self {
-		Self { system: system::Pallet::new(), balances: balances::Pallet::new(), proof : proof_of_existence::Pallet::new() }
+		Self {
+			system: system::Pallet::new(),
+			timestamp: timestamp::Pallet::new(),
+			balances: balances::Pallet::new(),
+			proof: proof_of_existence::Pallet::new(),
+			assets: assets::Pallet::new(),
+			vesting: vesting::Pallet::new(),
+			utility: utility::Pallet::new(),
+			multisig: multisig::Pallet::new(),
+			proxy: proxy::Pallet::new(),
+			scheduler: scheduler::Pallet::new(),
+			democracy: democracy::Pallet::new(),
+			treasury: treasury::Pallet::new(),
+			staking: staking::Pallet::new(),
+			identity: identity::Pallet::new(),
+			nfts: nfts::Pallet::new(),
+			transactions: Vec::new(),
+		}
 	}
 	// Execute a block of extrinsics. Increments the block number.
 	fn execute_block(&mut self, block: types::Block) -> support::DispatchResult {
+		// A block with invalid inherents is rejected before anything is changed.
+		self.check_inherents(&block.inherents)?;
 		self.system.inc_block_number();
 		assert_eq!(self.system.block_number(), block.header.block_number, "Incorrect Block Number");
+		self.timestamp.on_initialize(block.header.block_number);
+		self.balances.on_initialize(block.header.block_number);
+		self.proof.on_initialize(block.header.block_number);
+		self.assets.on_initialize(block.header.block_number);
+		self.vesting.on_initialize(block.header.block_number);
+		self.utility.on_initialize(block.header.block_number);
+		self.multisig.on_initialize(block.header.block_number);
+		self.proxy.on_initialize(block.header.block_number);
+		self.scheduler.on_initialize(block.header.block_number);
+		self.democracy.on_initialize(block.header.block_number);
+		self.treasury.on_initialize(block.header.block_number);
+		self.staking.on_initialize(block.header.block_number);
+		self.identity.on_initialize(block.header.block_number);
+		self.nfts.on_initialize(block.header.block_number);
+		// The inherents are dispatched before anything else, so that the pallets doing work at the
+		// start of the block see its time.
+		for inherent in block.inherents {
+			self.dispatch_inherent(inherent)?;
+		}
+		scheduler::Pallet::service_agenda(self, block.header.block_number);
+		self.democracy.end_referenda(
+			&mut self.balances,
+			&mut self.scheduler,
+			block.header.block_number,
+		);
+		self.treasury.spend_funds(&mut self.balances, block.header.block_number);
+		self.staking.rotate_era(&mut self.balances, block.header.block_number);
+		self.proof.expire_claims(&mut self.balances, block.header.block_number);
+		self.vesting.release_vested(&mut self.balances, block.header.block_number);
 		for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
 			self.system.inc_nonce(&caller);
-			let _res = self.dispatch(caller, call).map_err(|e| {
+			self.system.set_extrinsic_index(i as u32);
+			let result = self
+				.charge_fee(&caller)
+				.and_then(|()| self.dispatch(RuntimeOrigin::Signed(caller), call));
+			let _res = result.map_err(|e| {
 				eprintln!(
 					"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
 					block.header.block_number, i, e
 				)
 			});
 		}
+		self.timestamp.on_finalize(block.header.block_number);
+		self.proof.on_finalize(block.header.block_number);
+		// In this simplified runtime, the state root only commits to the proof of existence claims.
+		self.system.set_state_root(self.proof.claims_root());
 		Ok(())
 	}
+
+	// Check the inherents put in a block by its author, without dispatching them. A block must
+	// have exactly one timestamp inherent.
+	fn check_inherents(&self, inherents: &[RuntimeInherent]) -> support::DispatchResult {
+		let [RuntimeInherent::Timestamp(timestamp::EntryPoint::Set { now })] = inherents else {
+			return Err("Block must have exactly one timestamp inherent")
+		};
+		self.timestamp.check(*now)
+	}
+
+	// Dispatch an inherent put in the block by its author. Unlike extrinsics, inherents have no
+	// caller, and a block with a failing inherent is invalid.
+	fn dispatch_inherent(&mut self, inherent: RuntimeInherent) -> support::DispatchResult {
+		match inherent {
+			RuntimeInherent::Timestamp(call) => {
+				self.timestamp.dispatch(call)?;
+				self.proof.on_timestamp_set(self.timestamp.now());
+				self.vesting.on_timestamp_set(self.timestamp.now());
+				Ok(())
+			},
+		}
+	}
+
+	/// Build a Merkle proof of the claim on `content` against the state root of the last executed
+	/// block. Returns `None` if the content has not been claimed.
+	///
+	/// The proof can be checked by anyone with `proof_of_existence::verify_claim_proof`.
+	fn prove_claim(&self, content: &types::Content) -> Option<proof_of_existence::ClaimProof> {
+		self.proof.claim_proof(content)
+	}
 }
 
 fn main() {
@@ -94,6 +630,7 @@ fn main() {
 
 	let block_1 = types::Block {
 		header: support::Header { block_number: 1 },
+		inherents: vec![RuntimeInherent::Timestamp(timestamp::EntryPoint::Set { now: 6_000 })],
 		extrinsics: vec![
 			support::Extrinsic {
 				caller: "alice".to_owned(),
@@ -122,37 +659,316 @@ fn main() {
 	let sig = runtime.sign(&"Hello, world!".to_owned(), "alice".to_string());
 	let block_2 = types::Block {
         header: support::Header { block_number: 2 },
+        inherents: vec![RuntimeInherent::Timestamp(timestamp::EntryPoint::Set { now: 12_000 })],
         extrinsics: vec![
             support::Extrinsic {
                 caller: "alice".to_string(),
-                call: RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::CreateClaim("Hello, world!".to_owned())),
+                call: RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::create_claim_for_bytes(b"Hello, world!")),
             },
             support::Extrinsic {
                 caller: "bob".to_string(),
-				call: RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::CreateClaim("Hello, world!".to_owned())),
+				call: RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::create_claim_for_bytes(b"Hello, world!")),
             },
         ],
     };
 
     let block_3 = types::Block {
         header: support::Header { block_number: 3 },
+        inherents: vec![RuntimeInherent::Timestamp(timestamp::EntryPoint::Set { now: 18_000 })],
         extrinsics: vec![
             support::Extrinsic {
                 caller: "alice".to_owned(),
-                call: RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::RevokeClaim("Hello world!".to_string())),
+                call: RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::RevokeClaim(hashing::Sha256::hash(b"Hello world!"))),
             },
             support::Extrinsic {
                 caller: String::from("bob"),
-                call: RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::CreateClaim("Hello, world!".to_string())),
+                call: RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::create_claim_for_bytes(b"Hello, world!")),
             },
         ],
     };
 
+	// Register a batch of documents at once, and issue some loyalty points.
+	let batch = proof_of_existence::ClaimBatch::<Runtime>::from_documents(&[b"one", b"two"]);
+	let block_4 = types::Block {
+		header: support::Header { block_number: 4 },
+		inherents: vec![RuntimeInherent::Timestamp(timestamp::EntryPoint::Set { now: 24_000 })],
+		extrinsics: vec![
+			support::Extrinsic {
+				caller: "alice".to_owned(),
+				call: RuntimeCall::ProofOfExistence(batch.create_call()),
+			},
+			support::Extrinsic {
+				caller: "alice".to_owned(),
+				call: RuntimeCall::Assets(assets::EntryPoint::Create {
+					asset_id: 0,
+					admin: "alice".to_owned(),
+					min_balance: 1,
+				}),
+			},
+			support::Extrinsic {
+				caller: "alice".to_owned(),
+				call: RuntimeCall::Assets(assets::EntryPoint::Mint {
+					asset_id: 0,
+					beneficiary: "bob".to_owned(),
+					amount: 50,
+				}),
+			},
+		],
+	};
+
     // Execute the extrinsics which make up our blocks.
     // If there are any errors, our system panics, since we should not execute invalid blocks.
     runtime.execute_block(block_1).expect("invalid block");
     runtime.execute_block(block_2).expect("invalid block");
     runtime.execute_block(block_3).expect("invalid block");
+	runtime.execute_block(block_4).expect("invalid block");
+
+	// Prove the claim on our content to someone who only knows the state root of the last block.
+	let content = hashing::Sha256::hash(b"Hello, world!");
+	let info = runtime.proof.get_claim(&content).expect("claim exists").clone();
+	let proof = runtime.prove_claim(&content).expect("claim exists");
+	let root = runtime.system.state_root();
+	assert!(proof_of_existence::verify_claim_proof(&root, &content, &info, &proof));
+
+	// Show that a single document is part of the anchored batch.
+	let content = hashing::Sha256::hash(b"two");
+	let proof = batch.proof(&content).expect("document is in the batch");
+	assert!(runtime.proof.verify_batch_claim(&batch.root(), &content, &proof).is_some());
 
 	println!("{:#?}", runtime);
+
+	// Show the loyalty points of each account, under its verified name if it has one.
+	for who in ["alice".to_string(), "bob".to_string()] {
+		let name = runtime.identity.display_name(&who).unwrap_or_else(|| who.clone());
+		let points = runtime.assets.balance(0, &who);
+		println!("{} holds {} of {} points", name, points, runtime.assets.total_supply(0));
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::support::ReservableCurrency;
+
+	/// A runtime where alice, bob and charlie have 100 each.
+	fn new_runtime() -> Runtime {
+		let mut runtime = Runtime::new();
+		for who in ["alice", "bob", "charlie"] {
+			runtime.balances.set_balance(&who.to_string(), 100);
+		}
+		runtime
+	}
+
+	fn transfer(to: &str, amount: Balance) -> RuntimeCall {
+		RuntimeCall::Balances(balances::EntryPoint::Transfer { to: to.to_string(), amount })
+	}
+
+	/// Execute the next block, with the given extrinsics and a timestamp 6 seconds after the last.
+	fn next_block(runtime: &mut Runtime, extrinsics: Vec<(&str, RuntimeCall)>) {
+		let block_number = runtime.system.block_number() + 1;
+		let now = Moment::from(block_number) * 6_000;
+		let extrinsics = extrinsics
+			.into_iter()
+			.map(|(caller, call)| support::Extrinsic { caller: caller.to_string(), call })
+			.collect();
+		let block = types::Block {
+			header: support::Header { block_number },
+			inherents: vec![RuntimeInherent::Timestamp(timestamp::EntryPoint::Set { now })],
+			extrinsics,
+		};
+		runtime.execute_block(block).expect("invalid block");
+	}
+
+	#[test]
+	fn batch_all_rolls_back_every_pallet_it_touched() {
+		let mut runtime = new_runtime();
+		let alice = RuntimeOrigin::Signed("alice".to_string());
+		let hello = hashing::Sha256::hash(b"Hello, world!");
+		let claim = proof_of_existence::EntryPoint::CreateClaim(hello);
+		let inner = vec![RuntimeCall::ProofOfExistence(claim), transfer("bob", 10)];
+		let inner = RuntimeCall::Utility(utility::EntryPoint::BatchAll(inner));
+		let call = utility::EntryPoint::BatchAll(vec![inner.clone(), transfer("bob", 1_000)]);
+		assert_eq!(
+			runtime.dispatch(alice.clone(), RuntimeCall::Utility(call)),
+			Err("Not enough funds.")
+		);
+		assert_eq!(runtime.proof.get_claim(&hello), None);
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 0);
+		assert!(runtime.utility.events.is_empty());
+		assert!(runtime.transactions.is_empty());
+
+		// The committed inner batch is kept once the outer one succeeds.
+		let call = utility::EntryPoint::BatchAll(vec![inner, transfer("charlie", 1)]);
+		assert_eq!(runtime.dispatch(alice, RuntimeCall::Utility(call)), Ok(()));
+		assert_eq!(runtime.proof.get_claim(&hello).map(|info| info.owner.as_str()), Some("alice"));
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 110);
+		assert!(runtime.transactions.is_empty());
+	}
+
+	#[test]
+	fn passed_referendum_is_dispatched_as_root() {
+		let mut runtime = new_runtime();
+		let add_registrar =
+			RuntimeCall::Identity(identity::EntryPoint::AddRegistrar("charlie".to_string()));
+		let propose = democracy::EntryPoint::Propose(Box::new(add_registrar.clone()));
+		let aye = democracy::Vote {
+			aye: true,
+			conviction: democracy::Conviction::Locked1x,
+			balance: 50,
+		};
+		let vote = || RuntimeCall::Democracy(democracy::EntryPoint::Vote { index: 0, vote: aye });
+
+		// Only `Root` can add a registrar.
+		next_block(&mut runtime, vec![("alice", add_registrar)]);
+		assert!(runtime.identity.registrars.is_empty());
+
+		next_block(
+			&mut runtime,
+			vec![
+				("alice", RuntimeCall::Democracy(propose)),
+				// A quarter of the 300 issued must vote.
+				("alice", vote()),
+				("bob", vote()),
+			],
+		);
+		// Voting ends at block 12, and the call is enacted 5 blocks later.
+		while runtime.system.block_number() < 12 {
+			next_block(&mut runtime, vec![]);
+		}
+		assert!(matches!(
+			runtime.democracy.events.as_slice(),
+			[democracy::Event::Passed { index: 0, enactment: 17 }]
+		));
+		while runtime.system.block_number() < 16 {
+			next_block(&mut runtime, vec![]);
+		}
+		assert!(runtime.identity.registrars.is_empty());
+		next_block(&mut runtime, vec![]);
+		assert!(matches!(
+			runtime.scheduler.events.as_slice(),
+			[scheduler::Event::Dispatched { task: (17, 0), id: None, result: Ok(()) }]
+		));
+		let registrars: Vec<_> =
+			runtime.identity.registrars.iter().map(|info| info.account.as_str()).collect();
+		assert_eq!(registrars, vec!["charlie"]);
+	}
+
+	#[test]
+	fn non_transfer_proxies_can_not_move_funds() {
+		use support::InstanceFilter;
+		let bob = || "bob".to_string();
+		let hello = hashing::Sha256::hash(b"Hello, world!");
+		let denied = vec![
+			transfer("bob", 1),
+			RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::TransferClaim {
+				claim: hello,
+				new_owner: bob(),
+			}),
+			RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::OfferClaim {
+				claim: hello,
+				to: bob(),
+			}),
+			RuntimeCall::Assets(assets::EntryPoint::Transfer { asset_id: 0, to: bob(), amount: 1 }),
+			RuntimeCall::Democracy(democracy::EntryPoint::Propose(Box::new(transfer("bob", 1)))),
+			RuntimeCall::Democracy(democracy::EntryPoint::Vote {
+				index: 0,
+				vote: democracy::Vote {
+					aye: true,
+					conviction: democracy::Conviction::None,
+					balance: 1,
+				},
+			}),
+			RuntimeCall::Treasury(treasury::EntryPoint::ProposeSpend {
+				value: 1,
+				beneficiary: bob(),
+			}),
+			RuntimeCall::Staking(staking::EntryPoint::Bond(1)),
+			RuntimeCall::Staking(staking::EntryPoint::BondExtra(1)),
+			RuntimeCall::Identity(identity::EntryPoint::RequestJudgement {
+				registrar_index: 0,
+				max_fee: 1,
+			}),
+			RuntimeCall::Identity(identity::EntryPoint::SetIdentity(Default::default())),
+			RuntimeCall::Identity(identity::EntryPoint::AddSub { sub: bob(), name: Vec::new() }),
+			RuntimeCall::Nfts(nfts::EntryPoint::Transfer { collection: 0, item: 0, dest: bob() }),
+			RuntimeCall::Nfts(nfts::EntryPoint::ApproveTransfer {
+				collection: 0,
+				item: 0,
+				delegate: bob(),
+			}),
+			RuntimeCall::Nfts(nfts::EntryPoint::TransferOwnership {
+				collection: 0,
+				new_owner: bob(),
+			}),
+			RuntimeCall::Nfts(nfts::EntryPoint::Burn { collection: 0, item: 0 }),
+			RuntimeCall::Nfts(nfts::EntryPoint::Mint { collection: 0, item: 0, owner: bob() }),
+		];
+		for call in denied {
+			assert!(!ProxyType::NonTransfer.filter(&call), "{call:?} is allowed");
+			// Wrapping the call in a batch does not help.
+			let batch = RuntimeCall::Utility(utility::EntryPoint::Batch(vec![call]));
+			assert!(!ProxyType::NonTransfer.filter(&batch));
+		}
+
+		let allowed = vec![
+			RuntimeCall::ProofOfExistence(proof_of_existence::EntryPoint::CreateClaim(hello)),
+			RuntimeCall::Democracy(democracy::EntryPoint::RemoveVote(0)),
+			RuntimeCall::Staking(staking::EntryPoint::Unbond(1)),
+			RuntimeCall::Identity(identity::EntryPoint::CancelRequest(0)),
+			RuntimeCall::Nfts(nfts::EntryPoint::MintClaim { collection: 0, item: 0, claim: hello }),
+		];
+		for call in allowed {
+			assert!(ProxyType::NonTransfer.filter(&call), "{call:?} is denied");
+		}
+	}
+
+	#[test]
+	fn fees_are_split_between_the_treasury_and_burning() {
+		let mut runtime = new_runtime();
+		let pot = treasury::Pallet::<Runtime>::account_id();
+		let issuance = runtime.balances.total_issuance();
+
+		// A failed extrinsic still pays its fee.
+		let extrinsics = vec![("alice", transfer("bob", 10)), ("bob", transfer("alice", 500))];
+		next_block(&mut runtime, extrinsics);
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 100 - 10 - 2);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 110 - 2);
+		assert_eq!(runtime.balances.balance(&pot), 2);
+		assert_eq!(runtime.balances.total_issuance(), issuance - 2);
+
+		// An account which can not pay the fee can not dispatch anything.
+		next_block(&mut runtime, vec![("dave", transfer("alice", 0))]);
+		assert_eq!(runtime.balances.balance(&pot), 2);
+		assert_eq!(runtime.balances.total_issuance(), issuance - 2);
+	}
+
+	#[test]
+	fn blocks_with_invalid_inherents_change_nothing() {
+		let mut runtime = new_runtime();
+		next_block(&mut runtime, vec![("alice", transfer("bob", 10))]);
+		let set = |now| RuntimeInherent::Timestamp(timestamp::EntryPoint::Set { now });
+
+		// No timestamp, two timestamps, or one too close to the last.
+		let invalid = [
+			(vec![], "Block must have exactly one timestamp inherent"),
+			(vec![set(12_000), set(18_000)], "Block must have exactly one timestamp inherent"),
+			(vec![set(8_999)], "Timestamp must increment by at least the minimum period"),
+		];
+		for (inherents, error) in invalid {
+			let block = types::Block {
+				header: support::Header { block_number: 2 },
+				inherents,
+				extrinsics: vec![support::Extrinsic {
+					caller: "alice".to_string(),
+					call: transfer("bob", 10),
+				}],
+			};
+			assert_eq!(runtime.execute_block(block), Err(error));
+			assert_eq!(runtime.system.block_number(), 1);
+			assert_eq!(runtime.timestamp.now(), 6_000);
+			assert_eq!(runtime.balances.balance(&"bob".to_string()), 110);
+			assert_eq!(runtime.balances.events.len(), 1);
+		}
+	}
 }
@@ -0,0 +1,141 @@
+use crate::hashing::{sha2_256, Hash};
+
+/// A proof that a single leaf is included in a binary Merkle tree.
+///
+/// The tree is built bottom up by hashing pairs of nodes together. When a level has an odd number
+/// of nodes, the last node is promoted to the next level unchanged. That means the position of the
+/// leaf and the number of leaves are enough to know, at every level, whether the next sibling is
+/// on the left, on the right, or missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+	/// The position of the leaf in the tree.
+	pub leaf_index: u32,
+	/// The total number of leaves in the tree.
+	pub leaf_count: u32,
+	/// The sibling hashes on the path from the leaf to the root, bottom up.
+	pub siblings: Vec<Hash>,
+}
+
+/// Hash the encoded data of a leaf.
+///
+/// Leaves and inner nodes use a different prefix, so that an inner node can never be presented as
+/// a leaf.
+pub fn hash_leaf(data: &[u8]) -> Hash {
+	let mut input = Vec::with_capacity(data.len() + 1);
+	input.push(0u8);
+	input.extend_from_slice(data);
+	sha2_256(&input)
+}
+
+/// Hash two child nodes into their parent node.
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+	let mut input = Vec::with_capacity(65);
+	input.push(1u8);
+	input.extend_from_slice(left);
+	input.extend_from_slice(right);
+	sha2_256(&input)
+}
+
+/// Compute the next level of the tree from the current one.
+fn next_level(level: &[Hash]) -> Vec<Hash> {
+	level
+		.chunks(2)
+		.map(|pair| match pair {
+			[left, right] => hash_node(left, right),
+			[single] => *single,
+			_ => unreachable!("chunks of two are never empty"),
+		})
+		.collect()
+}
+
+/// Compute the root of a Merkle tree over the given leaf hashes.
+///
+/// The root of an empty tree is all zeros.
+pub fn merkle_root(leaves: &[Hash]) -> Hash {
+	if leaves.is_empty() {
+		return [0u8; 32]
+	}
+	let mut level = leaves.to_vec();
+	while level.len() > 1 {
+		level = next_level(&level);
+	}
+	level[0]
+}
+
+/// Build a proof that the leaf at `leaf_index` is included in the tree over `leaves`.
+///
+/// Returns `None` if the index is out of bounds.
+pub fn merkle_proof(leaves: &[Hash], leaf_index: usize) -> Option<MerkleProof> {
+	if leaf_index >= leaves.len() {
+		return None
+	}
+	let mut siblings = Vec::new();
+	let mut level = leaves.to_vec();
+	let mut index = leaf_index;
+	while level.len() > 1 {
+		let sibling = if index % 2 == 1 { index - 1 } else { index + 1 };
+		if let Some(hash) = level.get(sibling) {
+			siblings.push(*hash);
+		}
+		level = next_level(&level);
+		index /= 2;
+	}
+	Some(MerkleProof { leaf_index: leaf_index as u32, leaf_count: leaves.len() as u32, siblings })
+}
+
+/// Check that `leaf` is included in the tree with the given `root`, using `proof`.
+pub fn verify_proof(root: &Hash, leaf: Hash, proof: &MerkleProof) -> bool {
+	if proof.leaf_index >= proof.leaf_count {
+		return false
+	}
+	let mut siblings = proof.siblings.iter();
+	let mut hash = leaf;
+	let mut index = proof.leaf_index;
+	let mut width = proof.leaf_count;
+	while width > 1 {
+		if index % 2 == 1 {
+			let Some(left) = siblings.next() else { return false };
+			hash = hash_node(left, &hash);
+		} else if index + 1 < width {
+			let Some(right) = siblings.next() else { return false };
+			hash = hash_node(&hash, right);
+		}
+		index /= 2;
+		width = width.div_ceil(2);
+	}
+	// A valid proof uses every sibling it contains.
+	siblings.next().is_none() && &hash == root
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn proofs_verify_for_every_leaf() {
+		for count in 1..=9u8 {
+			let leaves = (0..count).map(|i| hash_leaf(&[i])).collect::<Vec<_>>();
+			let root = merkle_root(&leaves);
+			for (i, leaf) in leaves.iter().enumerate() {
+				let proof = merkle_proof(&leaves, i).unwrap();
+				assert!(verify_proof(&root, *leaf, &proof));
+				// The proof does not work for any other leaf.
+				assert!(!verify_proof(&root, hash_leaf(&[count]), &proof));
+			}
+			assert_eq!(merkle_proof(&leaves, count as usize), None);
+		}
+	}
+
+	#[test]
+	fn tampered_proofs_fail() {
+		let leaves = (0..5u8).map(|i| hash_leaf(&[i])).collect::<Vec<_>>();
+		let root = merkle_root(&leaves);
+		let mut proof = merkle_proof(&leaves, 2).unwrap();
+		proof.leaf_index = 3;
+		assert!(!verify_proof(&root, leaves[2], &proof));
+		proof.leaf_index = 2;
+		proof.siblings.push([0u8; 32]);
+		assert!(!verify_proof(&root, leaves[2], &proof));
+		assert_eq!(merkle_root(&[]), [0u8; 32]);
+	}
+}
@@ -0,0 +1,146 @@
+//! Test doubles for the traits in `support`, so that pallets can be tested without the rest of the
+//! runtime.
+
+use std::collections::BTreeMap;
+
+use crate::support::{
+	BalanceStatus, Currency, DispatchResult, NegativeImbalance, ReservableCurrency,
+	WithdrawReasons,
+};
+
+/// A minimal in-memory currency with free and reserved balances, and no locks.
+#[derive(Debug)]
+pub struct MockCurrency<AccountId> {
+	/// The free and reserved balance of each account.
+	accounts: BTreeMap<AccountId, (u128, u128)>,
+	total_issuance: u128,
+}
+
+impl<AccountId: Ord + Clone> MockCurrency<AccountId> {
+	pub fn new() -> Self {
+		Self { accounts: BTreeMap::new(), total_issuance: 0 }
+	}
+
+	/// Set the free balance of an account, creating or destroying funds as needed.
+	pub fn set_balance(&mut self, who: &AccountId, amount: u128) {
+		let (free, reserved) = self.get(who);
+		self.total_issuance = self.total_issuance - free + amount;
+		self.accounts.insert(who.clone(), (amount, reserved));
+	}
+
+	/// Get the free balance of an account.
+	pub fn balance(&self, who: &AccountId) -> u128 {
+		self.get(who).0
+	}
+
+	fn get(&self, who: &AccountId) -> (u128, u128) {
+		self.accounts.get(who).copied().unwrap_or((0, 0))
+	}
+}
+
+impl<AccountId: Ord + Clone> Currency<AccountId> for MockCurrency<AccountId> {
+	type Balance = u128;
+
+	fn free_balance(&self, who: &AccountId) -> u128 {
+		self.get(who).0
+	}
+
+	fn total_balance(&self, who: &AccountId) -> u128 {
+		let (free, reserved) = self.get(who);
+		free + reserved
+	}
+
+	fn total_issuance(&self) -> u128 {
+		self.total_issuance
+	}
+
+	fn transfer(&mut self, from: &AccountId, to: &AccountId, amount: u128) -> DispatchResult {
+		let imbalance = self.withdraw(from, amount, WithdrawReasons::TRANSFER)?;
+		self.resolve_creating(to, imbalance);
+		Ok(())
+	}
+
+	fn withdraw(
+		&mut self,
+		who: &AccountId,
+		amount: u128,
+		_reasons: WithdrawReasons,
+	) -> Result<NegativeImbalance<u128>, &'static str> {
+		let (free, reserved) = self.get(who);
+		let free = free.checked_sub(amount).ok_or("Not enough funds.")?;
+		self.accounts.insert(who.clone(), (free, reserved));
+		Ok(NegativeImbalance::new(amount))
+	}
+
+	fn deposit_creating(&mut self, who: &AccountId, amount: u128) -> DispatchResult {
+		let (free, reserved) = self.get(who);
+		self.total_issuance = self.total_issuance.checked_add(amount).ok_or("Overflow")?;
+		self.accounts.insert(who.clone(), (free + amount, reserved));
+		Ok(())
+	}
+
+	fn resolve_creating(&mut self, who: &AccountId, imbalance: NegativeImbalance<u128>) {
+		let (free, reserved) = self.get(who);
+		self.accounts.insert(who.clone(), (free + imbalance.peek(), reserved));
+	}
+
+	fn burn(&mut self, imbalance: NegativeImbalance<u128>) {
+		self.total_issuance -= imbalance.peek();
+	}
+
+	fn slash(&mut self, who: &AccountId, amount: u128) -> u128 {
+		let (free, reserved) = self.get(who);
+		let from_free = amount.min(free);
+		let from_reserved = (amount - from_free).min(reserved);
+		self.accounts.insert(who.clone(), (free - from_free, reserved - from_reserved));
+		self.total_issuance -= from_free + from_reserved;
+		amount - from_free - from_reserved
+	}
+}
+
+impl<AccountId: Ord + Clone> ReservableCurrency<AccountId> for MockCurrency<AccountId> {
+	fn reserved_balance(&self, who: &AccountId) -> u128 {
+		self.get(who).1
+	}
+
+	fn reserve(&mut self, who: &AccountId, amount: u128) -> DispatchResult {
+		let (free, reserved) = self.get(who);
+		let free = free.checked_sub(amount).ok_or("Not enough funds.")?;
+		self.accounts.insert(who.clone(), (free, reserved + amount));
+		Ok(())
+	}
+
+	fn unreserve(&mut self, who: &AccountId, amount: u128) -> u128 {
+		let (free, reserved) = self.get(who);
+		let actual = amount.min(reserved);
+		self.accounts.insert(who.clone(), (free + actual, reserved - actual));
+		amount - actual
+	}
+
+	fn slash_reserved(&mut self, who: &AccountId, amount: u128) -> u128 {
+		let (free, reserved) = self.get(who);
+		let actual = amount.min(reserved);
+		self.accounts.insert(who.clone(), (free, reserved - actual));
+		self.total_issuance -= actual;
+		amount - actual
+	}
+
+	fn repatriate_reserved(
+		&mut self,
+		slashed: &AccountId,
+		beneficiary: &AccountId,
+		amount: u128,
+		status: BalanceStatus,
+	) -> DispatchResult {
+		let (free, reserved) = self.get(slashed);
+		let reserved = reserved.checked_sub(amount).ok_or("Not enough reserved funds.")?;
+		self.accounts.insert(slashed.clone(), (free, reserved));
+		let (free, reserved) = self.get(beneficiary);
+		let to = match status {
+			BalanceStatus::Free => (free + amount, reserved),
+			BalanceStatus::Reserved => (free, reserved + amount),
+		};
+		self.accounts.insert(beneficiary.clone(), to);
+		Ok(())
+	}
+}
@@ -0,0 +1,558 @@
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+
+use crate::{
+	hashing::{Hash, Hasher},
+	support::{Convert, Dispatch, DispatchResult, Encode, Hooks, ReservableCurrency},
+};
+
+pub trait Config: crate::types::Config {
+	/// The outer call type of the runtime, which a multisig account can dispatch.
+	type RuntimeCall: Clone + Debug + Encode;
+	/// The hash function used to identify a call, and to derive multisig account ids.
+	type Hashing: Hasher<Output = Hash>;
+	/// The currency used to hold the deposit of a pending operation.
+	type Currency: ReservableCurrency<Self::AccountId, Balance = Self::Balance>;
+	/// How to turn the hash of a set of signatories into the id of their multisig account.
+	type AccountIdFromHash: Convert<Hash, Self::AccountId>;
+	/// The amount reserved from the account which starts an operation, until it is executed or
+	/// cancelled.
+	const DEPOSIT: Self::Balance;
+	/// The maximum number of signatories of a multisig account.
+	const MAX_SIGNATORIES: usize;
+}
+
+/// A point in the history of the chain: the extrinsic at `index` in the block at `height`.
+///
+/// Approvals must name the timepoint at which an operation was started, so that they can not be
+/// replayed against a later operation with the same call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timepoint<BlockNumber> {
+	/// The number of the block.
+	pub height: BlockNumber,
+	/// The index of the extrinsic in the block.
+	pub index: u32,
+}
+
+impl<BlockNumber: Encode> Encode for Timepoint<BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(&self.height, self.index).encode_to(dest)
+	}
+}
+
+/// The `Timepoint` used by a runtime configured with `T`.
+pub type TimepointOf<T> = Timepoint<<T as crate::types::Config>::BlockNumber>;
+
+/// An operation of a multisig account which is waiting for approvals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Multisig<AccountId, Balance, BlockNumber> {
+	/// When the operation was started.
+	pub when: Timepoint<BlockNumber>,
+	/// The deposit reserved from `depositor`.
+	pub deposit: Balance,
+	/// The signatory which started the operation.
+	pub depositor: AccountId,
+	/// The signatories which approved the operation so far, sorted.
+	pub approvals: Vec<AccountId>,
+}
+
+/// The `Multisig` stored by a runtime configured with `T`.
+pub type MultisigOf<T> = Multisig<
+	<T as crate::types::Config>::AccountId,
+	<T as crate::types::Config>::Balance,
+	<T as crate::types::Config>::BlockNumber,
+>;
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// `approving` started an operation of `multisig` to dispatch the call with `call_hash`.
+	Created { approving: T::AccountId, multisig: T::AccountId, call_hash: Hash },
+	/// `approving` approved the operation started at `timepoint`.
+	Approved {
+		approving: T::AccountId,
+		timepoint: TimepointOf<T>,
+		multisig: T::AccountId,
+		call_hash: Hash,
+	},
+	/// The operation started at `timepoint` reached its threshold, and the call was dispatched
+	/// with `result`.
+	Executed {
+		approving: T::AccountId,
+		timepoint: TimepointOf<T>,
+		multisig: T::AccountId,
+		call_hash: Hash,
+		result: DispatchResult,
+	},
+	/// The operation started at `timepoint` was cancelled.
+	Cancelled {
+		cancelling: T::AccountId,
+		timepoint: TimepointOf<T>,
+		multisig: T::AccountId,
+		call_hash: Hash,
+	},
+}
+
+/// This is the Multisig Module.
+/// It lets a set of signatories share an account, which can only dispatch a call once a threshold
+/// of them approved it.
+///
+/// Approved calls are dispatched through the runtime itself, so the functions of this pallet take
+/// the whole runtime rather than `&mut self`. The runtime gives them access to this pallet and to
+/// the currency through `AsMut`.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The pending operations, keyed by multisig account and then by call hash.
+	pub multisigs: BTreeMap<(T::AccountId, Hash), MultisigOf<T>>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	AsMulti {
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		maybe_timepoint: Option<TimepointOf<T>>,
+		call_hash: Hash,
+		call: Option<Box<T::RuntimeCall>>,
+	},
+	CancelAsMulti {
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		timepoint: TimepointOf<T>,
+		call_hash: Hash,
+	},
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::AsMulti {
+				threshold,
+				other_signatories,
+				maybe_timepoint,
+				call_hash,
+				call,
+			} => (0u8, threshold, other_signatories, maybe_timepoint, call_hash, call)
+				.encode_to(dest),
+			EntryPoint::CancelAsMulti { threshold, other_signatories, timepoint, call_hash } => {
+				(1u8, threshold, other_signatories, timepoint, call_hash).encode_to(dest)
+			},
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Multisig Module.
+	pub fn new() -> Self {
+		Self { multisigs: BTreeMap::new(), events: Vec::new() }
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
+	}
+
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute. `now` is the timepoint of the extrinsic being
+	/// executed.
+	pub fn dispatch<R>(
+		runtime: &mut R,
+		now: TimepointOf<T>,
+		caller: T::AccountId,
+		call: EntryPoint<T>,
+	) -> DispatchResult
+	where
+		R: Dispatch<Call = T::RuntimeCall> + AsMut<Self> + AsMut<T::Currency>,
+		R::Caller: From<T::AccountId>,
+	{
+		match call {
+			EntryPoint::AsMulti {
+				threshold,
+				other_signatories,
+				maybe_timepoint,
+				call_hash,
+				call,
+			} => Self::as_multi(
+				runtime,
+				now,
+				caller,
+				threshold,
+				other_signatories,
+				maybe_timepoint,
+				call_hash,
+				call.map(|call| *call),
+			),
+			EntryPoint::CancelAsMulti { threshold, other_signatories, timepoint, call_hash } => {
+				Self::cancel_as_multi(
+					runtime,
+					caller,
+					threshold,
+					other_signatories,
+					timepoint,
+					call_hash,
+				)
+			},
+		}
+	}
+
+	/// Get the hash identifying `call`.
+	pub fn call_hash(call: &T::RuntimeCall) -> Hash {
+		T::Hashing::hash(&call.encode())
+	}
+
+	/// Get the id of the account shared by `signatories`, which must be sorted, with the given
+	/// `threshold`. The same signatories with a different threshold share a different account.
+	pub fn multi_account_id(signatories: &[T::AccountId], threshold: u16) -> T::AccountId {
+		let data = (b"multisig", signatories, threshold).encode();
+		T::AccountIdFromHash::convert(T::Hashing::hash(&data))
+	}
+
+	/// Approve the call with `call_hash` on behalf of the multisig account of the `caller` and
+	/// `other_signatories`, which needs `threshold` approvals.
+	///
+	/// The first approval starts a new operation at timepoint `now`, and reserves `T::DEPOSIT`
+	/// from the `caller`. Later approvals must give the timepoint of that operation. The approval
+	/// which reaches the threshold must also give the `call`, which is then dispatched from the
+	/// multisig account, and the deposit is returned.
+	#[allow(clippy::too_many_arguments)]
+	pub fn as_multi<R>(
+		runtime: &mut R,
+		now: TimepointOf<T>,
+		caller: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		maybe_timepoint: Option<TimepointOf<T>>,
+		call_hash: Hash,
+		call: Option<T::RuntimeCall>,
+	) -> DispatchResult
+	where
+		R: Dispatch<Call = T::RuntimeCall> + AsMut<Self> + AsMut<T::Currency>,
+		R::Caller: From<T::AccountId>,
+	{
+		let signatories = Self::ensure_signatories(&caller, threshold, other_signatories)?;
+		if let Some(call) = &call {
+			if Self::call_hash(call) != call_hash {
+				return Err("Call does not match its hash")
+			}
+		}
+		let multisig = Self::multi_account_id(&signatories, threshold);
+		let key = (multisig.clone(), call_hash);
+		let pallet: &mut Self = runtime.as_mut();
+
+		let Some(mut operation) = pallet.multisigs.get(&key).cloned() else {
+			// This is a new operation.
+			if maybe_timepoint.is_some() {
+				return Err("Unexpected timepoint for a new operation")
+			}
+			let currency: &mut T::Currency = runtime.as_mut();
+			currency.reserve(&caller, T::DEPOSIT)?;
+			let operation = Multisig {
+				when: now,
+				deposit: T::DEPOSIT,
+				depositor: caller.clone(),
+				approvals: vec![caller.clone()],
+			};
+			let pallet: &mut Self = runtime.as_mut();
+			pallet.multisigs.insert(key, operation);
+			pallet.deposit_event(Event::Created { approving: caller, multisig, call_hash });
+			return Ok(())
+		};
+
+		let timepoint = operation.when;
+		if maybe_timepoint != Some(timepoint) {
+			return Err("Timepoint does not match the pending operation")
+		}
+		let Err(position) = operation.approvals.binary_search(&caller) else {
+			return Err("Caller already approved this operation")
+		};
+		operation.approvals.insert(position, caller.clone());
+
+		if operation.approvals.len() < threshold as usize {
+			pallet.multisigs.insert(key, operation);
+			pallet.deposit_event(Event::Approved {
+				approving: caller,
+				timepoint,
+				multisig,
+				call_hash,
+			});
+			return Ok(())
+		}
+		let Some(call) = call else { return Err("The call must be given by the last approval") };
+
+		pallet.multisigs.remove(&key);
+		let currency: &mut T::Currency = runtime.as_mut();
+		currency.unreserve(&operation.depositor, operation.deposit);
+		let result = runtime.dispatch(multisig.clone().into(), call);
+		let pallet: &mut Self = runtime.as_mut();
+		pallet.deposit_event(Event::Executed {
+			approving: caller,
+			timepoint,
+			multisig,
+			call_hash,
+			result,
+		});
+		Ok(())
+	}
+
+	/// Cancel the pending operation started at `timepoint` for the call with `call_hash`, and
+	/// return its deposit. Only the signatory which started the operation can do this.
+	pub fn cancel_as_multi<R>(
+		runtime: &mut R,
+		caller: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		timepoint: TimepointOf<T>,
+		call_hash: Hash,
+	) -> DispatchResult
+	where
+		R: AsMut<Self> + AsMut<T::Currency>,
+	{
+		let signatories = Self::ensure_signatories(&caller, threshold, other_signatories)?;
+		let multisig = Self::multi_account_id(&signatories, threshold);
+		let key = (multisig.clone(), call_hash);
+		let pallet: &mut Self = runtime.as_mut();
+		let operation = pallet.multisigs.get(&key).ok_or("Operation does not exist")?;
+		if operation.when != timepoint {
+			return Err("Timepoint does not match the pending operation")
+		}
+		if operation.depositor != caller {
+			return Err("Only the depositor can cancel an operation")
+		}
+		let deposit = operation.deposit;
+		pallet.multisigs.remove(&key);
+		pallet.deposit_event(Event::Cancelled {
+			cancelling: caller.clone(),
+			timepoint,
+			multisig,
+			call_hash,
+		});
+
+		let currency: &mut T::Currency = runtime.as_mut();
+		currency.unreserve(&caller, deposit);
+		Ok(())
+	}
+
+	/// Check the signatories and threshold of a multisig account, and return all the signatories
+	/// sorted.
+	fn ensure_signatories(
+		caller: &T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+	) -> Result<Vec<T::AccountId>, &'static str> {
+		if threshold < 2 {
+			return Err("Threshold must be at least two")
+		}
+		let mut signatories = other_signatories;
+		signatories.push(caller.clone());
+		if signatories.len() > T::MAX_SIGNATORIES {
+			return Err("Too many signatories")
+		}
+		signatories.sort();
+		if signatories.windows(2).any(|pair| pair[0] == pair[1]) {
+			return Err("Signatories must be unique")
+		}
+		if threshold as usize > signatories.len() {
+			return Err("Threshold is higher than the number of signatories")
+		}
+		Ok(signatories)
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, _n: T::BlockNumber) {
+		self.events.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{EntryPoint, Event, Timepoint};
+	use crate::{
+		balances,
+		hashing::Sha256,
+		support::{Dispatch, Encode, ReservableCurrency},
+	};
+
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub struct Test;
+
+	impl super::Config for Test {
+		type RuntimeCall = TestCall;
+		type Hashing = Sha256;
+		type Currency = balances::Pallet<Test>;
+		type AccountIdFromHash = crate::types::AccountIdFromHash;
+		const DEPOSIT: u128 = 10;
+		const MAX_SIGNATORIES: usize = 3;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	impl balances::Config for Test {
+		const MAX_TRANSFERS_PER_BATCH: usize = 100;
+	}
+
+	#[derive(Debug, Clone)]
+	pub enum TestCall {
+		Balances(balances::EntryPoint<Test>),
+	}
+
+	impl Encode for TestCall {
+		fn encode_to(&self, dest: &mut Vec<u8>) {
+			match self {
+				TestCall::Balances(call) => (0u8, call).encode_to(dest),
+			}
+		}
+	}
+
+	/// A runtime with just enough pallets to dispatch transfers from a multisig account.
+	struct TestRuntime {
+		balances: balances::Pallet<Test>,
+		multisig: super::Pallet<Test>,
+	}
+
+	impl AsMut<super::Pallet<Test>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Test> {
+			&mut self.multisig
+		}
+	}
+
+	impl AsMut<balances::Pallet<Test>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut balances::Pallet<Test> {
+			&mut self.balances
+		}
+	}
+
+	impl Dispatch for TestRuntime {
+		type Caller = String;
+		type Call = TestCall;
+
+		fn dispatch(&mut self, caller: String, call: TestCall) -> crate::support::DispatchResult {
+			match call {
+				TestCall::Balances(call) => self.balances.dispatch(caller, call),
+			}
+		}
+	}
+
+	fn others(who: &[&str]) -> Vec<String> {
+		who.iter().map(|who| who.to_string()).collect()
+	}
+
+	#[test]
+	fn multi_account_id_is_deterministic() {
+		let sorted = others(&["alice", "bob", "charlie"]);
+		let id = super::Pallet::<Test>::multi_account_id(&sorted, 2);
+		assert_eq!(id, super::Pallet::<Test>::multi_account_id(&sorted, 2));
+		assert_ne!(id, super::Pallet::<Test>::multi_account_id(&sorted, 3));
+		assert_eq!(id.len(), 64);
+	}
+
+	#[test]
+	fn two_of_three_transfer() {
+		let mut runtime =
+			TestRuntime { balances: balances::Pallet::new(), multisig: super::Pallet::new() };
+		let multi =
+			super::Pallet::<Test>::multi_account_id(&others(&["alice", "bob", "charlie"]), 2);
+		for who in ["alice", "bob", "charlie"] {
+			runtime.balances.set_balance(&who.to_string(), 100);
+		}
+		runtime.balances.set_balance(&multi, 1_000);
+
+		let call = || {
+			TestCall::Balances(balances::EntryPoint::Transfer {
+				to: "dave".to_string(),
+				amount: 500,
+			})
+		};
+		let call_hash = super::Pallet::<Test>::call_hash(&call());
+		let now = Timepoint { height: 1, index: 0 };
+		let as_multi = |caller: &str, others, maybe_timepoint, call| {
+			let call = EntryPoint::AsMulti {
+				threshold: 2,
+				other_signatories: others,
+				maybe_timepoint,
+				call_hash,
+				call,
+			};
+			(caller.to_string(), call)
+		};
+
+		// Alice starts the operation, and pays the deposit.
+		let (caller, call_1) = as_multi("alice", others(&["bob", "charlie"]), None, None);
+		assert_eq!(super::Pallet::dispatch(&mut runtime, now, caller, call_1), Ok(()));
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 10);
+
+		// Bob can not replay the approval against another timepoint, and must give the call.
+		let later = Timepoint { height: 2, index: 3 };
+		let (caller, call_2) = as_multi("bob", others(&["alice", "charlie"]), Some(later), None);
+		assert_eq!(
+			super::Pallet::dispatch(&mut runtime, later, caller, call_2),
+			Err("Timepoint does not match the pending operation")
+		);
+		let (caller, call_2) = as_multi("bob", others(&["charlie", "alice"]), Some(now), None);
+		assert_eq!(
+			super::Pallet::dispatch(&mut runtime, later, caller, call_2),
+			Err("The call must be given by the last approval")
+		);
+		let (caller, call_2) =
+			as_multi("bob", others(&["charlie", "alice"]), Some(now), Some(Box::new(call())));
+		assert_eq!(super::Pallet::dispatch(&mut runtime, later, caller, call_2), Ok(()));
+
+		assert_eq!(runtime.balances.balance(&"dave".to_string()), 500);
+		assert_eq!(runtime.balances.balance(&multi), 500);
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 0);
+		assert!(runtime.multisig.multisigs.is_empty());
+		assert!(matches!(
+			runtime.multisig.events.last(),
+			Some(Event::Executed { result: Ok(()), .. })
+		));
+	}
+
+	#[test]
+	fn cancel_and_invalid_signatories() {
+		let mut runtime =
+			TestRuntime { balances: balances::Pallet::new(), multisig: super::Pallet::new() };
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		let now = Timepoint { height: 1, index: 0 };
+		let cancel = |runtime: &mut TestRuntime, who: &str, others| {
+			super::Pallet::cancel_as_multi(runtime, who.to_string(), 2, others, now, [0u8; 32])
+		};
+		assert_eq!(
+			cancel(&mut runtime, "alice", others(&["alice"])),
+			Err("Signatories must be unique")
+		);
+		assert_eq!(
+			cancel(&mut runtime, "alice", others(&["bob", "charlie", "dave"])),
+			Err("Too many signatories")
+		);
+		assert_eq!(
+			cancel(&mut runtime, "alice", others(&["bob"])),
+			Err("Operation does not exist")
+		);
+
+		let call = EntryPoint::AsMulti {
+			threshold: 2,
+			other_signatories: others(&["bob"]),
+			maybe_timepoint: None,
+			call_hash: [0u8; 32],
+			call: None,
+		};
+		super::Pallet::dispatch(&mut runtime, now, "alice".to_string(), call).unwrap();
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 10);
+		assert_eq!(
+			cancel(&mut runtime, "bob", others(&["alice"])),
+			Err("Only the depositor can cancel an operation")
+		);
+		assert_eq!(cancel(&mut runtime, "alice", others(&["bob"])), Ok(()));
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+		assert!(runtime.multisig.multisigs.is_empty());
+	}
+}
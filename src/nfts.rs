@@ -0,0 +1,956 @@
+use core::fmt::Debug;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::support::{
+	BalanceStatus, ClaimOwnership, DispatchResult, Encode, Hooks, ReservableCurrency,
+};
+
+pub trait Config: crate::types::Config {
+	/// The type used to identify a collection.
+	type CollectionId: Ord + Copy + Debug + Encode;
+	/// The type used to identify an item within its collection.
+	type ItemId: Ord + Copy + Debug + Encode;
+	/// The content of the proof of existence claims which can be minted as items.
+	type Content: Ord + Clone + Debug + Encode;
+	/// Where to look up the owner of a claim.
+	type Claims: ClaimOwnership<Self::AccountId, Self::Content>;
+	/// The currency used to hold the deposits for what is stored.
+	type Currency: ReservableCurrency<Self::AccountId, Balance = Self::Balance>;
+	/// The amount reserved from the owner of a collection, which moves with the ownership.
+	const COLLECTION_DEPOSIT: Self::Balance;
+	/// The amount reserved from the account which mints an item, until the item is burned.
+	const ITEM_DEPOSIT: Self::Balance;
+	/// The amount reserved from the account which sets the metadata of an item.
+	const METADATA_DEPOSIT: Self::Balance;
+	/// The amount reserved from the account which sets an attribute of an item, for each
+	/// attribute.
+	const ATTRIBUTE_DEPOSIT: Self::Balance;
+	/// The maximum length in bytes of the metadata of an item, and of the keys and values of its
+	/// attributes.
+	const STRING_LIMIT: usize;
+	/// The maximum number of attributes of a single item.
+	const MAX_ATTRIBUTES: usize;
+	/// The maximum number of accounts approved to transfer a single item.
+	const MAX_APPROVALS: usize;
+}
+
+/// Funds reserved from `account` while something is stored, and given back once it is removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deposit<AccountId, Balance> {
+	pub account: AccountId,
+	pub amount: Balance,
+}
+
+/// Everything we record about a collection when it is created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectionDetails<AccountId, Balance> {
+	/// The account which created the collection, and which can change its team or destroy it.
+	pub owner: AccountId,
+	/// The account which can mint items.
+	pub issuer: AccountId,
+	/// The account which can burn any item, and set the metadata and attributes of items.
+	pub admin: AccountId,
+	/// The account which can freeze and thaw items.
+	pub freezer: AccountId,
+	/// The number of items in the collection.
+	pub items: u32,
+	/// The deposit reserved from the owner.
+	pub deposit: Balance,
+}
+
+/// Everything we record about an item when it is minted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemDetails<AccountId, Balance, Content> {
+	/// The account which holds the item.
+	pub owner: AccountId,
+	/// The accounts which can transfer the item on behalf of its owner, until it is transferred.
+	pub approvals: BTreeSet<AccountId>,
+	/// Whether the item is stopped from being transferred.
+	pub is_frozen: bool,
+	/// The proof of existence claim the item was minted from, if any. The item stops standing for
+	/// the claim once the claim changes hands, see `Pallet::claim_item`.
+	pub claim: Option<Content>,
+	/// The deposit reserved from the account which minted the item.
+	pub deposit: Deposit<AccountId, Balance>,
+}
+
+/// The `Deposit` held by a runtime configured with `T`.
+pub type DepositOf<T> =
+	Deposit<<T as crate::types::Config>::AccountId, <T as crate::types::Config>::Balance>;
+
+/// The attributes of an item, each with its value and deposit, stored by a runtime configured
+/// with `T`.
+pub type AttributesOf<T> = BTreeMap<Vec<u8>, (Vec<u8>, DepositOf<T>)>;
+
+/// The `CollectionDetails` stored by a runtime configured with `T`.
+pub type CollectionDetailsOf<T> =
+	CollectionDetails<<T as crate::types::Config>::AccountId, <T as crate::types::Config>::Balance>;
+
+/// The `ItemDetails` stored by a runtime configured with `T`.
+pub type ItemDetailsOf<T> = ItemDetails<
+	<T as crate::types::Config>::AccountId,
+	<T as crate::types::Config>::Balance,
+	<T as Config>::Content,
+>;
+
+/// The key under which an item and its metadata and attributes are stored.
+pub type ItemKey<T> = (<T as Config>::CollectionId, <T as Config>::ItemId);
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// `owner` created `collection`.
+	Created { collection: T::CollectionId, owner: T::AccountId },
+	/// The empty `collection` was destroyed.
+	Destroyed { collection: T::CollectionId },
+	/// The owner of `collection` changed its team.
+	TeamChanged {
+		collection: T::CollectionId,
+		issuer: T::AccountId,
+		admin: T::AccountId,
+		freezer: T::AccountId,
+	},
+	/// The ownership of `collection` moved to `new_owner`.
+	OwnerChanged { collection: T::CollectionId, new_owner: T::AccountId },
+	/// `item` of `collection` was minted to `owner`.
+	Issued { collection: T::CollectionId, item: T::ItemId, owner: T::AccountId },
+	/// `item` of `collection` was minted from the proof of existence claim on `claim`.
+	ClaimMinted { collection: T::CollectionId, item: T::ItemId, claim: T::Content },
+	/// `item` of `collection` moved from `from` to `to`.
+	Transferred {
+		collection: T::CollectionId,
+		item: T::ItemId,
+		from: T::AccountId,
+		to: T::AccountId,
+	},
+	/// `item` of `collection`, held by `owner`, was destroyed.
+	Burned { collection: T::CollectionId, item: T::ItemId, owner: T::AccountId },
+	/// `delegate` can transfer `item` of `collection` on behalf of its owner.
+	ApprovedTransfer { collection: T::CollectionId, item: T::ItemId, delegate: T::AccountId },
+	/// `delegate` can no longer transfer `item` of `collection`.
+	ApprovalCancelled { collection: T::CollectionId, item: T::ItemId, delegate: T::AccountId },
+	/// `item` of `collection` can no longer be transferred.
+	Frozen { collection: T::CollectionId, item: T::ItemId },
+	/// `item` of `collection` can be transferred again.
+	Thawed { collection: T::CollectionId, item: T::ItemId },
+	/// The metadata of `item` of `collection` was set to `data`.
+	MetadataSet { collection: T::CollectionId, item: T::ItemId, data: Vec<u8> },
+	/// The attribute `key` of `item` of `collection` was set to `value`.
+	AttributeSet { collection: T::CollectionId, item: T::ItemId, key: Vec<u8>, value: Vec<u8> },
+	/// The attribute `key` of `item` of `collection` was removed.
+	AttributeCleared { collection: T::CollectionId, item: T::ItemId, key: Vec<u8> },
+}
+
+/// This is the NFTs Module.
+/// It lets accounts create collections of unique items, which can be minted, transferred and
+/// burned. Each item is identified by its collection and its id within the collection.
+///
+/// Each collection has a team: an issuer minting items, an admin managing them, and a freezer
+/// freezing them. The owner of a proof of existence claim can also mint it as an item, to make it
+/// tradable. Collections, items, metadata and attributes each hold a deposit until they are
+/// removed.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The details of each collection.
+	pub collections: BTreeMap<T::CollectionId, CollectionDetailsOf<T>>,
+	/// The details of each item.
+	pub items: BTreeMap<ItemKey<T>, ItemDetailsOf<T>>,
+	/// The metadata of each item which has some, at most `T::STRING_LIMIT` bytes long, with its
+	/// deposit.
+	pub metadata: BTreeMap<ItemKey<T>, (Vec<u8>, DepositOf<T>)>,
+	/// The attributes of each item which has some, each with its deposit.
+	pub attributes: BTreeMap<ItemKey<T>, AttributesOf<T>>,
+	/// The item each minted claim became, and the owner of the claim when it was minted. A claim
+	/// can only be minted once at a time, but the link is stale once the claim is revoked, expires
+	/// or moves to another account.
+	pub claim_items: BTreeMap<T::Content, (ItemKey<T>, T::AccountId)>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	Create { collection: T::CollectionId, admin: T::AccountId },
+	Destroy(T::CollectionId),
+	SetTeam {
+		collection: T::CollectionId,
+		issuer: T::AccountId,
+		admin: T::AccountId,
+		freezer: T::AccountId,
+	},
+	TransferOwnership { collection: T::CollectionId, new_owner: T::AccountId },
+	Mint { collection: T::CollectionId, item: T::ItemId, owner: T::AccountId },
+	MintClaim { collection: T::CollectionId, item: T::ItemId, claim: T::Content },
+	Transfer { collection: T::CollectionId, item: T::ItemId, dest: T::AccountId },
+	Burn { collection: T::CollectionId, item: T::ItemId },
+	ApproveTransfer { collection: T::CollectionId, item: T::ItemId, delegate: T::AccountId },
+	CancelApproval { collection: T::CollectionId, item: T::ItemId, delegate: T::AccountId },
+	Freeze { collection: T::CollectionId, item: T::ItemId },
+	Thaw { collection: T::CollectionId, item: T::ItemId },
+	SetMetadata { collection: T::CollectionId, item: T::ItemId, data: Vec<u8> },
+	SetAttribute { collection: T::CollectionId, item: T::ItemId, key: Vec<u8>, value: Vec<u8> },
+	ClearAttribute { collection: T::CollectionId, item: T::ItemId, key: Vec<u8> },
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::Create { collection, admin } => (0u8, collection, admin).encode_to(dest),
+			EntryPoint::Destroy(collection) => (1u8, collection).encode_to(dest),
+			EntryPoint::SetTeam { collection, issuer, admin, freezer } => {
+				(2u8, collection, issuer, admin, freezer).encode_to(dest)
+			},
+			EntryPoint::TransferOwnership { collection, new_owner } => {
+				(3u8, collection, new_owner).encode_to(dest)
+			},
+			EntryPoint::Mint { collection, item, owner } => {
+				(4u8, collection, item, owner).encode_to(dest)
+			},
+			EntryPoint::MintClaim { collection, item, claim } => {
+				(5u8, collection, item, claim).encode_to(dest)
+			},
+			EntryPoint::Transfer { collection, item, dest: to } => {
+				(6u8, collection, item, to).encode_to(dest)
+			},
+			EntryPoint::Burn { collection, item } => (7u8, collection, item).encode_to(dest),
+			EntryPoint::ApproveTransfer { collection, item, delegate } => {
+				(8u8, collection, item, delegate).encode_to(dest)
+			},
+			EntryPoint::CancelApproval { collection, item, delegate } => {
+				(9u8, collection, item, delegate).encode_to(dest)
+			},
+			EntryPoint::Freeze { collection, item } => (10u8, collection, item).encode_to(dest),
+			EntryPoint::Thaw { collection, item } => (11u8, collection, item).encode_to(dest),
+			EntryPoint::SetMetadata { collection, item, data } => {
+				(12u8, collection, item, data).encode_to(dest)
+			},
+			EntryPoint::SetAttribute { collection, item, key, value } => {
+				(13u8, collection, item, key, value).encode_to(dest)
+			},
+			EntryPoint::ClearAttribute { collection, item, key } => {
+				(14u8, collection, item, key).encode_to(dest)
+			},
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the NFTs Module.
+	pub fn new() -> Self {
+		Self {
+			collections: BTreeMap::new(),
+			items: BTreeMap::new(),
+			metadata: BTreeMap::new(),
+			attributes: BTreeMap::new(),
+			claim_items: BTreeMap::new(),
+			events: Vec::new(),
+		}
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
+	}
+
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	///
+	/// The runtime hands us the currency holding our deposits and the claims which can be minted,
+	/// as described on `support::Dispatch`.
+	pub fn dispatch(
+		&mut self,
+		currency: &mut T::Currency,
+		claims: &T::Claims,
+		caller: T::AccountId,
+		call: EntryPoint<T>,
+	) -> DispatchResult {
+		match call {
+			EntryPoint::Create { collection, admin } => {
+				self.create(currency, caller, collection, admin)?
+			},
+			EntryPoint::Destroy(collection) => self.destroy(currency, caller, collection)?,
+			EntryPoint::SetTeam { collection, issuer, admin, freezer } => {
+				self.set_team(caller, collection, issuer, admin, freezer)?
+			},
+			EntryPoint::TransferOwnership { collection, new_owner } => {
+				self.transfer_ownership(currency, caller, collection, new_owner)?
+			},
+			EntryPoint::Mint { collection, item, owner } => {
+				self.mint(currency, caller, collection, item, owner)?
+			},
+			EntryPoint::MintClaim { collection, item, claim } => {
+				self.mint_claim(currency, claims, caller, collection, item, claim)?
+			},
+			EntryPoint::Transfer { collection, item, dest } => {
+				self.transfer(caller, collection, item, dest)?
+			},
+			EntryPoint::Burn { collection, item } => self.burn(currency, caller, collection, item)?,
+			EntryPoint::ApproveTransfer { collection, item, delegate } => {
+				self.approve_transfer(caller, collection, item, delegate)?
+			},
+			EntryPoint::CancelApproval { collection, item, delegate } => {
+				self.cancel_approval(caller, collection, item, delegate)?
+			},
+			EntryPoint::Freeze { collection, item } => {
+				self.set_frozen(caller, collection, item, true)?
+			},
+			EntryPoint::Thaw { collection, item } => {
+				self.set_frozen(caller, collection, item, false)?
+			},
+			EntryPoint::SetMetadata { collection, item, data } => {
+				self.set_metadata(currency, caller, collection, item, data)?
+			},
+			EntryPoint::SetAttribute { collection, item, key, value } => {
+				self.set_attribute(currency, caller, collection, item, key, value)?
+			},
+			EntryPoint::ClearAttribute { collection, item, key } => {
+				self.clear_attribute(currency, caller, collection, item, key)?
+			},
+		}
+		Ok(())
+	}
+
+	/// Get the details of `collection`.
+	pub fn collection(
+		&self,
+		collection: T::CollectionId,
+	) -> Result<&CollectionDetailsOf<T>, &'static str> {
+		self.collections.get(&collection).ok_or("Collection does not exist")
+	}
+
+	/// Get the details of `item` of `collection`.
+	pub fn item(
+		&self,
+		collection: T::CollectionId,
+		item: T::ItemId,
+	) -> Result<&ItemDetailsOf<T>, &'static str> {
+		self.items.get(&(collection, item)).ok_or("Item does not exist")
+	}
+
+	/// Get the item minted from the proof of existence claim on `claim`, if it still stands for
+	/// the claim: the claim must still be owned by the account which minted it.
+	pub fn claim_item(&self, claims: &T::Claims, claim: &T::Content) -> Option<ItemKey<T>> {
+		let (key, minter) = self.claim_items.get(claim)?;
+		(claims.claim_owner(claim).as_ref() == Some(minter)).then_some(*key)
+	}
+
+	fn item_mut(
+		&mut self,
+		collection: T::CollectionId,
+		item: T::ItemId,
+	) -> Result<&mut ItemDetailsOf<T>, &'static str> {
+		self.items.get_mut(&(collection, item)).ok_or("Item does not exist")
+	}
+
+	/// Create `collection`, owned by the `caller`, reserving `T::COLLECTION_DEPOSIT` from it.
+	/// `admin` takes every other role in its team.
+	pub fn create(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		admin: T::AccountId,
+	) -> DispatchResult {
+		if self.collections.contains_key(&collection) {
+			return Err("Collection already exists")
+		}
+		currency.reserve(&caller, T::COLLECTION_DEPOSIT)?;
+		let details = CollectionDetails {
+			owner: caller.clone(),
+			issuer: admin.clone(),
+			admin: admin.clone(),
+			freezer: admin,
+			items: 0,
+			deposit: T::COLLECTION_DEPOSIT,
+		};
+		self.collections.insert(collection, details);
+		self.deposit_event(Event::Created { collection, owner: caller });
+		Ok(())
+	}
+
+	/// Destroy `collection`, which must have no items left, giving back its deposit. Only the owner
+	/// can do this.
+	pub fn destroy(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+	) -> DispatchResult {
+		let details = self.collection(collection)?;
+		if details.owner != caller {
+			return Err("Caller is not the owner of the collection")
+		}
+		if details.items > 0 {
+			return Err("Collection is not empty")
+		}
+		currency.unreserve(&caller, details.deposit);
+		self.collections.remove(&collection);
+		self.deposit_event(Event::Destroyed { collection });
+		Ok(())
+	}
+
+	/// Change the team of `collection`. Only the owner can do this.
+	pub fn set_team(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		issuer: T::AccountId,
+		admin: T::AccountId,
+		freezer: T::AccountId,
+	) -> DispatchResult {
+		let details = self.collections.get_mut(&collection).ok_or("Collection does not exist")?;
+		if details.owner != caller {
+			return Err("Caller is not the owner of the collection")
+		}
+		details.issuer = issuer.clone();
+		details.admin = admin.clone();
+		details.freezer = freezer.clone();
+		self.deposit_event(Event::TeamChanged { collection, issuer, admin, freezer });
+		Ok(())
+	}
+
+	/// Give `collection` to `new_owner`, along with its deposit. Only the owner can do this.
+	pub fn transfer_ownership(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		new_owner: T::AccountId,
+	) -> DispatchResult {
+		let details = self.collections.get_mut(&collection).ok_or("Collection does not exist")?;
+		if details.owner != caller {
+			return Err("Caller is not the owner of the collection")
+		}
+		let deposit = details.deposit;
+		currency.repatriate_reserved(&caller, &new_owner, deposit, BalanceStatus::Reserved)?;
+		details.owner = new_owner.clone();
+		self.deposit_event(Event::OwnerChanged { collection, new_owner });
+		Ok(())
+	}
+
+	/// Mint `item` of `collection` to `owner`, reserving `T::ITEM_DEPOSIT` from the `caller`. Only
+	/// the issuer of the collection can do this.
+	pub fn mint(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		owner: T::AccountId,
+	) -> DispatchResult {
+		self.do_mint(currency, caller, collection, item, owner, None)
+	}
+
+	/// Mint `item` of `collection` from the proof of existence claim on `claim`, to the `caller`.
+	/// The `caller` must own the claim and be the issuer of the collection, and a claim can only be
+	/// minted once until its item is burned or the claim changes hands. Minting it again unlinks
+	/// the previous item from the claim.
+	///
+	/// The item is only a token of the claim: transferring it does not transfer the claim.
+	pub fn mint_claim(
+		&mut self,
+		currency: &mut T::Currency,
+		claims: &T::Claims,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		claim: T::Content,
+	) -> DispatchResult {
+		if claims.claim_owner(&claim).as_ref() != Some(&caller) {
+			return Err("Caller does not own the claim")
+		}
+		if self.claim_item(claims, &claim).is_some() {
+			return Err("Claim was already minted")
+		}
+		let owner = caller.clone();
+		self.do_mint(currency, caller.clone(), collection, item, owner, Some(claim.clone()))?;
+		let link = ((collection, item), caller);
+		if let Some((stale, _)) = self.claim_items.insert(claim.clone(), link) {
+			if let Some(details) = self.items.get_mut(&stale) {
+				details.claim = None;
+			}
+		}
+		self.deposit_event(Event::ClaimMinted { collection, item, claim });
+		Ok(())
+	}
+
+	fn do_mint(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		owner: T::AccountId,
+		claim: Option<T::Content>,
+	) -> DispatchResult {
+		let details = self.collections.get_mut(&collection).ok_or("Collection does not exist")?;
+		if details.issuer != caller {
+			return Err("Caller is not the issuer of the collection")
+		}
+		if self.items.contains_key(&(collection, item)) {
+			return Err("Item already exists")
+		}
+		let items = details.items.checked_add(1).ok_or("Overflow")?;
+		currency.reserve(&caller, T::ITEM_DEPOSIT)?;
+		details.items = items;
+
+		let details = ItemDetails {
+			owner: owner.clone(),
+			approvals: BTreeSet::new(),
+			is_frozen: false,
+			claim,
+			deposit: Deposit { account: caller, amount: T::ITEM_DEPOSIT },
+		};
+		self.items.insert((collection, item), details);
+		self.deposit_event(Event::Issued { collection, item, owner });
+		Ok(())
+	}
+
+	/// Move `item` of `collection` to `dest`. The owner of the item, or an account it approved,
+	/// can do this unless the item is frozen. Transferring an item cancels its approvals.
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		dest: T::AccountId,
+	) -> DispatchResult {
+		let details = self.item_mut(collection, item)?;
+		if details.owner != caller && !details.approvals.contains(&caller) {
+			return Err("Caller is not the owner of the item or approved")
+		}
+		if details.is_frozen {
+			return Err("Item is frozen")
+		}
+		let from = core::mem::replace(&mut details.owner, dest.clone());
+		details.approvals.clear();
+		self.deposit_event(Event::Transferred { collection, item, from, to: dest });
+		Ok(())
+	}
+
+	/// Destroy `item` of `collection`, with its metadata and attributes, giving back all their
+	/// deposits. The owner of the item or the admin of the collection can do this. If the item was
+	/// minted from a claim, the claim can be minted again.
+	pub fn burn(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+	) -> DispatchResult {
+		let owner = self.item(collection, item)?.owner.clone();
+		let details = self.collections.get_mut(&collection).ok_or("Collection does not exist")?;
+		if owner != caller && details.admin != caller {
+			return Err("Caller is not the owner of the item or the admin")
+		}
+		// Every item is counted in its collection, so this can not underflow.
+		details.items -= 1;
+
+		let key = (collection, item);
+		if let Some(details) = self.items.remove(&key) {
+			Self::release(currency, details.deposit);
+			if let Some(claim) = details.claim {
+				self.claim_items.remove(&claim);
+			}
+		}
+		if let Some((_, deposit)) = self.metadata.remove(&key) {
+			Self::release(currency, deposit);
+		}
+		for (_, (_, deposit)) in self.attributes.remove(&key).unwrap_or_default() {
+			Self::release(currency, deposit);
+		}
+		self.deposit_event(Event::Burned { collection, item, owner });
+		Ok(())
+	}
+
+	/// Let `delegate` transfer `item` of `collection` on behalf of the `caller`, which must own
+	/// it.
+	pub fn approve_transfer(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		delegate: T::AccountId,
+	) -> DispatchResult {
+		let details = self.item_mut(collection, item)?;
+		if details.owner != caller {
+			return Err("Caller is not the owner of the item")
+		}
+		if !details.approvals.contains(&delegate) && details.approvals.len() >= T::MAX_APPROVALS {
+			return Err("Too many approvals")
+		}
+		details.approvals.insert(delegate.clone());
+		self.deposit_event(Event::ApprovedTransfer { collection, item, delegate });
+		Ok(())
+	}
+
+	/// Stop `delegate` from transferring `item` of `collection` on behalf of the `caller`, which
+	/// must own it.
+	pub fn cancel_approval(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		delegate: T::AccountId,
+	) -> DispatchResult {
+		let details = self.item_mut(collection, item)?;
+		if details.owner != caller {
+			return Err("Caller is not the owner of the item")
+		}
+		if !details.approvals.remove(&delegate) {
+			return Err("Account is not approved")
+		}
+		self.deposit_event(Event::ApprovalCancelled { collection, item, delegate });
+		Ok(())
+	}
+
+	/// Freeze or thaw `item` of `collection`. Only the freezer of the collection can do this.
+	pub fn set_frozen(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		is_frozen: bool,
+	) -> DispatchResult {
+		if self.collection(collection)?.freezer != caller {
+			return Err("Caller is not the freezer of the collection")
+		}
+		self.item_mut(collection, item)?.is_frozen = is_frozen;
+		let event = if is_frozen {
+			Event::Frozen { collection, item }
+		} else {
+			Event::Thawed { collection, item }
+		};
+		self.deposit_event(event);
+		Ok(())
+	}
+
+	/// Set the metadata of `item` of `collection` to `data`, reserving `T::METADATA_DEPOSIT` from
+	/// the `caller`, or remove it if `data` is empty. The deposit of the previous metadata is given
+	/// back. Only the admin of the collection can do this.
+	pub fn set_metadata(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		data: Vec<u8>,
+	) -> DispatchResult {
+		self.ensure_admin(&caller, collection, item)?;
+		if data.len() > T::STRING_LIMIT {
+			return Err("Metadata is too long")
+		}
+		let previous = if data.is_empty() {
+			self.metadata.remove(&(collection, item))
+		} else {
+			currency.reserve(&caller, T::METADATA_DEPOSIT)?;
+			let deposit = Deposit { account: caller, amount: T::METADATA_DEPOSIT };
+			self.metadata.insert((collection, item), (data.clone(), deposit))
+		};
+		if let Some((_, deposit)) = previous {
+			Self::release(currency, deposit);
+		}
+		self.deposit_event(Event::MetadataSet { collection, item, data });
+		Ok(())
+	}
+
+	/// Set the attribute `key` of `item` of `collection` to `value`, reserving
+	/// `T::ATTRIBUTE_DEPOSIT` from the `caller` and giving back the deposit of the previous value.
+	/// Only the admin of the collection can do this.
+	pub fn set_attribute(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		key: Vec<u8>,
+		value: Vec<u8>,
+	) -> DispatchResult {
+		self.ensure_admin(&caller, collection, item)?;
+		if key.len() > T::STRING_LIMIT || value.len() > T::STRING_LIMIT {
+			return Err("Attribute key or value is too long")
+		}
+		let attributes = self.attributes.get(&(collection, item));
+		let is_new = attributes.is_none_or(|attributes| !attributes.contains_key(&key));
+		if is_new && attributes.map_or(0, BTreeMap::len) >= T::MAX_ATTRIBUTES {
+			return Err("Too many attributes")
+		}
+		currency.reserve(&caller, T::ATTRIBUTE_DEPOSIT)?;
+		let deposit = Deposit { account: caller, amount: T::ATTRIBUTE_DEPOSIT };
+		let attributes = self.attributes.entry((collection, item)).or_default();
+		if let Some((_, previous)) = attributes.insert(key.clone(), (value.clone(), deposit)) {
+			Self::release(currency, previous);
+		}
+		self.deposit_event(Event::AttributeSet { collection, item, key, value });
+		Ok(())
+	}
+
+	/// Remove the attribute `key` of `item` of `collection`, giving back its deposit. Only the
+	/// admin of the collection can do this.
+	pub fn clear_attribute(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		key: Vec<u8>,
+	) -> DispatchResult {
+		self.ensure_admin(&caller, collection, item)?;
+		let attributes =
+			self.attributes.get_mut(&(collection, item)).ok_or("Attribute does not exist")?;
+		let (_, deposit) = attributes.remove(&key).ok_or("Attribute does not exist")?;
+		if attributes.is_empty() {
+			self.attributes.remove(&(collection, item));
+		}
+		Self::release(currency, deposit);
+		self.deposit_event(Event::AttributeCleared { collection, item, key });
+		Ok(())
+	}
+
+	/// Give a deposit back to the account it was reserved from.
+	fn release(currency: &mut T::Currency, deposit: DepositOf<T>) {
+		currency.unreserve(&deposit.account, deposit.amount);
+	}
+
+	/// Check that `item` of `collection` exists, and that `caller` is the admin of the collection.
+	fn ensure_admin(
+		&self,
+		caller: &T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+	) -> DispatchResult {
+		if self.collection(collection)?.admin != *caller {
+			return Err("Caller is not the admin of the collection")
+		}
+		self.item(collection, item)?;
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, _n: T::BlockNumber) {
+		self.events.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::BTreeMap;
+
+	use super::Event;
+	use crate::{
+		balances,
+		support::{ClaimOwnership, Currency, ReservableCurrency},
+	};
+
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub struct Test;
+
+	/// The owner of each claim, standing in for the proof of existence pallet.
+	#[derive(Debug, Default)]
+	pub struct TestClaims(BTreeMap<u64, &'static str>);
+
+	impl ClaimOwnership<&'static str, u64> for TestClaims {
+		fn claim_owner(&self, content: &u64) -> Option<&'static str> {
+			self.0.get(content).copied()
+		}
+	}
+
+	impl super::Config for Test {
+		type CollectionId = u32;
+		type ItemId = u32;
+		type Content = u64;
+		type Claims = TestClaims;
+		type Currency = balances::Pallet<Test>;
+		const COLLECTION_DEPOSIT: u128 = 10;
+		const ITEM_DEPOSIT: u128 = 2;
+		const METADATA_DEPOSIT: u128 = 3;
+		const ATTRIBUTE_DEPOSIT: u128 = 4;
+		const STRING_LIMIT: usize = 8;
+		const MAX_ATTRIBUTES: usize = 1;
+		const MAX_APPROVALS: usize = 1;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	impl balances::Config for Test {
+		const MAX_TRANSFERS_PER_BATCH: usize = 100;
+	}
+
+	fn new_test() -> (super::Pallet<Test>, balances::Pallet<Test>) {
+		let mut balances = balances::Pallet::new();
+		for who in ["alice", "bob", "charlie"] {
+			balances.set_balance(&who, 100);
+		}
+		(super::Pallet::new(), balances)
+	}
+
+	#[test]
+	fn collection_roles() {
+		let (mut nfts, mut balances) = new_test();
+
+		assert_eq!(nfts.create(&mut balances, "alice", 0, "bob"), Ok(()));
+		assert_eq!(nfts.create(&mut balances, "bob", 0, "bob"), Err("Collection already exists"));
+		// Bob holds every role but the ownership.
+		assert_eq!(
+			nfts.mint(&mut balances, "alice", 0, 7, "charlie"),
+			Err("Caller is not the issuer of the collection")
+		);
+		assert_eq!(nfts.mint(&mut balances, "bob", 0, 7, "charlie"), Ok(()));
+		assert_eq!(nfts.mint(&mut balances, "bob", 0, 7, "charlie"), Err("Item already exists"));
+		assert_eq!(
+			nfts.set_team("bob", 0, "dave", "dave", "dave"),
+			Err("Caller is not the owner of the collection")
+		);
+		assert_eq!(nfts.set_team("alice", 0, "dave", "bob", "bob"), Ok(()));
+		assert_eq!(
+			nfts.mint(&mut balances, "bob", 0, 8, "charlie"),
+			Err("Caller is not the issuer of the collection")
+		);
+
+		// Only the admin manages metadata and attributes.
+		assert_eq!(
+			nfts.set_metadata(&mut balances, "charlie", 0, 7, b"cat".to_vec()),
+			Err("Caller is not the admin of the collection")
+		);
+		assert_eq!(nfts.set_metadata(&mut balances, "bob", 0, 7, b"cat".to_vec()), Ok(()));
+		let (key, value) = (b"color".to_vec(), b"black".to_vec());
+		assert_eq!(nfts.set_attribute(&mut balances, "bob", 0, 7, key, value), Ok(()));
+		assert_eq!(
+			nfts.set_attribute(&mut balances, "bob", 0, 7, b"legs".to_vec(), b"4".to_vec()),
+			Err("Too many attributes")
+		);
+		assert_eq!(nfts.attributes[&(0, 7)][&b"color".to_vec()].0, b"black".to_vec());
+
+		// The collection can only be destroyed once it is empty, and burning cleans up the item.
+		assert_eq!(nfts.destroy(&mut balances, "alice", 0), Err("Collection is not empty"));
+		assert_eq!(nfts.burn(&mut balances, "charlie", 0, 7), Ok(()));
+		assert!(nfts.metadata.is_empty() && nfts.attributes.is_empty());
+		assert_eq!(nfts.destroy(&mut balances, "alice", 0), Ok(()));
+		assert_eq!(nfts.events.last(), Some(&Event::Destroyed { collection: 0 }));
+	}
+
+	#[test]
+	fn deposits() {
+		let (mut nfts, mut balances) = new_test();
+		balances.set_balance(&"dave", 5);
+
+		// Each creation, mint, metadata and attribute holds a deposit from whoever paid for it.
+		assert_eq!(nfts.create(&mut balances, "dave", 0, "dave"), Err("Not enough funds."));
+		assert_eq!(nfts.create(&mut balances, "alice", 0, "bob"), Ok(()));
+		assert_eq!(nfts.mint(&mut balances, "bob", 0, 1, "charlie"), Ok(()));
+		assert_eq!(nfts.set_metadata(&mut balances, "bob", 0, 1, b"cat".to_vec()), Ok(()));
+		let (key, value) = (b"color".to_vec(), b"black".to_vec());
+		assert_eq!(nfts.set_attribute(&mut balances, "bob", 0, 1, key.clone(), value), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 10);
+		assert_eq!(balances.reserved_balance(&"bob"), 2 + 3 + 4);
+
+		// Replacing a value swaps its deposit, and clearing it gives the deposit back.
+		assert_eq!(nfts.set_team("alice", 0, "charlie", "charlie", "charlie"), Ok(()));
+		assert_eq!(nfts.set_metadata(&mut balances, "charlie", 0, 1, b"dog".to_vec()), Ok(()));
+		assert_eq!(balances.reserved_balance(&"bob"), 2 + 4);
+		assert_eq!(balances.reserved_balance(&"charlie"), 3);
+		assert_eq!(nfts.clear_attribute(&mut balances, "charlie", 0, 1, key), Ok(()));
+		assert_eq!(balances.reserved_balance(&"bob"), 2);
+
+		// The collection deposit moves with the ownership.
+		assert_eq!(nfts.transfer_ownership(&mut balances, "alice", 0, "bob"), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(balances.free_balance(&"alice"), 90);
+		assert_eq!(balances.reserved_balance(&"bob"), 10 + 2);
+
+		// Burning and destroying give everything back.
+		assert_eq!(nfts.burn(&mut balances, "charlie", 0, 1), Ok(()));
+		assert_eq!(nfts.destroy(&mut balances, "bob", 0), Ok(()));
+		for who in ["alice", "bob", "charlie"] {
+			assert_eq!(balances.reserved_balance(&who), 0);
+		}
+		assert_eq!(balances.free_balance(&"bob"), 110);
+	}
+
+	#[test]
+	fn transfers_and_approvals() {
+		let (mut nfts, mut balances) = new_test();
+		assert_eq!(nfts.create(&mut balances, "alice", 0, "alice"), Ok(()));
+		assert_eq!(nfts.mint(&mut balances, "alice", 0, 1, "bob"), Ok(()));
+
+		assert_eq!(
+			nfts.transfer("charlie", 0, 1, "charlie"),
+			Err("Caller is not the owner of the item or approved")
+		);
+		assert_eq!(nfts.approve_transfer("bob", 0, 1, "charlie"), Ok(()));
+		assert_eq!(nfts.approve_transfer("bob", 0, 1, "dave"), Err("Too many approvals"));
+
+		// A frozen item can not be transferred, even by an approved account.
+		assert_eq!(
+			nfts.set_frozen("bob", 0, 1, true),
+			Err("Caller is not the freezer of the collection")
+		);
+		assert_eq!(nfts.set_frozen("alice", 0, 1, true), Ok(()));
+		assert_eq!(nfts.transfer("charlie", 0, 1, "dave"), Err("Item is frozen"));
+		assert_eq!(nfts.set_frozen("alice", 0, 1, false), Ok(()));
+
+		// Transferring an item cancels its approvals.
+		assert_eq!(nfts.transfer("charlie", 0, 1, "dave"), Ok(()));
+		assert_eq!(nfts.item(0, 1).map(|item| item.owner), Ok("dave"));
+		assert_eq!(
+			nfts.transfer("charlie", 0, 1, "charlie"),
+			Err("Caller is not the owner of the item or approved")
+		);
+		assert_eq!(nfts.cancel_approval("dave", 0, 1, "charlie"), Err("Account is not approved"));
+	}
+
+	#[test]
+	fn claims_can_be_minted() {
+		let (mut nfts, mut balances) = new_test();
+		let mut claims = TestClaims::default();
+		claims.0.insert(42, "alice");
+		assert_eq!(nfts.create(&mut balances, "alice", 0, "alice"), Ok(()));
+		assert_eq!(nfts.create(&mut balances, "bob", 1, "bob"), Ok(()));
+
+		assert_eq!(
+			nfts.mint_claim(&mut balances, &claims, "bob", 1, 0, 42),
+			Err("Caller does not own the claim")
+		);
+		assert_eq!(
+			nfts.mint_claim(&mut balances, &claims, "alice", 1, 0, 42),
+			Err("Caller is not the issuer of the collection")
+		);
+		assert_eq!(nfts.mint_claim(&mut balances, &claims, "alice", 0, 0, 42), Ok(()));
+		assert_eq!(
+			nfts.mint_claim(&mut balances, &claims, "alice", 0, 1, 42),
+			Err("Claim was already minted")
+		);
+		assert_eq!(nfts.item(0, 0).map(|item| item.claim), Ok(Some(42)));
+
+		// The item can be traded on its own, and burning it lets the claim be minted again.
+		assert_eq!(nfts.transfer("alice", 0, 0, "bob"), Ok(()));
+		assert_eq!(nfts.burn(&mut balances, "bob", 0, 0), Ok(()));
+		assert_eq!(nfts.mint_claim(&mut balances, &claims, "alice", 0, 1, 42), Ok(()));
+		assert_eq!(
+			nfts.events.last(),
+			Some(&Event::ClaimMinted { collection: 0, item: 1, claim: 42 })
+		);
+		assert_eq!(nfts.claim_item(&claims, &42), Some((0, 1)));
+
+		// Once the claim moves to bob, the item no longer stands for it, and bob can mint it.
+		claims.0.insert(42, "bob");
+		assert_eq!(nfts.claim_item(&claims, &42), None);
+		assert_eq!(nfts.mint_claim(&mut balances, &claims, "bob", 1, 0, 42), Ok(()));
+		assert_eq!(nfts.claim_item(&claims, &42), Some((1, 0)));
+		assert_eq!(nfts.item(0, 1).map(|item| item.claim), Ok(None));
+
+		// Burning the stale item leaves the new link alone, and a revoked claim has no item.
+		assert_eq!(nfts.burn(&mut balances, "alice", 0, 1), Ok(()));
+		assert_eq!(nfts.claim_item(&claims, &42), Some((1, 0)));
+		claims.0.remove(&42);
+		assert_eq!(nfts.claim_item(&claims, &42), None);
+	}
+}
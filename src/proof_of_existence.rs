@@ -1,94 +1,716 @@
 use core::fmt::Debug;
-use std::{cmp::Ordering, collections::BTreeMap};
+use std::{
+	cmp::Ordering,
+	collections::{BTreeMap, BTreeSet},
+};
 
-use crate::support::DispatchResult;
+use num::Zero;
+
+use crate::{
+	hashing::{Hash, Hasher},
+	merkle::{self, MerkleProof},
+	support::{
+		BalanceStatus, ClaimOwnership, DispatchResult, Encode, Hooks, OnTimestampSet,
+		ReservableCurrency,
+	},
+};
 
 pub trait Config: crate::types::Config {
+	/// The hash function used to turn some content into the digest we store on chain.
+	type Hashing: Hasher<Output = Self::Content>;
 	/// The type which represents the content that can be claimed using this pallet.
-	/// Could be the content directly as bytes, or better yet the hash of that content.
-	/// We leave that decision to the runtime developer.
-	type Content: Debug + Ord;
+	/// This is always the digest of the content, so that claims on large documents stay small.
+	type Content: Debug + Ord + Clone + Encode;
+	/// The maximum length in bytes of the memo which can be attached to a claim.
+	const MAX_MEMO_LENGTH: usize;
+	/// The maximum number of claims a single account can own.
+	const MAX_CLAIMS_PER_ACCOUNT: usize;
+	/// The currency used to hold claim deposits.
+	type Currency: ReservableCurrency<Self::AccountId, Balance = Self::Balance>;
+	/// The amount reserved from the owner of a claim for as long as the claim exists.
+	const CLAIM_DEPOSIT: Self::Balance;
+	/// The type used to represent the time at which a claim was created.
+	type Moment: Zero + Copy + Debug + Eq + Encode;
+}
+
+/// Everything we record about a claim when it is created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimInfo<AccountId, Balance, BlockNumber, Moment> {
+	/// The account which owns the claim.
+	pub owner: AccountId,
+	/// The deposit reserved from the owner for this claim.
+	pub deposit: Balance,
+	/// The block in which the claim was created.
+	pub block_number: BlockNumber,
+	/// The time of the block in which the claim was created.
+	pub created_at: Moment,
+	/// An optional description of the content, at most `Config::MAX_MEMO_LENGTH` bytes long.
+	pub memo: Option<Vec<u8>>,
+	/// The block at which the claim expires and is removed, if any.
+	pub expires_at: Option<BlockNumber>,
+}
+
+impl<AccountId: Encode, Balance: Encode, BlockNumber: Encode, Moment: Encode> Encode
+	for ClaimInfo<AccountId, Balance, BlockNumber, Moment>
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(&self.owner, &self.deposit, &self.block_number, &self.created_at).encode_to(dest);
+		(&self.memo, &self.expires_at).encode_to(dest)
+	}
+}
+
+/// The `ClaimInfo` stored by a runtime configured with `T`.
+pub type ClaimInfoOf<T> = ClaimInfo<
+	<T as crate::types::Config>::AccountId,
+	<T as crate::types::Config>::Balance,
+	<T as crate::types::Config>::BlockNumber,
+	<T as Config>::Moment,
+>;
+
+/// A Merkle proof that a claim is part of the `claims` storage, checked against the state root
+/// of a block. See `verify_claim_proof`.
+pub type ClaimProof = MerkleProof;
+
+/// The leaf we store in the Merkle tree for a single claim: the encoded content and its full
+/// `ClaimInfo`, so that a proof also commits to when the claim was made and what it says.
+fn claim_leaf<C: Encode, I: Encode>(content: &C, info: &I) -> Hash {
+	let mut data = content.encode();
+	info.encode_to(&mut data);
+	merkle::hash_leaf(&data)
+}
+
+/// Check that the claim on `content` was recorded as `info` in the state committed to by `root`.
+///
+/// This does not need access to the runtime, so anyone holding a block's state root can verify a
+/// claim proof they were given.
+pub fn verify_claim_proof<C: Encode, I: Encode>(
+	root: &Hash,
+	content: &C,
+	info: &I,
+	proof: &ClaimProof,
+) -> bool {
+	merkle::verify_proof(root, claim_leaf(content, info), proof)
+}
+
+/// Everything we record about a batch of claims anchored by its Merkle root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchInfo<AccountId, Balance, BlockNumber> {
+	/// The account which owns every claim in the batch.
+	pub owner: AccountId,
+	/// The number of documents in the batch.
+	pub leaf_count: u32,
+	/// The deposit reserved from the owner for this batch.
+	pub deposit: Balance,
+	/// The block in which the batch was anchored.
+	pub block_number: BlockNumber,
+}
+
+/// The `BatchInfo` stored by a runtime configured with `T`.
+pub type BatchInfoOf<T> = BatchInfo<
+	<T as crate::types::Config>::AccountId,
+	<T as crate::types::Config>::Balance,
+	<T as crate::types::Config>::BlockNumber,
+>;
+
+/// The leaf we store in the Merkle tree of a batch for a single document: its digest.
+fn batch_leaf<C: Encode>(content: &C) -> Hash {
+	merkle::hash_leaf(&content.encode())
+}
+
+/// An off-chain helper to register many documents with a single `CreateBatchClaim` extrinsic.
+///
+/// The client builds the Merkle tree over the digests of its documents, anchors the root on chain,
+/// and keeps the tree around to hand out an inclusion proof for each document.
+#[derive(Debug, Clone)]
+pub struct ClaimBatch<T: Config> {
+	contents: Vec<T::Content>,
+	leaves: Vec<Hash>,
+}
+
+impl<T: Config> ClaimBatch<T> {
+	/// Build a batch from the raw documents, hashing each of them with `T::Hashing`.
+	pub fn from_documents(documents: &[&[u8]]) -> Self {
+		Self::from_contents(documents.iter().map(|document| T::Hashing::hash(document)).collect())
+	}
+
+	/// Build a batch from the digests of the documents.
+	pub fn from_contents(contents: Vec<T::Content>) -> Self {
+		let leaves = contents.iter().map(batch_leaf).collect();
+		Self { contents, leaves }
+	}
+
+	/// The Merkle root to anchor on chain.
+	pub fn root(&self) -> Hash {
+		merkle::merkle_root(&self.leaves)
+	}
+
+	/// The number of documents in the batch.
+	pub fn leaf_count(&self) -> u32 {
+		self.leaves.len() as u32
+	}
+
+	/// Build the `CreateBatchClaim` call anchoring this batch.
+	pub fn create_call(&self) -> EntryPoint<T> {
+		EntryPoint::CreateBatchClaim { merkle_root: self.root(), leaf_count: self.leaf_count() }
+	}
+
+	/// Build a proof that `content` is part of this batch.
+	/// Returns `None` if the content is not in the batch.
+	pub fn proof(&self, content: &T::Content) -> Option<ClaimProof> {
+		let index = self.contents.iter().position(|c| c == content)?;
+		merkle::merkle_proof(&self.leaves, index)
+	}
+}
+
+/// Check that `content` is part of the batch with the given Merkle `root`.
+///
+/// Like `verify_claim_proof`, this does not need access to the runtime. Use
+/// `Pallet::verify_batch_claim` to also check that the root was anchored on chain.
+pub fn verify_batch_proof<C: Encode>(root: &Hash, content: &C, proof: &ClaimProof) -> bool {
+	merkle::verify_proof(root, batch_leaf(content), proof)
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// The owner of `claim` offered to transfer it to `to`.
+	ClaimOffered { claim: T::Content, from: T::AccountId, to: T::AccountId },
+	/// The ownership of `claim` moved from `from` to `to`.
+	ClaimTransferred { claim: T::Content, from: T::AccountId, to: T::AccountId },
+	/// The owner of `claim` extended it until block `expires_at`.
+	Renewed { claim: T::Content, expires_at: T::BlockNumber },
+	/// `claim` reached its expiry block and was removed.
+	Expired { claim: T::Content, owner: T::AccountId },
+	/// `owner` anchored a batch of `leaf_count` claims with the given Merkle root.
+	BatchAnchored { merkle_root: Hash, owner: T::AccountId, leaf_count: u32 },
+	/// The batch of claims with the given Merkle root was revoked.
+	BatchRevoked { merkle_root: Hash, owner: T::AccountId },
 }
 
 /// This is the Proof of Existence Module.
 /// It is a simple module that allows accounts to claim existence of some data.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pallet<T: Config> {
-	/// A simple storage map from content to the owner of that content.
+	/// A simple storage map from content to the owner of that content and when it was claimed.
 	/// Accounts can make multiple different claims, but each claim can only have one owner.
-	pub claims: BTreeMap<<T as Config>::Content, T::AccountId>,
+	pub claims: BTreeMap<<T as Config>::Content, ClaimInfoOf<T>>,
+	/// An index from each account to all the claims it owns, kept in sync with `claims`.
+	pub claims_by_owner: BTreeMap<T::AccountId, BTreeSet<<T as Config>::Content>>,
+	/// A storage map from the Merkle root of a batch of claims to the details of that batch.
+	pub batches: BTreeMap<Hash, BatchInfoOf<T>>,
+	/// An index of the claims which expire, ordered by the block at which they expire.
+	pub expiries: BTreeMap<T::BlockNumber, BTreeSet<<T as Config>::Content>>,
+	/// Pending offers to transfer a claim, from the content to the account it is offered to.
+	pub offers: BTreeMap<<T as Config>::Content, T::AccountId>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+	/// The number of the block currently being executed, as given to us in `on_initialize`.
+	block_number: T::BlockNumber,
+	/// The time of the block currently being executed, as given to us in `on_timestamp_set`.
+	now: T::Moment,
 }
 
 // A public enum which describes the calls we want to expose to the dispatcher.
 // We should expect that the caller of each call will be provided by the dispatcher,
 // and not included as a parameter of the call.
+#[derive(Debug, Clone)]
 pub enum EntryPoint<T: Config> {
     CreateClaim(T::Content),
+    CreateClaimWithMemo(T::Content, Vec<u8>),
+    CreateClaimWithExpiry(T::Content, T::BlockNumber),
     RevokeClaim(T::Content),
+    RenewClaim { claim: T::Content, expires_at: T::BlockNumber },
+    TransferClaim { claim: T::Content, new_owner: T::AccountId },
+    OfferClaim { claim: T::Content, to: T::AccountId },
+    AcceptClaim(T::Content),
+    CreateBatchClaim { merkle_root: Hash, leaf_count: u32 },
+    RevokeBatchClaim(Hash),
 }
 
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::CreateClaim(claim) => (0u8, claim).encode_to(dest),
+			EntryPoint::CreateClaimWithMemo(claim, memo) => (1u8, claim, memo).encode_to(dest),
+			EntryPoint::CreateClaimWithExpiry(claim, expires_at) => {
+				(2u8, claim, expires_at).encode_to(dest)
+			},
+			EntryPoint::RevokeClaim(claim) => (3u8, claim).encode_to(dest),
+			EntryPoint::RenewClaim { claim, expires_at } => {
+				(4u8, claim, expires_at).encode_to(dest)
+			},
+			EntryPoint::TransferClaim { claim, new_owner } => {
+				(5u8, claim, new_owner).encode_to(dest)
+			},
+			EntryPoint::OfferClaim { claim, to } => (6u8, claim, to).encode_to(dest),
+			EntryPoint::AcceptClaim(claim) => (7u8, claim).encode_to(dest),
+			EntryPoint::CreateBatchClaim { merkle_root, leaf_count } => {
+				(8u8, merkle_root, leaf_count).encode_to(dest)
+			},
+			EntryPoint::RevokeBatchClaim(merkle_root) => (9u8, merkle_root).encode_to(dest),
+		}
+	}
+}
+
+impl<T: Config> EntryPoint<T> {
+	/// Build a `CreateClaim` call for some arbitrary content.
+	///
+	/// The content is hashed here, on the client side, so that only its digest ends up in the
+	/// extrinsic and in storage.
+	pub fn create_claim_for_bytes(data: &[u8]) -> Self {
+		EntryPoint::CreateClaim(T::Hashing::hash(data))
+	}
+}
 
-/// Implementation of the dispatch logic, mapping from `BalancesCall` to the appropriate underlying
-/// function we want to execute.
-impl<T: Config> crate::support::Dispatch for Pallet<T> {
-	//we are pulling the AccountId type in the config trait
-	type Caller = T::AccountId;
-	type Call = EntryPoint<T>;
 
-	fn dispatch(
+impl<T: Config> Pallet<T> {
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	///
+	/// The runtime hands us the currency holding claim deposits, as described on
+	/// `support::Dispatch`.
+	pub fn dispatch(
 		&mut self,
-		caller: Self::Caller,
-		call: Self::Call,
-	) -> crate::support::DispatchResult {
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		call: EntryPoint<T>,
+	) -> DispatchResult {
 		match call {
-			EntryPoint::CreateClaim(claim) => self.create_claim(caller, claim)?,
-            EntryPoint::RevokeClaim(claim) => self.revoke_claim(caller, claim)?
+			EntryPoint::CreateClaim(claim) => self.create_claim(currency, caller, claim)?,
+			EntryPoint::CreateClaimWithMemo(claim, memo) => {
+				self.create_claim_with_memo(currency, caller, claim, memo)?
+			},
+			EntryPoint::CreateClaimWithExpiry(claim, expires_at) => {
+				self.create_claim_with_expiry(currency, caller, claim, expires_at)?
+			},
+            EntryPoint::RevokeClaim(claim) => self.revoke_claim(currency, caller, claim)?,
+			EntryPoint::RenewClaim { claim, expires_at } => {
+				self.renew_claim(caller, claim, expires_at)?
+			},
+			EntryPoint::TransferClaim { claim, new_owner } => {
+				self.transfer_claim(currency, caller, claim, new_owner)?
+			},
+			EntryPoint::OfferClaim { claim, to } => self.offer_claim(caller, claim, to)?,
+			EntryPoint::AcceptClaim(claim) => self.accept_claim(currency, caller, claim)?,
+			EntryPoint::CreateBatchClaim { merkle_root, leaf_count } => {
+				self.create_batch_claim(currency, caller, merkle_root, leaf_count)?
+			},
+			EntryPoint::RevokeBatchClaim(merkle_root) => {
+				self.revoke_batch_claim(currency, caller, merkle_root)?
+			},
 		}
 		Ok(())
 	}
-}
-
 
-impl<T: Config> Pallet<T> {
 	/// Create a new instance of the Proof of Existence Module.
 	pub fn new() -> Self {
-		Self { claims: BTreeMap::new() }
+		Self {
+			claims: BTreeMap::new(),
+			claims_by_owner: BTreeMap::new(),
+			batches: BTreeMap::new(),
+			expiries: BTreeMap::new(),
+			offers: BTreeMap::new(),
+			events: Vec::new(),
+			block_number: T::BlockNumber::zero(),
+			now: T::Moment::zero(),
+		}
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
 	}
-	/// Get the owner (if any) of a claim.
-	pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
-		self.claims.get(claim.to_owned())
+
+	/// Get the full record (if any) of a claim.
+	pub fn get_claim(&self, claim: &T::Content) -> Option<&ClaimInfoOf<T>> {
+		self.claims.get(claim)
+	}
+	/// Create a new claim on behalf of the `caller`, reserving `T::CLAIM_DEPOSIT` from their
+	/// balance. This function will return an error if someone already has claimed that content, or
+	/// if the caller can not afford the deposit.
+	pub fn create_claim(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		claim: T::Content,
+	) -> DispatchResult {
+		self.do_create_claim(currency, caller, claim, None, None)
 	}
-	/// Create a new claim on behalf of the `caller`.
-	/// This function will return an error if someone already has claimed that content.
-	pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+
+	/// Create a new claim on behalf of the `caller`, with a memo describing the content.
+	/// This function will return an error if the memo is longer than `T::MAX_MEMO_LENGTH`, or if
+	/// someone already has claimed that content.
+	pub fn create_claim_with_memo(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		claim: T::Content,
+		memo: Vec<u8>,
+	) -> DispatchResult {
+		if memo.len() > T::MAX_MEMO_LENGTH {
+			return Err("Memo is too long")
+		}
+		self.do_create_claim(currency, caller, claim, Some(memo), None)
+	}
+
+	/// Create a new claim on behalf of the `caller`, which is removed at block `expires_at` unless
+	/// it is renewed with `renew_claim`. This function will return an error if `expires_at` is not
+	/// in the future, or if someone already has claimed that content.
+	pub fn create_claim_with_expiry(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		claim: T::Content,
+		expires_at: T::BlockNumber,
+	) -> DispatchResult {
+		self.do_create_claim(currency, caller, claim, None, Some(expires_at))
+	}
+
+	fn do_create_claim(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		claim: T::Content,
+		memo: Option<Vec<u8>>,
+		expires_at: Option<T::BlockNumber>,
+	) -> DispatchResult {
 		if self.get_claim(&claim).is_some() {
 			return Err("This claim has already been created")
 		}
-		self.claims.insert(claim, caller);
+		if let Some(expires_at) = expires_at {
+			self.ensure_in_future(expires_at)?;
+		}
+		self.ensure_can_own(&caller)?;
+		currency.reserve(&caller, T::CLAIM_DEPOSIT)?;
+		self.index_insert(&caller, &claim);
+		if let Some(expires_at) = expires_at {
+			self.expiries.entry(expires_at).or_default().insert(claim.clone());
+		}
+		let info = ClaimInfo {
+			owner: caller,
+			deposit: T::CLAIM_DEPOSIT,
+			block_number: self.block_number,
+			created_at: self.now,
+			memo,
+			expires_at,
+		};
+		self.claims.insert(claim, info);
 		Ok(())
 	}
 
-	/// Revoke an existing claim on some content.
+	/// Revoke an existing claim on some content, unreserving its deposit.
 	/// This function should only succeed if the caller is the owner of an existing claim.
 	/// It will return an error if the claim does not exist, or if the caller is not the owner.
-	pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
+	pub fn revoke_claim(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		claim: T::Content,
+	) -> DispatchResult {
 		let content = self.claims.get(&claim).ok_or("Claim does not exist")?;
-		if content.cmp(&caller) != Ordering::Equal {
+		if content.owner.cmp(&caller) != Ordering::Equal {
+			return Err("Content does not match the caller")
+		}
+		self.remove_claim(currency, &claim);
+		Ok(())
+	}
+
+	/// Move the expiry of an existing claim to the later block `expires_at`.
+	/// This function should only succeed if the caller is the owner of an expiring claim, and
+	/// `expires_at` is after its current expiry.
+	pub fn renew_claim(
+		&mut self,
+		caller: T::AccountId,
+		claim: T::Content,
+		expires_at: T::BlockNumber,
+	) -> DispatchResult {
+		let info = self.claims.get(&claim).ok_or("Claim does not exist")?;
+		if info.owner != caller {
+			return Err("Content does not match the caller")
+		}
+		let old = info.expires_at.ok_or("Claim does not expire")?;
+		if expires_at <= old {
+			return Err("Renewal must extend the expiry")
+		}
+		self.expiry_remove(old, &claim);
+		self.expiries.entry(expires_at).or_default().insert(claim.clone());
+		if let Some(info) = self.claims.get_mut(&claim) {
+			info.expires_at = Some(expires_at);
+		}
+		self.deposit_event(Event::Renewed { claim, expires_at });
+		Ok(())
+	}
+
+	/// Remove all the claims which expire at or before block `n`, giving back their deposits.
+	///
+	/// The runtime calls this at the start of every block, right after `on_initialize`. It is not
+	/// part of `Hooks` since we need the currency to unreserve the deposits.
+	pub fn expire_claims(&mut self, currency: &mut T::Currency, n: T::BlockNumber) {
+		while let Some(entry) = self.expiries.first_entry() {
+			if *entry.key() > n {
+				break
+			}
+			for claim in entry.remove() {
+				if let Some(info) = self.remove_claim(currency, &claim) {
+					self.deposit_event(Event::Expired { claim, owner: info.owner });
+				}
+			}
+		}
+	}
+
+	/// Remove a claim from storage and from all the indexes, unreserving its deposit.
+	fn remove_claim(
+		&mut self,
+		currency: &mut T::Currency,
+		claim: &T::Content,
+	) -> Option<ClaimInfoOf<T>> {
+		let info = self.claims.remove(claim)?;
+		currency.unreserve(&info.owner, info.deposit);
+		self.index_remove(&info.owner, claim);
+		if let Some(expires_at) = info.expires_at {
+			self.expiry_remove(expires_at, claim);
+		}
+		self.offers.remove(claim);
+		Some(info)
+	}
+
+	/// Transfer an existing claim to `new_owner`.
+	/// This function should only succeed if the caller is the owner of an existing claim. The
+	/// claim keeps its original block number and memo, its deposit moves to the reserved balance
+	/// of the new owner, and any pending offer is cancelled.
+	pub fn transfer_claim(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		claim: T::Content,
+		new_owner: T::AccountId,
+	) -> DispatchResult {
+		let info = self.claims.get(&claim).ok_or("Claim does not exist")?;
+		if info.owner != caller {
+			return Err("Content does not match the caller")
+		}
+		self.do_transfer_claim(currency, claim, new_owner)
+	}
+
+	/// Offer to transfer an existing claim to `to`, who must accept it with `accept_claim`.
+	/// Only the owner of the claim can make an offer, and a new offer replaces any previous one.
+	pub fn offer_claim(
+		&mut self,
+		caller: T::AccountId,
+		claim: T::Content,
+		to: T::AccountId,
+	) -> DispatchResult {
+		let info = self.claims.get(&claim).ok_or("Claim does not exist")?;
+		if info.owner != caller {
 			return Err("Content does not match the caller")
 		}
-		self.claims.remove(&claim);
+		self.offers.insert(claim.clone(), to.clone());
+		self.deposit_event(Event::ClaimOffered { claim, from: caller, to });
 		Ok(())
 	}
+
+	/// Accept a pending offer for a claim, making the caller its new owner.
+	/// This function will return an error if the claim was not offered to the caller.
+	pub fn accept_claim(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		claim: T::Content,
+	) -> DispatchResult {
+		if self.offers.get(&claim) != Some(&caller) {
+			return Err("Claim was not offered to the caller")
+		}
+		self.do_transfer_claim(currency, claim, caller)
+	}
+
+	fn do_transfer_claim(
+		&mut self,
+		currency: &mut T::Currency,
+		claim: T::Content,
+		new_owner: T::AccountId,
+	) -> DispatchResult {
+		let info = self.claims.get(&claim).ok_or("Claim does not exist")?;
+		let from = info.owner.clone();
+		if from != new_owner {
+			self.ensure_can_own(&new_owner)?;
+			currency.repatriate_reserved(&from, &new_owner, info.deposit, BalanceStatus::Reserved)?;
+			self.index_remove(&from, &claim);
+			self.index_insert(&new_owner, &claim);
+		}
+		self.offers.remove(&claim);
+		if let Some(info) = self.claims.get_mut(&claim) {
+			info.owner = new_owner.clone();
+		}
+		self.deposit_event(Event::ClaimTransferred { claim, from, to: new_owner });
+		Ok(())
+	}
+
+	/// Get all the claims owned by `who`.
+	pub fn claims_of(&self, who: &T::AccountId) -> Vec<T::Content> {
+		self.claims_by_owner
+			.get(who)
+			.map(|claims| claims.iter().cloned().collect())
+			.unwrap_or_default()
+	}
+
+	/// Get the number of claims owned by `who`.
+	pub fn claim_count(&self, who: &T::AccountId) -> usize {
+		self.claims_by_owner.get(who).map(|claims| claims.len()).unwrap_or(0)
+	}
+
+	fn ensure_can_own(&self, who: &T::AccountId) -> DispatchResult {
+		if self.claim_count(who) >= T::MAX_CLAIMS_PER_ACCOUNT {
+			return Err("Account owns too many claims")
+		}
+		Ok(())
+	}
+
+	fn ensure_in_future(&self, n: T::BlockNumber) -> DispatchResult {
+		if n <= self.block_number {
+			return Err("Expiry must be in the future")
+		}
+		Ok(())
+	}
+
+	fn expiry_remove(&mut self, expires_at: T::BlockNumber, claim: &T::Content) {
+		if let Some(claims) = self.expiries.get_mut(&expires_at) {
+			claims.remove(claim);
+			if claims.is_empty() {
+				self.expiries.remove(&expires_at);
+			}
+		}
+	}
+
+	fn index_insert(&mut self, who: &T::AccountId, claim: &T::Content) {
+		self.claims_by_owner.entry(who.clone()).or_default().insert(claim.clone());
+	}
+
+	fn index_remove(&mut self, who: &T::AccountId, claim: &T::Content) {
+		if let Some(claims) = self.claims_by_owner.get_mut(who) {
+			claims.remove(claim);
+			if claims.is_empty() {
+				self.claims_by_owner.remove(who);
+			}
+		}
+	}
+
+	/// All the leaves of the claims Merkle tree, in the order of the `claims` storage.
+	fn claim_leaves(&self) -> Vec<Hash> {
+		self.claims.iter().map(|(content, info)| claim_leaf(content, info)).collect()
+	}
+
+	/// The Merkle root over all the claims in storage.
+	pub fn claims_root(&self) -> Hash {
+		merkle::merkle_root(&self.claim_leaves())
+	}
+
+	/// Build a proof that `claim` is included in `claims_root`.
+	/// Returns `None` if the claim does not exist.
+	pub fn claim_proof(&self, claim: &T::Content) -> Option<ClaimProof> {
+		let index = self.claims.keys().position(|content| content == claim)?;
+		merkle::merkle_proof(&self.claim_leaves(), index)
+	}
+
+	/// Anchor the Merkle root of a batch of `leaf_count` documents on behalf of the `caller`,
+	/// reserving a single `T::CLAIM_DEPOSIT` for the whole batch. See `ClaimBatch` to build the
+	/// tree. This function will return an error if the root has already been anchored.
+	pub fn create_batch_claim(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		merkle_root: Hash,
+		leaf_count: u32,
+	) -> DispatchResult {
+		if leaf_count == 0 {
+			return Err("Batch must not be empty")
+		}
+		if self.batches.contains_key(&merkle_root) {
+			return Err("This batch has already been anchored")
+		}
+		currency.reserve(&caller, T::CLAIM_DEPOSIT)?;
+		let info = BatchInfo {
+			owner: caller.clone(),
+			leaf_count,
+			deposit: T::CLAIM_DEPOSIT,
+			block_number: self.block_number,
+		};
+		self.batches.insert(merkle_root, info);
+		self.deposit_event(Event::BatchAnchored { merkle_root, owner: caller, leaf_count });
+		Ok(())
+	}
+
+	/// Revoke an anchored batch of claims, unreserving its deposit.
+	/// This function should only succeed if the caller is the owner of the batch.
+	pub fn revoke_batch_claim(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		merkle_root: Hash,
+	) -> DispatchResult {
+		let info = self.batches.get(&merkle_root).ok_or("Batch does not exist")?;
+		if info.owner != caller {
+			return Err("Batch does not match the caller")
+		}
+		currency.unreserve(&caller, info.deposit);
+		self.batches.remove(&merkle_root);
+		self.deposit_event(Event::BatchRevoked { merkle_root, owner: caller });
+		Ok(())
+	}
+
+	/// Check that `content` is part of the batch anchored with `merkle_root`, returning the
+	/// details of the batch if it is.
+	pub fn verify_batch_claim(
+		&self,
+		merkle_root: &Hash,
+		content: &T::Content,
+		proof: &ClaimProof,
+	) -> Option<&BatchInfoOf<T>> {
+		let info = self.batches.get(merkle_root)?;
+		let valid = proof.leaf_count == info.leaf_count &&
+			verify_batch_proof(merkle_root, content, proof);
+		valid.then_some(info)
+	}
 }
 
 
 
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, n: T::BlockNumber) {
+		self.block_number = n;
+		self.events.clear();
+	}
+}
+
+impl<T: Config> ClaimOwnership<T::AccountId, T::Content> for Pallet<T> {
+	fn claim_owner(&self, content: &T::Content) -> Option<T::AccountId> {
+		self.claims.get(content).map(|claim| claim.owner.clone())
+	}
+}
+
+impl<T: Config> OnTimestampSet<T::Moment> for Pallet<T> {
+	fn on_timestamp_set(&mut self, moment: T::Moment) {
+		self.now = moment;
+	}
+}
+
 #[cfg(test)]
 mod test {
+	use crate::{
+		hashing::{Hasher, Sha256},
+		mock::MockCurrency,
+		support::ReservableCurrency,
+	};
+
+	#[derive(Debug, Clone, PartialEq, Eq)]
 	pub struct Test {}
 
 	impl super::Config for Test {
-		type Content = &'static str;
+		type Hashing = Sha256;
+		type Content = crate::hashing::Hash;
+		const MAX_MEMO_LENGTH: usize = 16;
+		const MAX_CLAIMS_PER_ACCOUNT: usize = 2;
+		type Currency = MockCurrency<&'static str>;
+		const CLAIM_DEPOSIT: u128 = 10;
+		type Moment = u64;
 	}
 
 	impl crate::types::Config for Test {
@@ -97,17 +719,327 @@ mod test {
 		type Nonce = u32;
 		type Balance = u128;
 	}
+
+	/// A new pallet, and a currency where alice, bob and charlie have 100 each.
+	fn new_test() -> (super::Pallet<Test>, MockCurrency<&'static str>) {
+		let mut balances = MockCurrency::new();
+		for who in ["alice", "bob", "charlie"] {
+			balances.set_balance(&who, 100);
+		}
+		(super::Pallet::new(), balances)
+	}
+
 	#[test]
 	fn basic_proof_of_existence() {
-		let mut pallet = super::Pallet::<Test>::new();
-		assert_eq!(pallet.get_claim(&"Hello, world!"), None);
-		assert_eq!(pallet.create_claim("alice", "Hello, world!"), Ok(()));
-		assert_eq!(pallet.get_claim(&"Hello, world!"), Some(&"alice"));
+		let (mut pallet, mut balances) = new_test();
+		let hello = Sha256::hash(b"Hello, world!");
+		assert_eq!(pallet.get_claim(&hello), None);
+		assert_eq!(pallet.create_claim(&mut balances, "alice", hello), Ok(()));
+		assert_eq!(pallet.get_claim(&hello).map(|info| info.owner), Some("alice"));
 		assert_eq!(
-			pallet.create_claim("bob", "Hello, world!"),
+			pallet.create_claim(&mut balances, "bob", hello),
 			Err("This claim has already been created")
 		);
-		assert_eq!(pallet.revoke_claim("alice", "Hello, world!"), Ok(()));
-		assert_eq!(pallet.create_claim("bob", "Hello, world!"), Ok(()));
+		assert_eq!(pallet.revoke_claim(&mut balances, "alice", hello), Ok(()));
+		assert_eq!(pallet.create_claim(&mut balances, "bob", hello), Ok(()));
+	}
+
+	#[test]
+	fn claim_deposits() {
+		let (mut pallet, mut balances) = new_test();
+		let hello = Sha256::hash(b"Hello, world!");
+		balances.set_balance(&"charlie", 9);
+		assert_eq!(pallet.create_claim(&mut balances, "charlie", hello), Err("Not enough funds."));
+		assert_eq!(pallet.get_claim(&hello), None);
+
+		pallet.create_claim(&mut balances, "alice", hello).unwrap();
+		assert_eq!(pallet.get_claim(&hello).unwrap().deposit, 10);
+		assert_eq!(balances.balance(&"alice"), 90);
+		assert_eq!(balances.reserved_balance(&"alice"), 10);
+
+		// The deposit moves with the claim.
+		pallet.transfer_claim(&mut balances, "alice", hello, "bob").unwrap();
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(balances.reserved_balance(&"bob"), 10);
+		assert_eq!(balances.balance(&"alice"), 90);
+
+		// And is given back to the owner when the claim is revoked.
+		pallet.revoke_claim(&mut balances, "bob", hello).unwrap();
+		assert_eq!(balances.reserved_balance(&"bob"), 0);
+		assert_eq!(balances.balance(&"bob"), 110);
+	}
+
+	#[test]
+	fn claim_metadata() {
+		use crate::support::{Hooks, OnTimestampSet};
+		let (mut pallet, mut balances) = new_test();
+		let hello = Sha256::hash(b"Hello, world!");
+		let goodbye = Sha256::hash(b"Goodbye, world!");
+		pallet.on_initialize(7);
+		pallet.on_timestamp_set(42_000);
+		assert_eq!(
+			pallet.create_claim_with_memo(&mut balances, "alice", hello, b"greeting".to_vec()),
+			Ok(())
+		);
+		assert_eq!(
+			pallet.get_claim(&hello),
+			Some(&super::ClaimInfo {
+				owner: "alice",
+				deposit: 10,
+				block_number: 7,
+				created_at: 42_000,
+				memo: Some(b"greeting".to_vec()),
+				expires_at: None,
+			})
+		);
+		pallet.on_initialize(8);
+		pallet.on_timestamp_set(48_000);
+		assert_eq!(
+			pallet.create_claim_with_memo(&mut balances, "bob", goodbye, vec![0u8; 17]),
+			Err("Memo is too long")
+		);
+		assert_eq!(pallet.create_claim(&mut balances, "bob", goodbye), Ok(()));
+		assert_eq!(
+			pallet.get_claim(&goodbye),
+			Some(&super::ClaimInfo {
+				owner: "bob",
+				deposit: 10,
+				block_number: 8,
+				created_at: 48_000,
+				memo: None,
+				expires_at: None,
+			})
+		);
+	}
+
+	#[test]
+	fn expiring_claims() {
+		use super::Event;
+		use crate::support::Hooks;
+		let (mut pallet, mut balances) = new_test();
+		let hello = Sha256::hash(b"Hello, world!");
+		let goodbye = Sha256::hash(b"Goodbye, world!");
+		pallet.on_initialize(5);
+		assert_eq!(
+			pallet.create_claim_with_expiry(&mut balances, "alice", hello, 5),
+			Err("Expiry must be in the future")
+		);
+		pallet.create_claim_with_expiry(&mut balances, "alice", hello, 7).unwrap();
+		pallet.create_claim_with_expiry(&mut balances, "bob", goodbye, 7).unwrap();
+		assert_eq!(
+			pallet.renew_claim("bob", hello, 9),
+			Err("Content does not match the caller")
+		);
+		assert_eq!(pallet.renew_claim("alice", hello, 6), Err("Renewal must extend the expiry"));
+		assert_eq!(pallet.renew_claim("alice", hello, 7), Err("Renewal must extend the expiry"));
+		assert_eq!(pallet.renew_claim("alice", hello, 9), Ok(()));
+		assert_eq!(pallet.get_claim(&hello).unwrap().expires_at, Some(9));
+
+		// Nothing expires before block 7.
+		pallet.on_initialize(6);
+		pallet.expire_claims(&mut balances, 6);
+		assert_eq!(pallet.claims.len(), 2);
+
+		pallet.on_initialize(7);
+		pallet.expire_claims(&mut balances, 7);
+		assert_eq!(pallet.get_claim(&goodbye), None);
+		assert_eq!(pallet.claim_count(&"bob"), 0);
+		assert_eq!(balances.reserved_balance(&"bob"), 0);
+		assert_eq!(pallet.events, vec![Event::Expired { claim: goodbye, owner: "bob" }]);
+
+		// Revoking a claim also removes it from the expiry index.
+		pallet.revoke_claim(&mut balances, "alice", hello).unwrap();
+		assert!(pallet.expiries.is_empty());
+
+		// Renewing can not put an expiry on a permanent claim.
+		pallet.create_claim(&mut balances, "alice", hello).unwrap();
+		assert_eq!(pallet.renew_claim("alice", hello, 9), Err("Claim does not expire"));
+	}
+
+	#[test]
+	fn transfer_claim() {
+		use super::Event;
+		let (mut pallet, mut balances) = new_test();
+		let hello = Sha256::hash(b"Hello, world!");
+		assert_eq!(
+			pallet.transfer_claim(&mut balances, "alice", hello, "bob"),
+			Err("Claim does not exist")
+		);
+		pallet.create_claim(&mut balances, "alice", hello).unwrap();
+		assert_eq!(
+			pallet.transfer_claim(&mut balances, "bob", hello, "bob"),
+			Err("Content does not match the caller")
+		);
+		assert_eq!(pallet.transfer_claim(&mut balances, "alice", hello, "bob"), Ok(()));
+		assert_eq!(pallet.get_claim(&hello).unwrap().owner, "bob");
+		assert_eq!(
+			pallet.events,
+			vec![Event::ClaimTransferred { claim: hello, from: "alice", to: "bob" }]
+		);
+		// alice no longer owns the claim.
+		assert_eq!(
+			pallet.revoke_claim(&mut balances, "alice", hello),
+			Err("Content does not match the caller")
+		);
+	}
+
+	#[test]
+	fn offer_and_accept_claim() {
+		use super::Event;
+		let (mut pallet, mut balances) = new_test();
+		let hello = Sha256::hash(b"Hello, world!");
+		pallet.create_claim(&mut balances, "alice", hello).unwrap();
+		assert_eq!(
+			pallet.offer_claim("bob", hello, "bob"),
+			Err("Content does not match the caller")
+		);
+		assert_eq!(
+			pallet.accept_claim(&mut balances, "bob", hello),
+			Err("Claim was not offered to the caller")
+		);
+		assert_eq!(pallet.offer_claim("alice", hello, "bob"), Ok(()));
+		assert_eq!(
+			pallet.accept_claim(&mut balances, "charlie", hello),
+			Err("Claim was not offered to the caller")
+		);
+		// The claim still belongs to alice until bob accepts.
+		assert_eq!(pallet.get_claim(&hello).unwrap().owner, "alice");
+		assert_eq!(pallet.accept_claim(&mut balances, "bob", hello), Ok(()));
+		assert_eq!(pallet.get_claim(&hello).unwrap().owner, "bob");
+		assert_eq!(
+			pallet.events,
+			vec![
+				Event::ClaimOffered { claim: hello, from: "alice", to: "bob" },
+				Event::ClaimTransferred { claim: hello, from: "alice", to: "bob" },
+			]
+		);
+		// The offer is consumed.
+		assert_eq!(
+			pallet.accept_claim(&mut balances, "bob", hello),
+			Err("Claim was not offered to the caller")
+		);
+	}
+
+	#[test]
+	fn claims_by_owner() {
+		let (mut pallet, mut balances) = new_test();
+		let claims = [b"one", b"two", b"six"].map(|content| Sha256::hash(content));
+		assert!(pallet.claims_of(&"alice").is_empty());
+		pallet.create_claim(&mut balances, "alice", claims[0]).unwrap();
+		pallet.create_claim(&mut balances, "alice", claims[1]).unwrap();
+		assert_eq!(
+			pallet.create_claim(&mut balances, "alice", claims[2]),
+			Err("Account owns too many claims")
+		);
+		pallet.create_claim(&mut balances, "bob", claims[2]).unwrap();
+		assert_eq!(pallet.claim_count(&"alice"), 2);
+		assert_eq!(pallet.claim_count(&"bob"), 1);
+
+		// bob can only take one more claim.
+		pallet.transfer_claim(&mut balances, "alice", claims[0], "bob").unwrap();
+		assert_eq!(
+			pallet.transfer_claim(&mut balances, "alice", claims[1], "bob"),
+			Err("Account owns too many claims")
+		);
+		pallet.offer_claim("alice", claims[1], "bob").unwrap();
+		assert_eq!(
+			pallet.accept_claim(&mut balances, "bob", claims[1]),
+			Err("Account owns too many claims")
+		);
+		assert_eq!(pallet.get_claim(&claims[1]).unwrap().owner, "alice");
+
+		let mut bobs = vec![claims[0], claims[2]];
+		bobs.sort();
+		assert_eq!(pallet.claims_of(&"bob"), bobs);
+		assert_eq!(pallet.claims_of(&"alice"), vec![claims[1]]);
+
+		pallet.revoke_claim(&mut balances, "alice", claims[1]).unwrap();
+		assert_eq!(pallet.claim_count(&"alice"), 0);
+		assert!(!pallet.claims_by_owner.contains_key("alice"));
+	}
+
+	#[test]
+	fn batch_claims() {
+		use super::{ClaimBatch, Event};
+		let (mut pallet, mut balances) = new_test();
+		let documents: Vec<&[u8]> = vec![b"one", b"two", b"three", b"four", b"five"];
+		let batch = ClaimBatch::<Test>::from_documents(&documents);
+		let root = batch.root();
+		assert_eq!(
+			pallet.create_batch_claim(&mut balances, "alice", root, 0),
+			Err("Batch must not be empty")
+		);
+		assert_eq!(pallet.dispatch(&mut balances, "alice", batch.create_call()), Ok(()));
+		assert_eq!(
+			pallet.dispatch(&mut balances, "bob", batch.create_call()),
+			Err("This batch has already been anchored")
+		);
+		// A single deposit covers the whole batch.
+		assert_eq!(balances.reserved_balance(&"alice"), 10);
+		assert_eq!(
+			pallet.events,
+			vec![Event::BatchAnchored { merkle_root: root, owner: "alice", leaf_count: 5 }]
+		);
+
+		for document in &documents {
+			let content = Sha256::hash(document);
+			let proof = batch.proof(&content).unwrap();
+			assert!(super::verify_batch_proof(&root, &content, &proof));
+			let info = pallet.verify_batch_claim(&root, &content, &proof).unwrap();
+			assert_eq!(info.owner, "alice");
+		}
+		let other = Sha256::hash(b"six");
+		assert_eq!(batch.proof(&other), None);
+		let proof = batch.proof(&Sha256::hash(b"one")).unwrap();
+		assert!(!super::verify_batch_proof(&root, &other, &proof));
+
+		assert_eq!(
+			pallet.revoke_batch_claim(&mut balances, "bob", root),
+			Err("Batch does not match the caller")
+		);
+		assert_eq!(pallet.revoke_batch_claim(&mut balances, "alice", root), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(pallet.verify_batch_claim(&root, &Sha256::hash(b"one"), &proof), None);
+	}
+
+	#[test]
+	fn create_claim_for_bytes() {
+		let (mut pallet, mut balances) = new_test();
+		let document = vec![42u8; 1 << 20];
+		let call = super::EntryPoint::<Test>::create_claim_for_bytes(&document);
+		assert_eq!(pallet.dispatch(&mut balances, "alice", call), Ok(()));
+		// Only the digest of the document is stored.
+		let info = pallet.get_claim(&Sha256::hash(&document)).unwrap();
+		assert_eq!(info.owner, "alice");
+		assert_eq!(pallet.claims.len(), 1);
+	}
+
+	#[test]
+	fn claim_proofs() {
+		let (mut pallet, mut balances) = new_test();
+		let hello = Sha256::hash(b"Hello, world!");
+		let goodbye = Sha256::hash(b"Goodbye, world!");
+		assert_eq!(pallet.claim_proof(&hello), None);
+		pallet.create_claim(&mut balances, "alice", hello).unwrap();
+		pallet.create_claim(&mut balances, "bob", goodbye).unwrap();
+		pallet.create_claim(&mut balances, "charlie", Sha256::hash(b"Another claim")).unwrap();
+
+		let root = pallet.claims_root();
+		let proof = pallet.claim_proof(&hello).unwrap();
+		let info = pallet.get_claim(&hello).unwrap().clone();
+		assert!(super::verify_claim_proof(&root, &hello, &info, &proof));
+		// The proof is tied to the content and to every field of the claim.
+		assert!(!super::verify_claim_proof(&root, &goodbye, &info, &proof));
+		let forged = super::ClaimInfo { owner: "bob", ..info.clone() };
+		assert!(!super::verify_claim_proof(&root, &hello, &forged, &proof));
+		let forged = super::ClaimInfo { block_number: 7, ..info.clone() };
+		assert!(!super::verify_claim_proof(&root, &hello, &forged, &proof));
+		let forged = super::ClaimInfo { memo: Some(b"backdated".to_vec()), ..info.clone() };
+		assert!(!super::verify_claim_proof(&root, &hello, &forged, &proof));
+
+		// Once the claim is revoked, the old proof no longer matches the new root.
+		pallet.revoke_claim(&mut balances, "alice", hello).unwrap();
+		let root = pallet.claims_root();
+		assert!(!super::verify_claim_proof(&root, &hello, &info, &proof));
 	}
 }
@@ -0,0 +1,537 @@
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+
+use num::{CheckedAdd, Zero};
+
+use crate::{
+	hashing::{Hash, Hasher},
+	support::{Dispatch, DispatchResult, Encode, Hooks, InstanceFilter, ReservableCurrency},
+};
+
+pub trait Config: crate::types::Config {
+	/// The outer call type of the runtime, which a proxy can dispatch.
+	type RuntimeCall: Clone + Debug + Encode;
+	/// The kinds of proxy an account can register, each allowing a different set of calls.
+	type ProxyType: InstanceFilter<Self::RuntimeCall> + Copy + Eq + Debug + Encode;
+	/// The hash function used to identify an announced call.
+	type Hashing: Hasher<Output = Hash>;
+	/// The currency used to hold the deposit of each proxy.
+	type Currency: ReservableCurrency<Self::AccountId, Balance = Self::Balance>;
+	/// The amount reserved from the real account for each proxy it registers.
+	const PROXY_DEPOSIT: Self::Balance;
+	/// The maximum number of proxies of a single account.
+	const MAX_PROXIES: usize;
+	/// The maximum number of calls a delegate can have announced at the same time.
+	const MAX_PENDING: usize;
+}
+
+/// A delegate which can dispatch some calls on behalf of an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyDefinition<AccountId, ProxyType, BlockNumber> {
+	/// The account which can make calls on behalf of the real account.
+	pub delegate: AccountId,
+	/// Which calls the delegate is allowed to make.
+	pub proxy_type: ProxyType,
+	/// How many blocks a call must be announced before the delegate can make it.
+	pub delay: BlockNumber,
+}
+
+/// The `ProxyDefinition` stored by a runtime configured with `T`.
+pub type ProxyDefinitionOf<T> = ProxyDefinition<
+	<T as crate::types::Config>::AccountId,
+	<T as Config>::ProxyType,
+	<T as crate::types::Config>::BlockNumber,
+>;
+
+/// A call a delegate announced it will make on behalf of `real`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Announcement<AccountId, BlockNumber> {
+	/// The account the call will be made for.
+	pub real: AccountId,
+	/// The hash of the call.
+	pub call_hash: Hash,
+	/// The block in which the call was announced.
+	pub height: BlockNumber,
+}
+
+/// The `Announcement` stored by a runtime configured with `T`.
+pub type AnnouncementOf<T> =
+	Announcement<<T as crate::types::Config>::AccountId, <T as crate::types::Config>::BlockNumber>;
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// `delegator` registered `delegatee` as a proxy.
+	ProxyAdded {
+		delegator: T::AccountId,
+		delegatee: T::AccountId,
+		proxy_type: T::ProxyType,
+		delay: T::BlockNumber,
+	},
+	/// `delegator` removed `delegatee` as a proxy.
+	ProxyRemoved { delegator: T::AccountId, delegatee: T::AccountId, proxy_type: T::ProxyType },
+	/// `proxy` announced it will make the call with `call_hash` on behalf of `real`.
+	Announced { real: T::AccountId, proxy: T::AccountId, call_hash: Hash },
+	/// A call was dispatched on behalf of `real`, with `result`.
+	ProxyExecuted { real: T::AccountId, result: DispatchResult },
+}
+
+/// This is the Proxy Module.
+/// It lets an account register delegates which can dispatch some of its calls for it, without
+/// having access to its keys.
+///
+/// Proxied calls are dispatched through the runtime itself, so `proxy` takes the whole runtime
+/// rather than `&mut self`. The runtime gives it access to this pallet through `AsMut`.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The proxies registered by each real account.
+	pub proxies: BTreeMap<T::AccountId, Vec<ProxyDefinitionOf<T>>>,
+	/// The calls announced by each delegate.
+	pub announcements: BTreeMap<T::AccountId, Vec<AnnouncementOf<T>>>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+	/// The number of the block currently being executed, as given to us in `on_initialize`.
+	block_number: T::BlockNumber,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	AddProxy { delegate: T::AccountId, proxy_type: T::ProxyType, delay: T::BlockNumber },
+	RemoveProxy { delegate: T::AccountId, proxy_type: T::ProxyType, delay: T::BlockNumber },
+	Announce { real: T::AccountId, call_hash: Hash },
+	RemoveAnnouncement { real: T::AccountId, call_hash: Hash },
+	Proxy { real: T::AccountId, call: Box<T::RuntimeCall> },
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::AddProxy { delegate, proxy_type, delay } => {
+				(0u8, delegate, proxy_type, delay).encode_to(dest)
+			},
+			EntryPoint::RemoveProxy { delegate, proxy_type, delay } => {
+				(1u8, delegate, proxy_type, delay).encode_to(dest)
+			},
+			EntryPoint::Announce { real, call_hash } => (2u8, real, call_hash).encode_to(dest),
+			EntryPoint::RemoveAnnouncement { real, call_hash } => {
+				(3u8, real, call_hash).encode_to(dest)
+			},
+			EntryPoint::Proxy { real, call } => (4u8, real, call).encode_to(dest),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Proxy Module.
+	pub fn new() -> Self {
+		Self {
+			proxies: BTreeMap::new(),
+			announcements: BTreeMap::new(),
+			events: Vec::new(),
+			block_number: T::BlockNumber::zero(),
+		}
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
+	}
+
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	pub fn dispatch<R>(runtime: &mut R, caller: T::AccountId, call: EntryPoint<T>) -> DispatchResult
+	where
+		R: Dispatch<Call = T::RuntimeCall> + AsMut<Self> + AsMut<T::Currency>,
+		R::Caller: From<T::AccountId>,
+	{
+		match call {
+			EntryPoint::AddProxy { delegate, proxy_type, delay } => {
+				let currency: &mut T::Currency = runtime.as_mut();
+				currency.reserve(&caller, T::PROXY_DEPOSIT)?;
+				let pallet: &mut Self = runtime.as_mut();
+				let result = pallet.add_proxy(caller.clone(), delegate, proxy_type, delay);
+				if result.is_err() {
+					let currency: &mut T::Currency = runtime.as_mut();
+					currency.unreserve(&caller, T::PROXY_DEPOSIT);
+				}
+				result
+			},
+			EntryPoint::RemoveProxy { delegate, proxy_type, delay } => {
+				let pallet: &mut Self = runtime.as_mut();
+				pallet.remove_proxy(caller.clone(), delegate, proxy_type, delay)?;
+				let currency: &mut T::Currency = runtime.as_mut();
+				currency.unreserve(&caller, T::PROXY_DEPOSIT);
+				Ok(())
+			},
+			EntryPoint::Announce { real, call_hash } => {
+				let pallet: &mut Self = runtime.as_mut();
+				pallet.announce(caller, real, call_hash)
+			},
+			EntryPoint::RemoveAnnouncement { real, call_hash } => {
+				let pallet: &mut Self = runtime.as_mut();
+				pallet.remove_announcement(caller, real, call_hash)
+			},
+			EntryPoint::Proxy { real, call } => Self::proxy(runtime, caller, real, *call),
+		}
+	}
+
+	/// Register `delegate` as a proxy of the `caller`, allowed to make the calls of `proxy_type`
+	/// once they have been announced for `delay` blocks. The runtime reserves `T::PROXY_DEPOSIT`
+	/// from the `caller` for it.
+	pub fn add_proxy(
+		&mut self,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: T::ProxyType,
+		delay: T::BlockNumber,
+	) -> DispatchResult {
+		if caller == delegate {
+			return Err("An account can not be its own proxy")
+		}
+		let proxies = self.proxies.entry(caller.clone()).or_default();
+		let definition = ProxyDefinition { delegate: delegate.clone(), proxy_type, delay };
+		if proxies.contains(&definition) {
+			return Err("This proxy already exists")
+		}
+		if proxies.len() >= T::MAX_PROXIES {
+			return Err("Too many proxies")
+		}
+		proxies.push(definition);
+		self.deposit_event(Event::ProxyAdded {
+			delegator: caller,
+			delegatee: delegate,
+			proxy_type,
+			delay,
+		});
+		Ok(())
+	}
+
+	/// Remove a proxy of the `caller` registered with `add_proxy`.
+	pub fn remove_proxy(
+		&mut self,
+		caller: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: T::ProxyType,
+		delay: T::BlockNumber,
+	) -> DispatchResult {
+		let proxies = self.proxies.get_mut(&caller).ok_or("Proxy does not exist")?;
+		let definition = ProxyDefinition { delegate: delegate.clone(), proxy_type, delay };
+		let index = proxies.iter().position(|p| p == &definition).ok_or("Proxy does not exist")?;
+		proxies.remove(index);
+		if proxies.is_empty() {
+			self.proxies.remove(&caller);
+		}
+		self.deposit_event(Event::ProxyRemoved {
+			delegator: caller,
+			delegatee: delegate,
+			proxy_type,
+		});
+		Ok(())
+	}
+
+	/// Announce that the `caller` will make the call with `call_hash` on behalf of `real`, so that
+	/// `real` can react before the delay of the proxy is over.
+	pub fn announce(
+		&mut self,
+		caller: T::AccountId,
+		real: T::AccountId,
+		call_hash: Hash,
+	) -> DispatchResult {
+		if self.find_proxies(&real, &caller).next().is_none() {
+			return Err("Caller is not a proxy of this account")
+		}
+		let announcements = self.announcements.entry(caller.clone()).or_default();
+		if announcements.len() >= T::MAX_PENDING {
+			return Err("Too many pending announcements")
+		}
+		let height = self.block_number;
+		announcements.push(Announcement { real: real.clone(), call_hash, height });
+		self.deposit_event(Event::Announced { real, proxy: caller, call_hash });
+		Ok(())
+	}
+
+	/// Withdraw a call the `caller` announced with `announce`.
+	pub fn remove_announcement(
+		&mut self,
+		caller: T::AccountId,
+		real: T::AccountId,
+		call_hash: Hash,
+	) -> DispatchResult {
+		let announcements = self.announcements.get_mut(&caller).ok_or("Announcement not found")?;
+		let index = announcements
+			.iter()
+			.position(|a| a.real == real && a.call_hash == call_hash)
+			.ok_or("Announcement not found")?;
+		announcements.remove(index);
+		if announcements.is_empty() {
+			self.announcements.remove(&caller);
+		}
+		Ok(())
+	}
+
+	/// Dispatch `call` on behalf of `real`, if the `caller` is a proxy of `real` whose type allows
+	/// it. A proxy with a delay must have announced the call at least that many blocks ago.
+	pub fn proxy<R>(
+		runtime: &mut R,
+		caller: T::AccountId,
+		real: T::AccountId,
+		call: T::RuntimeCall,
+	) -> DispatchResult
+	where
+		R: Dispatch<Call = T::RuntimeCall> + AsMut<Self>,
+		R::Caller: From<T::AccountId>,
+	{
+		let pallet: &mut Self = runtime.as_mut();
+		let delays = pallet
+			.find_proxies(&real, &caller)
+			.filter(|p| p.proxy_type.filter(&call))
+			.map(|p| p.delay)
+			.collect::<Vec<_>>();
+		if delays.is_empty() {
+			return Err("Caller is not a proxy of this account for this call")
+		}
+		if !delays.iter().any(|delay| delay.is_zero()) {
+			pallet.use_announcement(&caller, &real, &call)?;
+		}
+
+		let result = runtime.dispatch(real.clone().into(), call);
+		let pallet: &mut Self = runtime.as_mut();
+		pallet.deposit_event(Event::ProxyExecuted { real, result });
+		Ok(())
+	}
+
+	/// Get the proxies of `real` which have `delegate` as their delegate.
+	fn find_proxies<'a>(
+		&'a self,
+		real: &T::AccountId,
+		delegate: &'a T::AccountId,
+	) -> impl Iterator<Item = &'a ProxyDefinitionOf<T>> {
+		self.proxies
+			.get(real)
+			.into_iter()
+			.flatten()
+			.filter(move |definition| &definition.delegate == delegate)
+	}
+
+	/// Remove the announcement of `call` by `delegate` for `real`, checking that it is older than
+	/// the delay of one of the proxies allowing the call.
+	fn use_announcement(
+		&mut self,
+		delegate: &T::AccountId,
+		real: &T::AccountId,
+		call: &T::RuntimeCall,
+	) -> DispatchResult {
+		let call_hash = T::Hashing::hash(&call.encode());
+		let now = self.block_number;
+		let delays = self
+			.find_proxies(real, delegate)
+			.filter(|p| p.proxy_type.filter(call))
+			.map(|p| p.delay)
+			.collect::<Vec<_>>();
+		let announcements = self.announcements.get_mut(delegate).ok_or("Call was not announced")?;
+		let index = announcements
+			.iter()
+			.position(|a| {
+				&a.real == real
+					&& a.call_hash == call_hash
+					&& delays
+						.iter()
+						.any(|delay| a.height.checked_add(delay).is_some_and(|ready| ready <= now))
+			})
+			.ok_or("Call was not announced long enough ago")?;
+		announcements.remove(index);
+		if announcements.is_empty() {
+			self.announcements.remove(delegate);
+		}
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, n: T::BlockNumber) {
+		self.block_number = n;
+		self.events.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::EntryPoint;
+	use crate::{
+		balances,
+		hashing::{Hasher, Sha256},
+		support::{Dispatch, Encode, Hooks, InstanceFilter, ReservableCurrency},
+	};
+
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum ProxyType {
+		Any,
+		NonTransfer,
+	}
+
+	impl Encode for ProxyType {
+		fn encode_to(&self, dest: &mut Vec<u8>) {
+			(*self as u8).encode_to(dest)
+		}
+	}
+
+	impl InstanceFilter<TestCall> for ProxyType {
+		fn filter(&self, call: &TestCall) -> bool {
+			match self {
+				ProxyType::Any => true,
+				ProxyType::NonTransfer => !matches!(call, TestCall::Balances(_)),
+			}
+		}
+	}
+
+	#[derive(Debug, Clone)]
+	pub struct Test;
+
+	impl super::Config for Test {
+		type RuntimeCall = TestCall;
+		type ProxyType = ProxyType;
+		type Hashing = Sha256;
+		type Currency = balances::Pallet<Test>;
+		const PROXY_DEPOSIT: u128 = 10;
+		const MAX_PROXIES: usize = 2;
+		const MAX_PENDING: usize = 2;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	impl balances::Config for Test {
+		const MAX_TRANSFERS_PER_BATCH: usize = 100;
+	}
+
+	#[derive(Debug, Clone)]
+	pub enum TestCall {
+		Balances(balances::EntryPoint<Test>),
+		Proxy(EntryPoint<Test>),
+	}
+
+	impl Encode for TestCall {
+		fn encode_to(&self, dest: &mut Vec<u8>) {
+			match self {
+				TestCall::Balances(call) => (0u8, call).encode_to(dest),
+				TestCall::Proxy(call) => (1u8, call).encode_to(dest),
+			}
+		}
+	}
+
+	/// A runtime with just enough pallets to dispatch transfers through a proxy.
+	struct TestRuntime {
+		balances: balances::Pallet<Test>,
+		proxy: super::Pallet<Test>,
+	}
+
+	impl AsMut<super::Pallet<Test>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Test> {
+			&mut self.proxy
+		}
+	}
+
+	impl AsMut<balances::Pallet<Test>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut balances::Pallet<Test> {
+			&mut self.balances
+		}
+	}
+
+	impl Dispatch for TestRuntime {
+		type Caller = String;
+		type Call = TestCall;
+
+		fn dispatch(&mut self, caller: String, call: TestCall) -> crate::support::DispatchResult {
+			match call {
+				TestCall::Balances(call) => self.balances.dispatch(caller, call),
+				TestCall::Proxy(call) => super::Pallet::dispatch(self, caller, call),
+			}
+		}
+	}
+
+	fn new_runtime() -> TestRuntime {
+		let mut balances = balances::Pallet::new();
+		balances.set_balance(&"alice".to_string(), 100);
+		TestRuntime { balances, proxy: super::Pallet::new() }
+	}
+
+	fn transfer(amount: u128) -> TestCall {
+		TestCall::Balances(balances::EntryPoint::Transfer { to: "charlie".to_string(), amount })
+	}
+
+	fn proxied(call: TestCall) -> TestCall {
+		TestCall::Proxy(EntryPoint::Proxy { real: "alice".to_string(), call: Box::new(call) })
+	}
+
+	fn add_proxy(proxy_type: ProxyType, delay: u32) -> TestCall {
+		TestCall::Proxy(EntryPoint::AddProxy { delegate: "bob".to_string(), proxy_type, delay })
+	}
+
+	#[test]
+	fn proxy_types_filter_calls() {
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		let mut runtime = new_runtime();
+		assert_eq!(
+			runtime.dispatch(bob.clone(), proxied(transfer(10))),
+			Err("Caller is not a proxy of this account for this call")
+		);
+
+		runtime.dispatch(alice.clone(), add_proxy(ProxyType::NonTransfer, 0)).unwrap();
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+		assert_eq!(
+			runtime.dispatch(bob.clone(), proxied(transfer(10))),
+			Err("Caller is not a proxy of this account for this call")
+		);
+
+		runtime.dispatch(alice.clone(), add_proxy(ProxyType::Any, 0)).unwrap();
+		assert_eq!(runtime.dispatch(bob.clone(), proxied(transfer(10))), Ok(()));
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 10);
+		assert_eq!(
+			runtime.dispatch(alice.clone(), add_proxy(ProxyType::Any, 5)),
+			Err("Too many proxies")
+		);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 20);
+
+		let remove =
+			EntryPoint::RemoveProxy { delegate: bob, proxy_type: ProxyType::Any, delay: 0 };
+		runtime.dispatch(alice.clone(), TestCall::Proxy(remove)).unwrap();
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+	}
+
+	#[test]
+	fn delayed_proxy() {
+		let (alice, bob) = ("alice".to_string(), "bob".to_string());
+		let mut runtime = new_runtime();
+		runtime.proxy.on_initialize(1);
+		runtime.dispatch(alice.clone(), add_proxy(ProxyType::Any, 3)).unwrap();
+		assert_eq!(
+			runtime.dispatch(bob.clone(), proxied(transfer(10))),
+			Err("Call was not announced")
+		);
+
+		let call_hash = Sha256::hash(&transfer(10).encode());
+		let announce = EntryPoint::Announce { real: alice, call_hash };
+		runtime.dispatch(bob.clone(), TestCall::Proxy(announce)).unwrap();
+		runtime.proxy.on_initialize(3);
+		assert_eq!(
+			runtime.dispatch(bob.clone(), proxied(transfer(10))),
+			Err("Call was not announced long enough ago")
+		);
+		runtime.proxy.on_initialize(4);
+		// Only the announced call can be made.
+		assert_eq!(
+			runtime.dispatch(bob.clone(), proxied(transfer(11))),
+			Err("Call was not announced long enough ago")
+		);
+		assert_eq!(runtime.dispatch(bob.clone(), proxied(transfer(10))), Ok(()));
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 10);
+		// Announcements can only be used once.
+		assert_eq!(runtime.dispatch(bob, proxied(transfer(10))), Err("Call was not announced"));
+	}
+}
@@ -0,0 +1,750 @@
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+
+use num::{CheckedAdd, Zero};
+
+use crate::support::{
+	ChargeFee, Dispatch, DispatchResult, Encode, Hooks, RawOrigin, ReservableCurrency,
+	ScheduleCall,
+};
+
+/// A measure of the time a call takes to execute.
+pub type Weight = u64;
+
+/// The name of a task, which lets it be cancelled or rescheduled without knowing where it is.
+pub type TaskName = [u8; 32];
+
+/// Where a task is in the agenda: the block it is scheduled for, and its index in that block.
+pub type TaskAddress<BlockNumber> = (BlockNumber, u32);
+
+/// A task which repeats every `period` blocks, `count` times in total.
+pub type Period<BlockNumber> = (BlockNumber, u32);
+
+pub trait Config: crate::types::Config {
+	/// The outer call type of the runtime, which can be scheduled.
+	type RuntimeCall: Clone + Debug + Encode;
+	/// The currency used to hold the deposits of scheduled tasks.
+	type Currency: ReservableCurrency<Self::AccountId, Balance = Self::Balance>;
+	/// The amount reserved from a signed origin for each task it schedules, until the task is
+	/// dispatched for the last time or cancelled.
+	const SCHEDULE_DEPOSIT: Self::Balance;
+	/// The weight available to scheduled calls in each block.
+	const MAXIMUM_WEIGHT: Weight;
+	/// The part of `MAXIMUM_WEIGHT` which tasks scheduled by signed origins can use. The rest is
+	/// kept for tasks scheduled by `Root`.
+	const MAX_SIGNED_WEIGHT: Weight;
+	/// The weight charged for every scheduled call which is dispatched.
+	const TASK_WEIGHT: Weight;
+	/// The maximum number of tasks which can be scheduled for the same block.
+	const MAX_SCHEDULED_PER_BLOCK: usize;
+	/// The part of `MAX_SCHEDULED_PER_BLOCK` which tasks scheduled by signed origins can use. The
+	/// rest is kept for tasks scheduled by `Root`.
+	const MAX_SIGNED_PER_BLOCK: usize;
+}
+
+/// A call scheduled to be dispatched on behalf of `origin`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheduled<Call, Origin, Balance, BlockNumber> {
+	/// The name of the task, if it has one.
+	pub maybe_id: Option<TaskName>,
+	/// The origin which scheduled the call, and with which it is dispatched.
+	pub origin: Origin,
+	/// The call to dispatch.
+	pub call: Call,
+	/// How the task repeats, if it is periodic.
+	pub maybe_periodic: Option<Period<BlockNumber>>,
+	/// The deposit reserved from the signed origin which scheduled the task.
+	pub deposit: Balance,
+}
+
+/// The `Scheduled` task stored by a runtime configured with `T`.
+pub type ScheduledOf<T> = Scheduled<
+	<T as Config>::RuntimeCall,
+	RawOrigin<<T as crate::types::Config>::AccountId>,
+	<T as crate::types::Config>::Balance,
+	<T as crate::types::Config>::BlockNumber,
+>;
+
+/// The `TaskAddress` of a runtime configured with `T`.
+pub type TaskAddressOf<T> = TaskAddress<<T as crate::types::Config>::BlockNumber>;
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// A task was scheduled at `task`.
+	Scheduled { task: TaskAddressOf<T> },
+	/// The task at `task` was cancelled.
+	Cancelled { task: TaskAddressOf<T> },
+	/// The task at `task` was dispatched, with `result`.
+	Dispatched { task: TaskAddressOf<T>, id: Option<TaskName>, result: DispatchResult },
+	/// The periodic task at `task` could not be scheduled again, so it will not run anymore.
+	PeriodicFailed { task: TaskAddressOf<T>, id: Option<TaskName> },
+}
+
+/// This is the Scheduler Module.
+/// It keeps calls to dispatch at a later block, optionally again and again every few blocks.
+///
+/// Scheduled calls are dispatched through the runtime itself, so `service_agenda` takes the whole
+/// runtime rather than `&mut self`. The runtime gives it access to this pallet and to the currency
+/// through `AsMut`, and charges the fee of the calls dispatched on behalf of signed origins.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The tasks scheduled for each block. A cancelled or dispatched task leaves an empty slot,
+	/// so that the address of the other tasks does not change.
+	pub agenda: BTreeMap<T::BlockNumber, Vec<Option<ScheduledOf<T>>>>,
+	/// Where each named task is in the agenda.
+	pub lookup: BTreeMap<TaskName, TaskAddressOf<T>>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+	/// The number of the block currently being executed, as given to us in `on_initialize`.
+	block_number: T::BlockNumber,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	Schedule {
+		when: T::BlockNumber,
+		maybe_periodic: Option<Period<T::BlockNumber>>,
+		call: Box<T::RuntimeCall>,
+	},
+	Cancel { when: T::BlockNumber, index: u32 },
+	ScheduleNamed {
+		id: TaskName,
+		when: T::BlockNumber,
+		maybe_periodic: Option<Period<T::BlockNumber>>,
+		call: Box<T::RuntimeCall>,
+	},
+	CancelNamed(TaskName),
+	RescheduleNamed { id: TaskName, when: T::BlockNumber },
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::Schedule { when, maybe_periodic, call } => {
+				(0u8, when, maybe_periodic, call).encode_to(dest)
+			},
+			EntryPoint::Cancel { when, index } => (1u8, when, index).encode_to(dest),
+			EntryPoint::ScheduleNamed { id, when, maybe_periodic, call } => {
+				(2u8, id, when, maybe_periodic, call).encode_to(dest)
+			},
+			EntryPoint::CancelNamed(id) => (3u8, id).encode_to(dest),
+			EntryPoint::RescheduleNamed { id, when } => (4u8, id, when).encode_to(dest),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Scheduler Module.
+	pub fn new() -> Self {
+		Self {
+			agenda: BTreeMap::new(),
+			lookup: BTreeMap::new(),
+			events: Vec::new(),
+			block_number: T::BlockNumber::zero(),
+		}
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
+	}
+
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	///
+	/// Both signed origins and `Root` can schedule calls, which are then dispatched with the same
+	/// origin.
+	pub fn dispatch(
+		&mut self,
+		currency: &mut T::Currency,
+		origin: RawOrigin<T::AccountId>,
+		call: EntryPoint<T>,
+	) -> DispatchResult {
+		match call {
+			EntryPoint::Schedule { when, maybe_periodic, call } => {
+				self.schedule(currency, origin, None, when, maybe_periodic, *call)?;
+			},
+			EntryPoint::Cancel { when, index } => self.cancel(currency, origin, (when, index))?,
+			EntryPoint::ScheduleNamed { id, when, maybe_periodic, call } => {
+				self.schedule(currency, origin, Some(id), when, maybe_periodic, *call)?;
+			},
+			EntryPoint::CancelNamed(id) => self.cancel_named(currency, origin, id)?,
+			EntryPoint::RescheduleNamed { id, when } => self.reschedule_named(origin, id, when)?,
+		}
+		Ok(())
+	}
+
+	/// Schedule `call` to be dispatched on behalf of `origin` at block `when`, and then every
+	/// `period` blocks if `maybe_periodic` is `Some((period, count))`, `count` times in total.
+	/// A signed origin pays `T::SCHEDULE_DEPOSIT` for the task.
+	///
+	/// A named task can later be cancelled or rescheduled by its name. This function will return
+	/// an error if `when` is not in the future, if a task with the same name already exists, or if
+	/// the agenda of block `when` is full.
+	pub fn schedule(
+		&mut self,
+		currency: &mut T::Currency,
+		origin: RawOrigin<T::AccountId>,
+		maybe_id: Option<TaskName>,
+		when: T::BlockNumber,
+		maybe_periodic: Option<Period<T::BlockNumber>>,
+		call: T::RuntimeCall,
+	) -> Result<TaskAddressOf<T>, &'static str> {
+		let deposit = match &origin {
+			RawOrigin::Signed(who) => {
+				currency.reserve(who, T::SCHEDULE_DEPOSIT)?;
+				T::SCHEDULE_DEPOSIT
+			},
+			RawOrigin::Root => T::Balance::zero(),
+		};
+		let scheduled =
+			Scheduled { maybe_id, origin: origin.clone(), call, maybe_periodic, deposit };
+		self.do_schedule(when, scheduled)
+			.inspect_err(|_| Self::release_deposit(currency, &origin, deposit))
+	}
+
+	fn do_schedule(
+		&mut self,
+		when: T::BlockNumber,
+		scheduled: ScheduledOf<T>,
+	) -> Result<TaskAddressOf<T>, &'static str> {
+		if when <= self.block_number {
+			return Err("Target block must be in the future")
+		}
+		if scheduled.maybe_periodic.is_some_and(|(period, _)| period.is_zero()) {
+			return Err("Period must not be zero")
+		}
+		if scheduled.maybe_id.is_some_and(|id| self.lookup.contains_key(&id)) {
+			return Err("A task with this name already exists")
+		}
+		// A task which runs once is not periodic.
+		let maybe_periodic = scheduled.maybe_periodic.filter(|(_, count)| *count > 1);
+		let task = self.place(when, Scheduled { maybe_periodic, ..scheduled })?;
+		self.deposit_event(Event::Scheduled { task });
+		Ok(task)
+	}
+
+	/// Give the deposit of a task back to the signed origin which scheduled it.
+	fn release_deposit(
+		currency: &mut T::Currency,
+		origin: &RawOrigin<T::AccountId>,
+		deposit: T::Balance,
+	) {
+		if let RawOrigin::Signed(who) = origin {
+			currency.unreserve(who, deposit);
+		}
+	}
+
+	/// Cancel the task at `task`, which must have been scheduled by the `caller`, giving back its
+	/// deposit.
+	pub fn cancel(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: RawOrigin<T::AccountId>,
+		task: TaskAddressOf<T>,
+	) -> DispatchResult {
+		let (when, index) = task;
+		let slot = self
+			.agenda
+			.get_mut(&when)
+			.and_then(|agenda| agenda.get_mut(index as usize))
+			.filter(|slot| slot.is_some())
+			.ok_or("Task does not exist")?;
+		if slot.as_ref().is_some_and(|scheduled| scheduled.origin != caller) {
+			return Err("Task was scheduled by another origin")
+		}
+		let scheduled = slot.take().ok_or("Task does not exist")?;
+		if let Some(id) = scheduled.maybe_id {
+			self.lookup.remove(&id);
+		}
+		Self::release_deposit(currency, &scheduled.origin, scheduled.deposit);
+		self.deposit_event(Event::Cancelled { task });
+		Ok(())
+	}
+
+	/// Cancel the task named `id`, which must have been scheduled by the `caller`.
+	pub fn cancel_named(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: RawOrigin<T::AccountId>,
+		id: TaskName,
+	) -> DispatchResult {
+		let task = *self.lookup.get(&id).ok_or("Task does not exist")?;
+		self.cancel(currency, caller, task)
+	}
+
+	/// Move the task named `id`, which must have been scheduled by the `caller`, to block `when`.
+	pub fn reschedule_named(
+		&mut self,
+		caller: RawOrigin<T::AccountId>,
+		id: TaskName,
+		when: T::BlockNumber,
+	) -> DispatchResult {
+		let (old_when, index) = *self.lookup.get(&id).ok_or("Task does not exist")?;
+		if when <= self.block_number {
+			return Err("Target block must be in the future")
+		}
+		if when == old_when {
+			return Err("Task is already scheduled for this block")
+		}
+		if !self.has_room(when, &caller) {
+			return Err("Agenda for this block is full")
+		}
+		let slot =
+			&mut self.agenda.get_mut(&old_when).ok_or("Task does not exist")?[index as usize];
+		if slot.as_ref().is_some_and(|scheduled| scheduled.origin != caller) {
+			return Err("Task was scheduled by another origin")
+		}
+		let scheduled = slot.take().ok_or("Task does not exist")?;
+		self.deposit_event(Event::Cancelled { task: (old_when, index) });
+
+		let task = self.place(when, scheduled)?;
+		self.deposit_event(Event::Scheduled { task });
+		Ok(())
+	}
+
+	/// Whether a task scheduled by `origin` can be added to the agenda of block `when`. Signed
+	/// origins can only use the first `T::MAX_SIGNED_PER_BLOCK` slots of an agenda, so that they
+	/// can not crowd out the tasks of `Root`.
+	fn has_room(&self, when: T::BlockNumber, origin: &RawOrigin<T::AccountId>) -> bool {
+		let limit = match origin {
+			RawOrigin::Signed(_) => T::MAX_SIGNED_PER_BLOCK,
+			RawOrigin::Root => T::MAX_SCHEDULED_PER_BLOCK,
+		};
+		self.agenda.get(&when).map_or(0, Vec::len) < limit
+	}
+
+	/// Add `scheduled` at the end of the agenda of block `when`, and keep track of its name.
+	fn place(
+		&mut self,
+		when: T::BlockNumber,
+		scheduled: ScheduledOf<T>,
+	) -> Result<TaskAddressOf<T>, &'static str> {
+		if !self.has_room(when, &scheduled.origin) {
+			return Err("Agenda for this block is full")
+		}
+		let agenda = self.agenda.entry(when).or_default();
+		let task = (when, agenda.len() as u32);
+		if let Some(id) = scheduled.maybe_id {
+			self.lookup.insert(id, task);
+		}
+		agenda.push(Some(scheduled));
+		Ok(task)
+	}
+
+	/// Dispatch the tasks scheduled for block `n`, or for earlier blocks but not dispatched yet,
+	/// in the order they were scheduled. The fee of each call dispatched on behalf of a signed
+	/// origin is charged as if it was an extrinsic, and the call is not dispatched if it can not
+	/// be paid.
+	///
+	/// Every task costs `T::TASK_WEIGHT`, and no more than `T::MAXIMUM_WEIGHT` is spent in a block,
+	/// of which at most `T::MAX_SIGNED_WEIGHT` on tasks of signed origins. The tasks which do not
+	/// fit stay in the agenda, and are dispatched in the next blocks.
+	pub fn service_agenda<R>(runtime: &mut R, n: T::BlockNumber)
+	where
+		R: Dispatch<Caller = RawOrigin<T::AccountId>, Call = T::RuntimeCall>
+			+ AsMut<Self>
+			+ AsMut<T::Currency>
+			+ ChargeFee<T::AccountId>,
+	{
+		let mut weight: Weight = 0;
+		let mut signed_weight: Weight = 0;
+		let pallet: &mut Self = runtime.as_mut();
+		let due: Vec<_> =
+			pallet.agenda.range(..=n).map(|(when, agenda)| (*when, agenda.len())).collect();
+		for (when, len) in due {
+			for index in 0..len {
+				let pallet: &mut Self = runtime.as_mut();
+				let Some(slot) =
+					pallet.agenda.get_mut(&when).and_then(|agenda| agenda.get_mut(index))
+				else {
+					continue
+				};
+				let Some(scheduled) = slot.as_ref() else { continue };
+				if weight + T::TASK_WEIGHT > T::MAXIMUM_WEIGHT {
+					return
+				}
+				let signed = matches!(scheduled.origin, RawOrigin::Signed(_));
+				if signed && signed_weight + T::TASK_WEIGHT > T::MAX_SIGNED_WEIGHT {
+					continue
+				}
+				let Some(scheduled) = slot.take() else { continue };
+				let Scheduled { maybe_id, origin, call, maybe_periodic, deposit } = scheduled;
+				weight += T::TASK_WEIGHT;
+				if signed {
+					signed_weight += T::TASK_WEIGHT;
+				}
+				if let Some(id) = &maybe_id {
+					pallet.lookup.remove(id);
+				}
+				let next = maybe_periodic.map(|period| (period, call.clone()));
+
+				let task = (when, index as u32);
+				let paid = match &origin {
+					RawOrigin::Signed(who) => runtime.charge_fee(who),
+					RawOrigin::Root => Ok(()),
+				};
+				let result = paid.and_then(|()| runtime.dispatch(origin.clone(), call));
+				let pallet: &mut Self = runtime.as_mut();
+				pallet.deposit_event(Event::Dispatched { task, id: maybe_id, result });
+
+				let Some(((period, count), call)) = next else {
+					Self::release_deposit(runtime.as_mut(), &origin, deposit);
+					continue
+				};
+				let maybe_periodic = Some((period, count - 1)).filter(|(_, count)| *count > 1);
+				let scheduled = Scheduled { maybe_id, origin, call, maybe_periodic, deposit };
+				let placed = match n.checked_add(&period) {
+					Some(next) => pallet.place(next, scheduled.clone()),
+					None => Err("Overflow"),
+				};
+				match placed {
+					Ok(task) => pallet.deposit_event(Event::Scheduled { task }),
+					Err(_) => {
+						pallet.deposit_event(Event::PeriodicFailed { task, id: maybe_id });
+						Self::release_deposit(runtime.as_mut(), &scheduled.origin, deposit);
+					},
+				}
+			}
+			// Only forget about an agenda once every task in it was dispatched or cancelled.
+			let pallet: &mut Self = runtime.as_mut();
+			if pallet
+				.agenda
+				.get(&when)
+				.is_some_and(|agenda| agenda.iter().all(Option::is_none))
+			{
+				pallet.agenda.remove(&when);
+			}
+		}
+	}
+}
+
+// Calls scheduled by other pallets do not pay a deposit.
+impl<T: Config> ScheduleCall<T::BlockNumber, T::RuntimeCall, RawOrigin<T::AccountId>>
+	for Pallet<T>
+{
+	fn schedule_call(
+		&mut self,
+		when: T::BlockNumber,
+		call: T::RuntimeCall,
+		origin: RawOrigin<T::AccountId>,
+	) -> DispatchResult {
+		let deposit = T::Balance::zero();
+		let scheduled = Scheduled { maybe_id: None, origin, call, maybe_periodic: None, deposit };
+		self.do_schedule(when, scheduled)?;
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, n: T::BlockNumber) {
+		self.block_number = n;
+		self.events.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{EntryPoint, Event};
+	use crate::{
+		balances,
+		support::{
+			ensure_signed, ChargeFee, Currency, Dispatch, Hooks, RawOrigin, ReservableCurrency,
+			WithdrawReasons,
+		},
+	};
+
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub struct Test;
+
+	impl super::Config for Test {
+		type RuntimeCall = TestCall;
+		type Currency = balances::Pallet<Self>;
+		const SCHEDULE_DEPOSIT: u128 = 10;
+		const MAXIMUM_WEIGHT: super::Weight = 30;
+		const MAX_SIGNED_WEIGHT: super::Weight = 20;
+		const TASK_WEIGHT: super::Weight = 10;
+		const MAX_SCHEDULED_PER_BLOCK: usize = 4;
+		const MAX_SIGNED_PER_BLOCK: usize = 3;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	impl balances::Config for Test {
+		const MAX_TRANSFERS_PER_BATCH: usize = 100;
+	}
+
+	#[derive(Debug, Clone)]
+	pub enum TestCall {
+		Balances(balances::EntryPoint<Test>),
+		Scheduler(EntryPoint<Test>),
+	}
+
+	impl crate::support::Encode for TestCall {
+		fn encode_to(&self, dest: &mut Vec<u8>) {
+			match self {
+				TestCall::Balances(call) => (0u8, call).encode_to(dest),
+				TestCall::Scheduler(call) => (1u8, call).encode_to(dest),
+			}
+		}
+	}
+
+	/// A runtime with just enough pallets to schedule transfers.
+	struct TestRuntime {
+		balances: balances::Pallet<Test>,
+		scheduler: super::Pallet<Test>,
+	}
+
+	impl AsMut<super::Pallet<Test>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Test> {
+			&mut self.scheduler
+		}
+	}
+
+	impl AsMut<balances::Pallet<Test>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut balances::Pallet<Test> {
+			&mut self.balances
+		}
+	}
+
+	// Every call costs a fee of 1, which is burned.
+	impl ChargeFee<String> for TestRuntime {
+		fn charge_fee(&mut self, who: &String) -> crate::support::DispatchResult {
+			let fee = self.balances.withdraw(who, 1, WithdrawReasons::FEE)?;
+			self.balances.burn(fee);
+			Ok(())
+		}
+	}
+
+	impl Dispatch for TestRuntime {
+		type Caller = RawOrigin<String>;
+		type Call = TestCall;
+
+		fn dispatch(
+			&mut self,
+			origin: RawOrigin<String>,
+			call: TestCall,
+		) -> crate::support::DispatchResult {
+			match call {
+				TestCall::Balances(call) => self.balances.dispatch(ensure_signed(origin)?, call),
+				TestCall::Scheduler(call) => {
+					self.scheduler.dispatch(&mut self.balances, origin, call)
+				},
+			}
+		}
+	}
+
+	impl TestRuntime {
+		fn run_to_block(&mut self, n: u32) {
+			self.scheduler.on_initialize(n);
+			super::Pallet::service_agenda(self, n);
+		}
+	}
+
+	fn new_runtime() -> TestRuntime {
+		let mut balances = balances::Pallet::new();
+		balances.set_balance(&"alice".to_string(), 100);
+		let mut runtime = TestRuntime { balances, scheduler: super::Pallet::new() };
+		runtime.run_to_block(1);
+		runtime
+	}
+
+	fn signed(who: &str) -> RawOrigin<String> {
+		RawOrigin::Signed(who.to_string())
+	}
+
+	fn transfer(amount: u128) -> Box<TestCall> {
+		Box::new(TestCall::Balances(balances::EntryPoint::Transfer {
+			to: "bob".to_string(),
+			amount,
+		}))
+	}
+
+	fn schedule(when: u32) -> TestCall {
+		TestCall::Scheduler(EntryPoint::Schedule { when, maybe_periodic: None, call: transfer(10) })
+	}
+
+	#[test]
+	fn periodic_tasks() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = new_runtime();
+
+		assert_eq!(
+			runtime.dispatch(signed("alice"), schedule(1)),
+			Err("Target block must be in the future")
+		);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		// Pay bob 10 at block 3, then every 2 blocks, 3 times in total.
+		let call =
+			EntryPoint::Schedule { when: 3, maybe_periodic: Some((2, 3)), call: transfer(10) };
+		assert_eq!(runtime.dispatch(signed("alice"), TestCall::Scheduler(call)), Ok(()));
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+
+		runtime.run_to_block(2);
+		assert_eq!(runtime.balances.balance(&bob), 0);
+		runtime.run_to_block(3);
+		assert_eq!(runtime.balances.balance(&bob), 10);
+		assert_eq!(
+			runtime.scheduler.events,
+			vec![
+				Event::Dispatched { task: (3, 0), id: None, result: Ok(()) },
+				Event::Scheduled { task: (5, 0) }
+			]
+		);
+		// The deposit is kept until the last run.
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+		for n in 4..10 {
+			runtime.run_to_block(n);
+		}
+		assert_eq!(runtime.balances.balance(&bob), 30);
+		assert!(runtime.scheduler.agenda.is_empty());
+		// Alice paid the fee of every run, and got her deposit back.
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		assert_eq!(runtime.balances.balance(&alice), 100 - 30 - 3);
+	}
+
+	#[test]
+	fn named_tasks() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = new_runtime();
+		let id = [1; 32];
+
+		let schedule = |when| {
+			TestCall::Scheduler(EntryPoint::ScheduleNamed {
+				id,
+				when,
+				maybe_periodic: None,
+				call: transfer(10),
+			})
+		};
+		assert_eq!(runtime.dispatch(signed("alice"), schedule(3)), Ok(()));
+		assert_eq!(
+			runtime.dispatch(signed("alice"), schedule(4)),
+			Err("A task with this name already exists")
+		);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 10);
+
+		// Only the origin which scheduled a task can move or cancel it.
+		let reschedule = TestCall::Scheduler(EntryPoint::RescheduleNamed { id, when: 5 });
+		assert_eq!(
+			runtime.dispatch(signed("bob"), reschedule.clone()),
+			Err("Task was scheduled by another origin")
+		);
+		assert_eq!(
+			runtime.dispatch(RawOrigin::Root, reschedule.clone()),
+			Err("Task was scheduled by another origin")
+		);
+		assert_eq!(runtime.dispatch(signed("alice"), reschedule), Ok(()));
+		assert_eq!(runtime.scheduler.lookup.get(&id), Some(&(5, 0)));
+
+		runtime.run_to_block(3);
+		runtime.run_to_block(4);
+		assert_eq!(runtime.balances.balance(&bob), 0);
+
+		// Cancelling a task gives its deposit back.
+		let cancel = TestCall::Scheduler(EntryPoint::CancelNamed(id));
+		assert_eq!(runtime.dispatch(signed("alice"), cancel.clone()), Ok(()));
+		assert_eq!(runtime.dispatch(signed("alice"), cancel), Err("Task does not exist"));
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		runtime.run_to_block(5);
+		assert_eq!(runtime.balances.balance(&bob), 0);
+		assert_eq!(runtime.balances.balance(&alice), 100);
+	}
+
+	#[test]
+	fn deposits_and_fees() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mut runtime = new_runtime();
+
+		// Bob can not pay the deposit.
+		assert_eq!(runtime.dispatch(signed("bob"), schedule(2)), Err("Not enough funds."));
+		assert!(runtime.scheduler.agenda.is_empty());
+
+		// Alice keeps just enough to pay for the fee and the transfer, and gets her deposit back.
+		assert_eq!(runtime.dispatch(signed("alice"), schedule(2)), Ok(()));
+		assert_eq!(runtime.balances.transfer(&alice, &bob, 79), Ok(()));
+		runtime.run_to_block(2);
+		assert_eq!(runtime.balances.balance(&bob), 89);
+		assert_eq!(runtime.balances.balance(&alice), 10);
+		assert_eq!(runtime.balances.total_issuance(), 99);
+
+		// She can not pay the fee of the next task once its deposit is reserved, so the transfer is
+		// not dispatched.
+		assert_eq!(runtime.dispatch(signed("alice"), schedule(3)), Ok(()));
+		runtime.run_to_block(3);
+		assert_eq!(runtime.balances.balance(&bob), 89);
+		assert_eq!(runtime.balances.balance(&alice), 10);
+		assert!(matches!(
+			runtime.scheduler.events.as_slice(),
+			[Event::Dispatched { task: (3, 0), id: None, result: Err(_) }]
+		));
+	}
+
+	#[test]
+	fn weight_budget() {
+		let bob = "bob".to_string();
+		let mut runtime = new_runtime();
+
+		// Only two tasks of signed origins fit in the weight of a block.
+		for _ in 0..3 {
+			assert_eq!(runtime.dispatch(signed("alice"), schedule(2)), Ok(()));
+		}
+		runtime.run_to_block(2);
+		assert_eq!(runtime.balances.balance(&bob), 20);
+		assert_eq!(runtime.scheduler.agenda.get(&2).map(Vec::len), Some(3));
+
+		// The last one is dispatched in the next block.
+		runtime.run_to_block(3);
+		assert_eq!(runtime.balances.balance(&bob), 30);
+		assert!(runtime.scheduler.agenda.is_empty());
+	}
+
+	#[test]
+	fn root_capacity() {
+		let mut runtime = new_runtime();
+
+		// Signed origins can only use 3 of the 4 slots of an agenda, and the last one is kept for
+		// `Root`, which pays no deposit.
+		for _ in 0..3 {
+			assert_eq!(runtime.dispatch(signed("alice"), schedule(2)), Ok(()));
+		}
+		assert_eq!(
+			runtime.dispatch(signed("alice"), schedule(2)),
+			Err("Agenda for this block is full")
+		);
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 30);
+		assert_eq!(runtime.dispatch(RawOrigin::Root, schedule(2)), Ok(()));
+		assert_eq!(
+			runtime.dispatch(RawOrigin::Root, schedule(2)),
+			Err("Agenda for this block is full")
+		);
+
+		// The signed tasks use up their share of the weight, but the task of `Root` is still
+		// dispatched, and the signed task which did not fit waits for the next block.
+		runtime.run_to_block(2);
+		let dispatched: Vec<_> = runtime
+			.scheduler
+			.events
+			.iter()
+			.filter_map(|event| match event {
+				Event::Dispatched { task, .. } => Some(*task),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(dispatched, vec![(2, 0), (2, 1), (2, 3)]);
+		runtime.run_to_block(3);
+		assert!(runtime.scheduler.agenda.is_empty());
+		assert_eq!(runtime.balances.reserved_balance(&"alice".to_string()), 0);
+	}
+}
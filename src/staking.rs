@@ -0,0 +1,723 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use num::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Zero};
+
+use crate::support::{
+	ensure_root, ensure_signed, percent_of, Currency, DispatchResult, Encode, Hooks,
+	LockIdentifier, LockableCurrency, RawOrigin, WithdrawReasons,
+};
+
+/// The identifier of the balance lock holding bonded funds.
+pub const STAKING_ID: LockIdentifier = *b"staking ";
+
+/// The index of an era, counted from the genesis.
+pub type EraIndex = u32;
+
+pub trait Config: crate::types::Config {
+	/// The currency which is bonded and paid out as rewards.
+	type Currency: LockableCurrency<Self::AccountId, Balance = Self::Balance>;
+	/// How many blocks an era lasts.
+	const ERA_LENGTH: Self::BlockNumber;
+	/// The number of validators elected for each era.
+	const VALIDATOR_COUNT: usize;
+	/// The maximum number of validators a nominator can back.
+	const MAX_NOMINATIONS: usize;
+	/// The smallest amount which can stay bonded.
+	const MIN_BOND: Self::Balance;
+	/// How many eras unbonded funds stay locked before they can be withdrawn.
+	const BONDING_DURATION: EraIndex;
+	/// The maximum number of unbonded amounts waiting to be withdrawn by an account.
+	const MAX_UNLOCKING_CHUNKS: usize;
+	/// The funds minted at the end of each era, and shared between its validators and their
+	/// nominators.
+	const ERA_REWARD: Self::Balance;
+}
+
+/// An amount which was unbonded, and can be withdrawn once `era` starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnlockChunk<Balance> {
+	pub value: Balance,
+	pub era: EraIndex,
+}
+
+/// The funds bonded by an account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StakingLedger<Balance> {
+	/// All the locked funds, including the ones which are being unbonded.
+	pub total: Balance,
+	/// The funds backing a validator, which can be rewarded and slashed.
+	pub active: Balance,
+	/// The funds which are being unbonded, in the order they were unbonded.
+	pub unlocking: Vec<UnlockChunk<Balance>>,
+}
+
+/// The stake of a single nominator behind a validator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndividualExposure<AccountId, Balance> {
+	pub who: AccountId,
+	pub value: Balance,
+}
+
+/// All the stake behind an elected validator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exposure<AccountId, Balance> {
+	/// The sum of `own` and the stake of all the `others`.
+	pub total: Balance,
+	/// The stake of the validator itself.
+	pub own: Balance,
+	/// The stake of the nominators backing the validator.
+	pub others: Vec<IndividualExposure<AccountId, Balance>>,
+}
+
+/// The `StakingLedger` stored by a runtime configured with `T`.
+pub type StakingLedgerOf<T> = StakingLedger<<T as crate::types::Config>::Balance>;
+
+/// The `Exposure` stored by a runtime configured with `T`.
+pub type ExposureOf<T> =
+	Exposure<<T as crate::types::Config>::AccountId, <T as crate::types::Config>::Balance>;
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// `amount` of the funds of `stash` was bonded.
+	Bonded { stash: T::AccountId, amount: T::Balance },
+	/// `amount` of the funds of `stash` was unbonded, and can be withdrawn later.
+	Unbonded { stash: T::AccountId, amount: T::Balance },
+	/// `amount` of unbonded funds was unlocked in the account of `stash`.
+	Withdrawn { stash: T::AccountId, amount: T::Balance },
+	/// The era `era` started with the elected `validators`.
+	NewEra { era: EraIndex, validators: Vec<T::AccountId> },
+	/// `stash` was rewarded `amount` for the era which just ended.
+	Rewarded { stash: T::AccountId, amount: T::Balance },
+	/// The validator `offender` was reported for an offence, and removed from the candidates.
+	OffenceReported { offender: T::AccountId, era: EraIndex },
+	/// `amount` of the funds bonded by `staker` was destroyed because of an offence.
+	Slashed { staker: T::AccountId, amount: T::Balance },
+}
+
+/// This is the Staking Module.
+/// It lets accounts bond funds to back the validators of the network, either as a validator
+/// themselves or as a nominator of other validators.
+///
+/// Every `T::ERA_LENGTH` blocks, the validators of the era which ended are rewarded with newly
+/// minted funds, and the `T::VALIDATOR_COUNT` candidates with the most stake are elected for the
+/// next era. An elected validator reported for an offence is slashed, along with its nominators.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The bonded funds of each account which bonded some.
+	pub ledger: BTreeMap<T::AccountId, StakingLedgerOf<T>>,
+	/// The accounts which want to be elected as validators.
+	pub validators: BTreeSet<T::AccountId>,
+	/// The validators each nominator wants to back.
+	pub nominators: BTreeMap<T::AccountId, Vec<T::AccountId>>,
+	/// The current era.
+	pub current_era: EraIndex,
+	/// The validators elected for the current era, and the stake behind them.
+	pub exposures: BTreeMap<T::AccountId, ExposureOf<T>>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+	/// The block at which the next era starts.
+	next_era: T::BlockNumber,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	Bond(T::Balance),
+	BondExtra(T::Balance),
+	Unbond(T::Balance),
+	WithdrawUnbonded,
+	Validate,
+	Nominate(Vec<T::AccountId>),
+	Chill,
+	ReportOffence { offender: T::AccountId, slash_percent: u32 },
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::Bond(value) => (0u8, value).encode_to(dest),
+			EntryPoint::BondExtra(value) => (1u8, value).encode_to(dest),
+			EntryPoint::Unbond(value) => (2u8, value).encode_to(dest),
+			EntryPoint::WithdrawUnbonded => 3u8.encode_to(dest),
+			EntryPoint::Validate => 4u8.encode_to(dest),
+			EntryPoint::Nominate(targets) => (5u8, targets).encode_to(dest),
+			EntryPoint::Chill => 6u8.encode_to(dest),
+			EntryPoint::ReportOffence { offender, slash_percent } => {
+				(7u8, offender, slash_percent).encode_to(dest)
+			},
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Staking Module.
+	pub fn new() -> Self {
+		Self {
+			ledger: BTreeMap::new(),
+			validators: BTreeSet::new(),
+			nominators: BTreeMap::new(),
+			current_era: 0,
+			exposures: BTreeMap::new(),
+			events: Vec::new(),
+			next_era: T::ERA_LENGTH,
+		}
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
+	}
+
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	///
+	/// Offences can only be reported by `Root`. Every other call must be signed.
+	pub fn dispatch(
+		&mut self,
+		currency: &mut T::Currency,
+		origin: RawOrigin<T::AccountId>,
+		call: EntryPoint<T>,
+	) -> DispatchResult {
+		match call {
+			EntryPoint::Bond(value) => self.bond(currency, ensure_signed(origin)?, value)?,
+			EntryPoint::BondExtra(value) => {
+				self.bond_extra(currency, ensure_signed(origin)?, value)?
+			},
+			EntryPoint::Unbond(value) => self.unbond(ensure_signed(origin)?, value)?,
+			EntryPoint::WithdrawUnbonded => {
+				self.withdraw_unbonded(currency, ensure_signed(origin)?)?
+			},
+			EntryPoint::Validate => self.validate(ensure_signed(origin)?)?,
+			EntryPoint::Nominate(targets) => self.nominate(ensure_signed(origin)?, targets)?,
+			EntryPoint::Chill => self.chill(ensure_signed(origin)?)?,
+			EntryPoint::ReportOffence { offender, slash_percent } => {
+				ensure_root(origin)?;
+				self.report_offence(currency, offender, slash_percent)?
+			},
+		}
+		Ok(())
+	}
+
+	/// Get the funds of `who` which back a validator.
+	pub fn active(&self, who: &T::AccountId) -> T::Balance {
+		self.ledger.get(who).map(|ledger| ledger.active).unwrap_or(T::Balance::zero())
+	}
+
+	/// Bond `value` of the funds of the `caller`, locking them.
+	pub fn bond(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		value: T::Balance,
+	) -> DispatchResult {
+		if self.ledger.contains_key(&caller) {
+			return Err("Account is already bonded")
+		}
+		if value < T::MIN_BOND {
+			return Err("Bond is below the minimum")
+		}
+		if value > currency.free_balance(&caller) {
+			return Err("Not enough funds.")
+		}
+
+		let ledger = StakingLedger { total: value, active: value, unlocking: Vec::new() };
+		self.ledger.insert(caller.clone(), ledger);
+		self.update_lock(currency, &caller);
+		self.deposit_event(Event::Bonded { stash: caller, amount: value });
+		Ok(())
+	}
+
+	/// Bond `value` more of the funds of the `caller`, on top of what it already bonded.
+	pub fn bond_extra(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		value: T::Balance,
+	) -> DispatchResult {
+		let ledger = self.ledger.get_mut(&caller).ok_or("Account is not bonded")?;
+		let total = ledger.total.checked_add(&value).ok_or("Overflow")?;
+		if total > currency.free_balance(&caller) {
+			return Err("Not enough funds.")
+		}
+		ledger.active = ledger.active.checked_add(&value).ok_or("Overflow")?;
+		ledger.total = total;
+
+		self.update_lock(currency, &caller);
+		self.deposit_event(Event::Bonded { stash: caller, amount: value });
+		Ok(())
+	}
+
+	/// Unbond `value` of the funds bonded by the `caller`. They stay locked for
+	/// `T::BONDING_DURATION` eras, and can then be unlocked with `withdraw_unbonded`.
+	///
+	/// This function will return an error if less than `T::MIN_BOND` would stay bonded, unless
+	/// everything is unbonded.
+	pub fn unbond(&mut self, caller: T::AccountId, value: T::Balance) -> DispatchResult {
+		let era = self.current_era.checked_add(T::BONDING_DURATION).ok_or("Overflow")?;
+		let ledger = self.ledger.get_mut(&caller).ok_or("Account is not bonded")?;
+		let active = ledger.active.checked_sub(&value).ok_or("Not enough bonded funds")?;
+		if !active.is_zero() && active < T::MIN_BOND {
+			return Err("Bonded funds would be below the minimum")
+		}
+		if ledger.unlocking.len() >= T::MAX_UNLOCKING_CHUNKS {
+			return Err("Too many unlocking chunks")
+		}
+		ledger.active = active;
+		ledger.unlocking.push(UnlockChunk { value, era });
+
+		self.deposit_event(Event::Unbonded { stash: caller, amount: value });
+		Ok(())
+	}
+
+	/// Unlock the funds of the `caller` which finished unbonding. Once nothing is bonded anymore,
+	/// the `caller` stops validating or nominating.
+	pub fn withdraw_unbonded(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+	) -> DispatchResult {
+		let current_era = self.current_era;
+		let ledger = self.ledger.get_mut(&caller).ok_or("Account is not bonded")?;
+		let mut amount = T::Balance::zero();
+		ledger.unlocking.retain(|chunk| {
+			if chunk.era > current_era {
+				return true;
+			}
+			// Every chunk is part of the total, so this can not overflow.
+			amount += chunk.value;
+			false
+		});
+		ledger.total = ledger.total.checked_sub(&amount).ok_or("Underflow")?;
+
+		self.update_lock(currency, &caller);
+		if !amount.is_zero() {
+			self.deposit_event(Event::Withdrawn { stash: caller, amount });
+		}
+		Ok(())
+	}
+
+	/// Make the `caller` a candidate in the next elections of validators.
+	pub fn validate(&mut self, caller: T::AccountId) -> DispatchResult {
+		if !self.ledger.contains_key(&caller) {
+			return Err("Account is not bonded")
+		}
+		self.nominators.remove(&caller);
+		self.validators.insert(caller);
+		Ok(())
+	}
+
+	/// Back the validators in `targets` with the funds bonded by the `caller`, which are split
+	/// equally between the ones which are candidates in an election.
+	pub fn nominate(
+		&mut self,
+		caller: T::AccountId,
+		mut targets: Vec<T::AccountId>,
+	) -> DispatchResult {
+		if !self.ledger.contains_key(&caller) {
+			return Err("Account is not bonded")
+		}
+		targets.sort();
+		targets.dedup();
+		if targets.is_empty() {
+			return Err("Nominations must not be empty")
+		}
+		if targets.len() > T::MAX_NOMINATIONS {
+			return Err("Too many nominations")
+		}
+		self.validators.remove(&caller);
+		self.nominators.insert(caller, targets);
+		Ok(())
+	}
+
+	/// Stop the `caller` from validating or nominating in the next elections. Its funds stay
+	/// bonded.
+	pub fn chill(&mut self, caller: T::AccountId) -> DispatchResult {
+		if !self.ledger.contains_key(&caller) {
+			return Err("Account is not bonded")
+		}
+		self.validators.remove(&caller);
+		self.nominators.remove(&caller);
+		Ok(())
+	}
+
+	/// Report an offence of the validator `offender` in the current era. `slash_percent` percent of
+	/// the stake of the validator and of each of its nominators is slashed, and the validator
+	/// stops being rewarded for the era and being a candidate in the next elections.
+	pub fn report_offence(
+		&mut self,
+		currency: &mut T::Currency,
+		offender: T::AccountId,
+		slash_percent: u32,
+	) -> DispatchResult {
+		if slash_percent > 100 {
+			return Err("Slash must be at most 100 percent")
+		}
+		let exposure =
+			self.exposures.remove(&offender).ok_or("Offender is not an active validator")?;
+		self.validators.remove(&offender);
+		self.deposit_event(Event::OffenceReported {
+			offender: offender.clone(),
+			era: self.current_era,
+		});
+
+		let stakers = exposure.others.into_iter().map(|other| (other.who, other.value));
+		for (staker, value) in core::iter::once((offender, exposure.own)).chain(stakers) {
+			// `slash_percent` is at most 100, so this only overflows for huge stakes, which we
+			// slash entirely.
+			let amount = percent_of(value, slash_percent).unwrap_or(value);
+			self.slash(currency, staker, amount);
+		}
+		Ok(())
+	}
+
+	/// Destroy up to `amount` of the bonded funds of `staker`. The funds which are being unbonded
+	/// were at stake too, so they are slashed once the active funds are gone, the most recently
+	/// unbonded first.
+	fn slash(&mut self, currency: &mut T::Currency, staker: T::AccountId, amount: T::Balance) {
+		let Some(ledger) = self.ledger.get_mut(&staker) else { return };
+		let amount = amount.min(ledger.total);
+		let slashed = amount.checked_sub(&currency.slash(&staker, amount)).unwrap_or(amount);
+		let sub = |a: T::Balance, b: T::Balance| a.checked_sub(&b).unwrap_or(T::Balance::zero());
+
+		let from_active = slashed.min(ledger.active);
+		ledger.active = sub(ledger.active, from_active);
+		let mut remaining = sub(slashed, from_active);
+		for chunk in ledger.unlocking.iter_mut().rev() {
+			let from_chunk = remaining.min(chunk.value);
+			chunk.value = sub(chunk.value, from_chunk);
+			remaining = sub(remaining, from_chunk);
+		}
+		ledger.unlocking.retain(|chunk| !chunk.value.is_zero());
+		ledger.total = sub(ledger.total, slashed);
+
+		self.update_lock(currency, &staker);
+		if !slashed.is_zero() {
+			self.deposit_event(Event::Slashed { staker, amount: slashed });
+		}
+	}
+
+	/// Make the staking lock on the funds of `who` match what it bonded, and forget about it once
+	/// nothing is bonded anymore.
+	fn update_lock(&mut self, currency: &mut T::Currency, who: &T::AccountId) {
+		let Some(ledger) = self.ledger.get(who) else { return };
+		if ledger.total.is_zero() {
+			currency.remove_lock(STAKING_ID, who);
+			self.ledger.remove(who);
+			self.validators.remove(who);
+			self.nominators.remove(who);
+		} else {
+			// Fees can still be paid from bonded funds, so that a staker which bonded all of its
+			// funds can still unbond them.
+			let reasons = WithdrawReasons::TRANSFER.union(WithdrawReasons::RESERVE);
+			currency.set_lock(STAKING_ID, who, ledger.total, reasons);
+		}
+	}
+
+	/// At the end of an era, reward its validators and elect the ones of the next era. The runtime
+	/// calls this at the start of every block.
+	pub fn rotate_era(&mut self, currency: &mut T::Currency, n: T::BlockNumber) {
+		if n < self.next_era {
+			return
+		}
+		// If this overflows, there will be no more eras.
+		self.next_era = n.checked_add(&T::ERA_LENGTH).unwrap_or(n);
+
+		self.reward_validators(currency);
+		self.current_era = self.current_era.saturating_add(1);
+		self.exposures = self.elect();
+		let validators = self.exposures.keys().cloned().collect();
+		self.deposit_event(Event::NewEra { era: self.current_era, validators });
+	}
+
+	/// Mint `T::ERA_REWARD`, split equally between the validators of the current era, and share
+	/// the part of each validator with its nominators, in proportion to their stake.
+	fn reward_validators(&mut self, currency: &mut T::Currency) {
+		let Ok(count) = u32::try_from(self.exposures.len()) else { return };
+		let Some(per_validator) = T::ERA_REWARD.checked_div(&T::Balance::from(count)) else {
+			return
+		};
+
+		let mut payouts = Vec::new();
+		for (validator, exposure) in &self.exposures {
+			let others = exposure.others.iter().map(|other| (&other.who, other.value));
+			for (stash, value) in core::iter::once((validator, exposure.own)).chain(others) {
+				let amount = value
+					.checked_mul(&per_validator)
+					.and_then(|total| total.checked_div(&exposure.total))
+					.unwrap_or(T::Balance::zero());
+				payouts.push((stash.clone(), amount));
+			}
+		}
+		for (stash, amount) in payouts {
+			if !amount.is_zero() && currency.deposit_creating(&stash, amount).is_ok() {
+				self.deposit_event(Event::Rewarded { stash, amount });
+			}
+		}
+	}
+
+	/// Elect the `T::VALIDATOR_COUNT` candidates with the most stake behind them: their own active
+	/// funds, and an equal share of the active funds of each nominator backing them. Ties go to
+	/// the smallest account.
+	fn elect(&self) -> BTreeMap<T::AccountId, ExposureOf<T>> {
+		let mut exposures = BTreeMap::new();
+		for validator in &self.validators {
+			let own = self.active(validator);
+			exposures.insert(validator.clone(), Exposure { total: own, own, others: Vec::new() });
+		}
+		for (nominator, targets) in &self.nominators {
+			let targets: Vec<_> = targets
+				.iter()
+				.filter(|target| exposures.contains_key(*target))
+				.cloned()
+				.collect();
+			let Ok(count) = u32::try_from(targets.len()) else { continue };
+			let share = self
+				.active(nominator)
+				.checked_div(&T::Balance::from(count))
+				.unwrap_or(T::Balance::zero());
+			if share.is_zero() {
+				continue
+			}
+			for target in targets {
+				if let Some(exposure) = exposures.get_mut(&target) {
+					// All the stake is part of the total issuance, so this can not overflow.
+					exposure.total += share;
+					exposure
+						.others
+						.push(IndividualExposure { who: nominator.clone(), value: share });
+				}
+			}
+		}
+
+		let mut ranked: Vec<_> = exposures
+			.into_iter()
+			.filter(|(_, exposure)| !exposure.total.is_zero())
+			.collect();
+		// The sort is stable, and the candidates are sorted by account to begin with.
+		ranked.sort_by_key(|(_, exposure)| core::cmp::Reverse(exposure.total));
+		ranked.truncate(T::VALIDATOR_COUNT);
+		ranked.into_iter().collect()
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, _n: T::BlockNumber) {
+		self.events.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{EntryPoint, Event, StakingLedger, UnlockChunk};
+	use crate::{
+		balances,
+		support::{Currency, RawOrigin, WithdrawReasons},
+	};
+
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub struct Test;
+
+	impl super::Config for Test {
+		type Currency = balances::Pallet<Test>;
+		const ERA_LENGTH: u32 = 10;
+		const VALIDATOR_COUNT: usize = 2;
+		const MAX_NOMINATIONS: usize = 2;
+		const MIN_BOND: u128 = 10;
+		const BONDING_DURATION: u32 = 2;
+		const MAX_UNLOCKING_CHUNKS: usize = 2;
+		const ERA_REWARD: u128 = 1_000;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	impl balances::Config for Test {
+		const MAX_TRANSFERS_PER_BATCH: usize = 100;
+	}
+
+	type Staking = super::Pallet<Test>;
+
+	fn new_test() -> (Staking, balances::Pallet<Test>) {
+		let mut balances = balances::Pallet::new();
+		for who in ["alice", "bob", "charlie", "dave"] {
+			balances.set_balance(&who.to_string(), 1_000);
+		}
+		(Staking::new(), balances)
+	}
+
+	fn call(
+		staking: &mut Staking,
+		balances: &mut balances::Pallet<Test>,
+		who: &str,
+		call: EntryPoint<Test>,
+	) -> crate::support::DispatchResult {
+		staking.dispatch(balances, RawOrigin::Signed(who.to_string()), call)
+	}
+
+	#[test]
+	fn elections_and_rewards() {
+		let (mut staking, mut balances) = new_test();
+		let (alice, bob, charlie, dave) =
+			("alice".to_string(), "bob".to_string(), "charlie".to_string(), "dave".to_string());
+
+		for (who, value) in [("alice", 100), ("bob", 300), ("charlie", 200), ("dave", 400)] {
+			assert_eq!(call(&mut staking, &mut balances, who, EntryPoint::Bond(value)), Ok(()));
+		}
+		assert_eq!(
+			call(&mut staking, &mut balances, "alice", EntryPoint::Bond(10)),
+			Err("Account is already bonded")
+		);
+		// Bonded funds are locked.
+		assert_eq!(balances.transfer(&bob, &alice, 701), Err("Funds are locked."));
+
+		for who in ["alice", "bob", "charlie"] {
+			assert_eq!(call(&mut staking, &mut balances, who, EntryPoint::Validate), Ok(()));
+		}
+		let nominate = EntryPoint::Nominate(vec![alice.clone(), charlie.clone()]);
+		assert_eq!(call(&mut staking, &mut balances, "dave", nominate), Ok(()));
+
+		// Dave backs alice and charlie with 200 each, so bob has the least stake.
+		staking.rotate_era(&mut balances, 9);
+		assert_eq!(staking.current_era, 0);
+		staking.rotate_era(&mut balances, 10);
+		assert_eq!(staking.current_era, 1);
+		assert_eq!(
+			staking.events.last(),
+			Some(&Event::NewEra { era: 1, validators: vec![alice.clone(), charlie.clone()] })
+		);
+		assert_eq!(staking.exposures[&alice].total, 300);
+		assert_eq!(staking.exposures[&charlie].total, 400);
+
+		// Each validator gets half of the reward, shared with dave by stake.
+		let issuance = balances.total_issuance();
+		staking.rotate_era(&mut balances, 20);
+		assert_eq!(balances.free_balance(&alice), 1_000 + 166);
+		assert_eq!(balances.free_balance(&charlie), 1_000 + 250);
+		assert_eq!(balances.free_balance(&dave), 1_000 + 333 + 250);
+		assert_eq!(balances.free_balance(&bob), 1_000);
+		assert_eq!(balances.total_issuance(), issuance + 999);
+	}
+
+	#[test]
+	fn unbonding_takes_some_eras() {
+		let (mut staking, mut balances) = new_test();
+		let alice = "alice".to_string();
+
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::Bond(100)), Ok(()));
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::BondExtra(50)), Ok(()));
+		assert_eq!(
+			call(&mut staking, &mut balances, "alice", EntryPoint::Unbond(145)),
+			Err("Bonded funds would be below the minimum")
+		);
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::Unbond(50)), Ok(()));
+		assert_eq!(
+			staking.ledger[&alice],
+			StakingLedger {
+				total: 150,
+				active: 100,
+				unlocking: vec![UnlockChunk { value: 50, era: 2 }]
+			}
+		);
+
+		// The funds stay locked until the bonding duration has passed.
+		staking.rotate_era(&mut balances, 10);
+		assert_eq!(
+			call(&mut staking, &mut balances, "alice", EntryPoint::WithdrawUnbonded),
+			Ok(())
+		);
+		assert_eq!(balances.frozen_balance(&alice, WithdrawReasons::TRANSFER), 150);
+		staking.rotate_era(&mut balances, 20);
+		assert_eq!(
+			call(&mut staking, &mut balances, "alice", EntryPoint::WithdrawUnbonded),
+			Ok(())
+		);
+		assert_eq!(balances.frozen_balance(&alice, WithdrawReasons::TRANSFER), 100);
+
+		// Withdrawing everything forgets about the account.
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::Validate), Ok(()));
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::Unbond(100)), Ok(()));
+		staking.rotate_era(&mut balances, 30);
+		staking.rotate_era(&mut balances, 40);
+		assert_eq!(
+			call(&mut staking, &mut balances, "alice", EntryPoint::WithdrawUnbonded),
+			Ok(())
+		);
+		assert_eq!(balances.frozen_balance(&alice, WithdrawReasons::TRANSFER), 0);
+		assert!(!staking.ledger.contains_key(&alice));
+		assert!(staking.validators.is_empty());
+	}
+
+	#[test]
+	fn offences_are_slashed() {
+		let (mut staking, mut balances) = new_test();
+		let (alice, dave) = ("alice".to_string(), "dave".to_string());
+
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::Bond(100)), Ok(()));
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::Validate), Ok(()));
+		assert_eq!(call(&mut staking, &mut balances, "dave", EntryPoint::Bond(400)), Ok(()));
+		let nominate = EntryPoint::Nominate(vec![alice.clone()]);
+		assert_eq!(call(&mut staking, &mut balances, "dave", nominate), Ok(()));
+		staking.rotate_era(&mut balances, 10);
+
+		let report = || EntryPoint::ReportOffence { offender: alice.clone(), slash_percent: 10 };
+		assert_eq!(
+			call(&mut staking, &mut balances, "dave", report()),
+			Err("Bad origin: expected root")
+		);
+		assert_eq!(staking.dispatch(&mut balances, RawOrigin::Root, report()), Ok(()));
+		assert_eq!(staking.active(&alice), 90);
+		assert_eq!(staking.active(&dave), 360);
+		assert_eq!(balances.free_balance(&alice), 990);
+		assert_eq!(balances.free_balance(&dave), 960);
+		assert_eq!(
+			staking.events.last(),
+			Some(&Event::Slashed { staker: dave.clone(), amount: 40 })
+		);
+
+		// The offender is no longer a validator, and can not be reported again.
+		assert!(!staking.validators.contains(&alice));
+		assert_eq!(
+			staking.dispatch(&mut balances, RawOrigin::Root, report()),
+			Err("Offender is not an active validator")
+		);
+	}
+
+	#[test]
+	fn unbonding_funds_are_slashed() {
+		let (mut staking, mut balances) = new_test();
+		let alice = "alice".to_string();
+
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::Bond(100)), Ok(()));
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::Validate), Ok(()));
+		staking.rotate_era(&mut balances, 10);
+
+		// Alice unbonds most of her stake after the election, but it was still at stake.
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::Unbond(40)), Ok(()));
+		assert_eq!(call(&mut staking, &mut balances, "alice", EntryPoint::Unbond(40)), Ok(()));
+		let report = EntryPoint::ReportOffence { offender: alice.clone(), slash_percent: 50 };
+		assert_eq!(staking.dispatch(&mut balances, RawOrigin::Root, report), Ok(()));
+		assert_eq!(
+			staking.ledger[&alice],
+			StakingLedger {
+				total: 50,
+				active: 0,
+				unlocking: vec![
+					UnlockChunk { value: 40, era: 3 },
+					UnlockChunk { value: 10, era: 3 }
+				]
+			}
+		);
+		assert_eq!(balances.free_balance(&alice), 950);
+		assert_eq!(balances.frozen_balance(&alice, WithdrawReasons::TRANSFER), 50);
+
+		// The bonded funds can not be moved, but fees can still be paid from them.
+		assert_eq!(balances.transfer(&alice, &"bob".to_string(), 901), Err("Funds are locked."));
+		assert!(balances.withdraw(&alice, 950, WithdrawReasons::FEE).is_ok());
+	}
+}
@@ -1,7 +1,15 @@
+use std::ops::{Add, Sub};
+
+use num::{CheckedDiv, CheckedMul};
+
 /// The most primitive representation of a Blockchain block.
-pub struct Block<H, E> {
+pub struct Block<H, I, E> {
 	/// The block header contains metadata about the block.
 	pub header: H,
+	/// The inherents are put in the block by its author rather than signed by a user, like the
+	/// current time. They are executed before the extrinsics, and a block is invalid if any of them
+	/// fails.
+	pub inherents: Vec<I>,
 	/// The extrinsics represent the state transitions to be executed in this block.
 	pub extrinsics: Vec<E>,
 }
@@ -30,6 +38,12 @@ pub type DispatchResult = Result<(), &'static str>;
 
 /// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
 /// function call.
+///
+/// Pallets which only touch their own storage implement this trait. A pallet which also needs
+/// the storage of another pallet, like the currency holding its deposits, instead has an inherent
+/// `dispatch` function taking that storage as its first argument, and the runtime's
+/// implementation of this trait passes it in. Pallets which dispatch other calls, like utility and
+/// proxy, take the whole runtime instead and reach the storage they need through `AsMut`.
 pub trait Dispatch {
 	/// The type used to identify the caller of the function.
 	type Caller;
@@ -40,3 +54,432 @@ pub trait Dispatch {
 	/// based on the outcome of that function call.
 	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
 }
+
+/// Who a call is dispatched on behalf of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawOrigin<AccountId> {
+	/// The runtime itself, which can make privileged calls. No extrinsic has this origin: calls
+	/// are only dispatched as `Root` by pallets like governance.
+	Root,
+	/// An account, which signed the extrinsic or on behalf of which a pallet dispatches the call.
+	Signed(AccountId),
+}
+
+impl<AccountId> From<AccountId> for RawOrigin<AccountId> {
+	fn from(who: AccountId) -> Self {
+		RawOrigin::Signed(who)
+	}
+}
+
+/// Get the account a call is dispatched on behalf of, or fail if the origin is not signed.
+pub fn ensure_signed<AccountId>(origin: RawOrigin<AccountId>) -> Result<AccountId, &'static str> {
+	match origin {
+		RawOrigin::Signed(who) => Ok(who),
+		RawOrigin::Root => Err("Bad origin: expected a signed origin"),
+	}
+}
+
+/// Fail if a call is not dispatched as `Root`.
+pub fn ensure_root<AccountId>(origin: RawOrigin<AccountId>) -> DispatchResult {
+	match origin {
+		RawOrigin::Root => Ok(()),
+		RawOrigin::Signed(_) => Err("Bad origin: expected root"),
+	}
+}
+
+/// Get `percent` percent of `amount`, rounded down, or `None` if it overflows.
+pub fn percent_of<Balance>(amount: Balance, percent: u32) -> Option<Balance>
+where
+	Balance: CheckedMul + CheckedDiv + From<u32>,
+{
+	amount.checked_mul(&Balance::from(percent))?.checked_div(&Balance::from(100))
+}
+
+/// Storage which can undo the changes made to it since a transaction was started.
+///
+/// Transactions can be nested: committing one keeps its changes as part of the transaction around
+/// it, which can still roll them back.
+pub trait Transactional {
+	/// Start a new transaction, inside the current one if there is one.
+	fn start_transaction(&mut self);
+	/// Keep the changes made since the innermost transaction was started, and close it.
+	fn commit_transaction(&mut self);
+	/// Undo the changes made since the innermost transaction was started, and close it.
+	fn rollback_transaction(&mut self);
+}
+
+/// Run `f` on `state` in a new transaction, and put `state` back the way it was if `f` returns an
+/// error.
+pub fn with_transaction<S: Transactional, R>(
+	state: &mut S,
+	f: impl FnOnce(&mut S) -> Result<R, &'static str>,
+) -> Result<R, &'static str> {
+	state.start_transaction();
+	let result = f(state);
+	if result.is_ok() {
+		state.commit_transaction();
+	} else {
+		state.rollback_transaction();
+	}
+	result
+}
+
+/// Funds which were taken out of an account with `Currency::withdraw`, and which have not been put
+/// anywhere yet. They must either be given to an account with `Currency::resolve_creating`, or
+/// destroyed with `Currency::burn`, so that the total issuance stays correct.
+#[must_use = "withdrawn funds must be resolved or burned"]
+#[derive(Debug, PartialEq, Eq)]
+pub struct NegativeImbalance<Balance>(Balance);
+
+impl<Balance> NegativeImbalance<Balance>
+where
+	Balance: Copy + Ord + Add<Output = Balance> + Sub<Output = Balance>,
+{
+	/// Wrap some withdrawn funds. Only a `Currency` implementation should need to do this.
+	pub fn new(amount: Balance) -> Self {
+		Self(amount)
+	}
+
+	/// The amount of funds in this imbalance.
+	pub fn peek(&self) -> Balance {
+		self.0
+	}
+
+	/// Split off up to `amount` from this imbalance. The first imbalance returned holds what was
+	/// split off, and the second one holds the rest.
+	pub fn split(self, amount: Balance) -> (Self, Self) {
+		let first = self.0.min(amount);
+		(Self(first), Self(self.0 - first))
+	}
+
+	/// Combine two imbalances into one.
+	pub fn merge(self, other: Self) -> Self {
+		Self(self.0 + other.0)
+	}
+}
+
+/// A fungible currency.
+///
+/// Pallets which need to move funds around depend on this trait rather than on a concrete balances
+/// pallet, so that they work with any currency and can be tested with a mock.
+pub trait Currency<AccountId> {
+	/// The type used to represent an amount of this currency.
+	type Balance;
+
+	/// Get the funds `who` can spend.
+	fn free_balance(&self, who: &AccountId) -> Self::Balance;
+
+	/// Get all the funds which belong to `who`, including the ones it can not spend.
+	fn total_balance(&self, who: &AccountId) -> Self::Balance;
+
+	/// Get the total amount of this currency in existence.
+	fn total_issuance(&self) -> Self::Balance;
+
+	/// Move `amount` from the free balance of `from` to the free balance of `to`.
+	fn transfer(&mut self, from: &AccountId, to: &AccountId, amount: Self::Balance)
+		-> DispatchResult;
+
+	/// Take `amount` out of the free balance of `who`, for the given `reasons`. This function will
+	/// return an error if `who` does not have enough funds, or if they are locked.
+	fn withdraw(
+		&mut self,
+		who: &AccountId,
+		amount: Self::Balance,
+		reasons: WithdrawReasons,
+	) -> Result<NegativeImbalance<Self::Balance>, &'static str>;
+
+	/// Create `amount` new funds in the free balance of `who`, increasing the total issuance.
+	fn deposit_creating(&mut self, who: &AccountId, amount: Self::Balance) -> DispatchResult;
+
+	/// Give withdrawn funds to `who`.
+	fn resolve_creating(&mut self, who: &AccountId, imbalance: NegativeImbalance<Self::Balance>);
+
+	/// Destroy withdrawn funds, decreasing the total issuance.
+	fn burn(&mut self, imbalance: NegativeImbalance<Self::Balance>);
+
+	/// Destroy up to `amount` of the funds of `who`, for example as a punishment, decreasing the
+	/// total issuance. Free funds are slashed first, even if they are locked, and then reserved
+	/// funds. Returns the part of `amount` which could not be slashed.
+	fn slash(&mut self, who: &AccountId, amount: Self::Balance) -> Self::Balance;
+}
+
+/// Where funds moved out of a reserved balance end up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceStatus {
+	/// The funds can be spent right away.
+	Free,
+	/// The funds stay reserved.
+	Reserved,
+}
+
+/// A currency where part of the balance of an account can be set aside, or "reserved". Reserved
+/// funds still belong to the account, but they cannot be spent until they are unreserved.
+///
+/// Pallets which need to hold a deposit depend on this trait, rather than on a concrete balances
+/// pallet.
+pub trait ReservableCurrency<AccountId>: Currency<AccountId> {
+	/// Get the amount of funds reserved by `who`.
+	fn reserved_balance(&self, who: &AccountId) -> Self::Balance;
+
+	/// Move `amount` from the free balance of `who` to its reserved balance.
+	/// This function will return an error if `who` does not have enough free funds.
+	fn reserve(&mut self, who: &AccountId, amount: Self::Balance) -> DispatchResult;
+
+	/// Move up to `amount` from the reserved balance of `who` back to its free balance.
+	/// Returns the part of `amount` which could not be unreserved.
+	fn unreserve(&mut self, who: &AccountId, amount: Self::Balance) -> Self::Balance;
+
+	/// Destroy up to `amount` from the reserved balance of `who`, for example as a punishment,
+	/// decreasing the total issuance.
+	/// Returns the part of `amount` which could not be slashed.
+	fn slash_reserved(&mut self, who: &AccountId, amount: Self::Balance) -> Self::Balance;
+
+	/// Move `amount` from the reserved balance of `slashed` to the free or reserved balance of
+	/// `beneficiary`, depending on `status`. This function will return an error if `slashed` does
+	/// not have enough reserved funds.
+	fn repatriate_reserved(
+		&mut self,
+		slashed: &AccountId,
+		beneficiary: &AccountId,
+		amount: Self::Balance,
+		status: BalanceStatus,
+	) -> DispatchResult;
+}
+
+/// An identifier for a lock on the balance of an account, like `*b"staking "`. Each pallet uses
+/// its own identifier, so that its locks do not interfere with the locks of other pallets.
+pub type LockIdentifier = [u8; 8];
+
+/// A set of reasons for which funds can leave the free balance of an account. A lock only stops
+/// withdrawals for the reasons it was set with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WithdrawReasons(u8);
+
+impl WithdrawReasons {
+	/// Transferring funds to another account.
+	pub const TRANSFER: Self = Self(1);
+	/// Moving funds into the reserved balance of the account.
+	pub const RESERVE: Self = Self(1 << 1);
+	/// Paying transaction fees.
+	pub const FEE: Self = Self(1 << 2);
+	/// All of the above.
+	pub const ALL: Self = Self(Self::TRANSFER.0 | Self::RESERVE.0 | Self::FEE.0);
+
+	/// Whether `self` and `other` have at least one reason in common.
+	pub fn intersects(self, other: Self) -> bool {
+		self.0 & other.0 != 0
+	}
+
+	/// All the reasons in either `self` or `other`.
+	pub fn union(self, other: Self) -> Self {
+		Self(self.0 | other.0)
+	}
+}
+
+/// A currency where funds can be locked: they stay in the free balance of an account, but can not
+/// be withdrawn for the locked reasons.
+///
+/// Locks with different identifiers overlap rather than add up, so the amount which can not be
+/// withdrawn is the largest lock which applies.
+pub trait LockableCurrency<AccountId>: Currency<AccountId> {
+	/// Create or replace the lock `id` on the funds of `who`.
+	fn set_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &AccountId,
+		amount: Self::Balance,
+		reasons: WithdrawReasons,
+	);
+
+	/// Create the lock `id` on the funds of `who`, or extend it so that it covers at least
+	/// `amount` and all of `reasons`. This never shrinks an existing lock.
+	fn extend_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &AccountId,
+		amount: Self::Balance,
+		reasons: WithdrawReasons,
+	);
+
+	/// Remove the lock `id` from the funds of `who`.
+	fn remove_lock(&mut self, id: LockIdentifier, who: &AccountId);
+}
+
+/// A filter deciding which values of type `T` an instance allows, like which calls a kind of
+/// proxy is allowed to make.
+pub trait InstanceFilter<T> {
+	/// Whether `value` is allowed by `self`.
+	fn filter(&self, value: &T) -> bool;
+}
+
+/// A conversion from one type to another, for example from a block number to a balance.
+pub trait Convert<A, B> {
+	/// Convert `a` into the target type.
+	fn convert(a: A) -> B;
+}
+
+/// A `Convert` implementation for types which can be converted using `Into`.
+#[derive(Debug)]
+pub struct ConvertInto;
+
+impl<A: Into<B>, B> Convert<A, B> for ConvertInto {
+	fn convert(a: A) -> B {
+		a.into()
+	}
+}
+
+/// Hooks which the runtime calls on a pallet at the start and at the end of every block.
+pub trait Hooks<BlockNumber> {
+	/// Called at the start of block `n`, before any extrinsic is executed.
+	fn on_initialize(&mut self, _n: BlockNumber) {}
+
+	/// Called at the end of block `n`, after all extrinsics have been executed.
+	fn on_finalize(&mut self, _n: BlockNumber) {}
+}
+
+/// A hook for pallets which need the time of the current block, called by the runtime once the
+/// timestamp inherent has set it.
+pub trait OnTimestampSet<Moment> {
+	fn on_timestamp_set(&mut self, moment: Moment);
+}
+
+/// Something which can dispatch a call with some origin at a later block, like the scheduler
+/// pallet. Pallets use it to delay calls without depending on the scheduler itself.
+pub trait ScheduleCall<BlockNumber, Call, Origin> {
+	/// Dispatch `call` with `origin` at block `when`.
+	fn schedule_call(&mut self, when: BlockNumber, call: Call, origin: Origin) -> DispatchResult;
+}
+
+/// Something which charges the fee of a call dispatched on behalf of an account, like the runtime.
+/// Pallets which dispatch calls outside of an extrinsic use it, so that those calls are not free.
+pub trait ChargeFee<AccountId> {
+	/// Take the fee of one call from `who`.
+	fn charge_fee(&mut self, who: &AccountId) -> DispatchResult;
+}
+
+/// Something which records who claimed some content, like the proof of existence pallet. Pallets
+/// use it to check who owns a claim without depending on the proof of existence pallet itself.
+pub trait ClaimOwnership<AccountId, Content> {
+	/// Get the owner of the claim on `content`, if it was claimed.
+	fn claim_owner(&self, content: &Content) -> Option<AccountId>;
+}
+
+/// A trait for types which can be turned into a canonical sequence of bytes.
+///
+/// This is a very simplified version of the SCALE codec used by real blockchains. We need it so
+/// that storage items can be hashed, for example when building a Merkle tree over our state.
+pub trait Encode {
+	/// Append the encoded bytes of `self` to `dest`.
+	fn encode_to(&self, dest: &mut Vec<u8>);
+
+	/// Return the encoded bytes of `self`.
+	fn encode(&self) -> Vec<u8> {
+		let mut dest = Vec::new();
+		self.encode_to(&mut dest);
+		dest
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest)
+	}
+}
+
+// Variable length data is prefixed with its length, so that concatenated encodings stay
+// unambiguous.
+impl<T: Encode> Encode for [T] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(self.len() as u32).encode_to(dest);
+		for item in self {
+			item.encode_to(dest);
+		}
+	}
+}
+
+impl<T: Encode> Encode for Vec<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_slice().encode_to(dest)
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for Box<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest)
+	}
+}
+
+// Optional data is prefixed with a single byte saying whether it is there.
+impl<T: Encode> Encode for Option<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			None => dest.push(0),
+			Some(value) => {
+				dest.push(1);
+				value.encode_to(dest);
+			},
+		}
+	}
+}
+
+impl Encode for bool {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.push(*self as u8);
+	}
+}
+
+// Tuples are encoded as their elements one after the other. Calls are encoded as a tuple of the
+// index of their variant and their fields.
+macro_rules! impl_encode_for_tuple {
+	($(($($t:ident $i:tt),+)),*) => {
+		$(
+			impl<$($t: Encode),+> Encode for ($($t,)+) {
+				fn encode_to(&self, dest: &mut Vec<u8>) {
+					$(self.$i.encode_to(dest);)+
+				}
+			}
+		)*
+	};
+}
+
+impl_encode_for_tuple!(
+	(A 0, B 1),
+	(A 0, B 1, C 2),
+	(A 0, B 1, C 2, D 3),
+	(A 0, B 1, C 2, D 3, E 4),
+	(A 0, B 1, C 2, D 3, E 4, F 5)
+);
+
+impl Encode for str {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(self.len() as u32).encode_to(dest);
+		dest.extend_from_slice(self.as_bytes());
+	}
+}
+
+impl Encode for String {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_str().encode_to(dest)
+	}
+}
+
+// Fixed size data, like hashes, is encoded as is.
+impl<const N: usize> Encode for [u8; N] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.extend_from_slice(self);
+	}
+}
+
+macro_rules! impl_encode_for_int {
+	($($t:ty),*) => {
+		$(
+			impl Encode for $t {
+				fn encode_to(&self, dest: &mut Vec<u8>) {
+					dest.extend_from_slice(&self.to_le_bytes());
+				}
+			}
+		)*
+	};
+}
+
+impl_encode_for_int!(u8, u16, u32, u64, u128);
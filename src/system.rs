@@ -2,16 +2,20 @@ use std::collections::BTreeMap;
 
 use num::{CheckedAdd, One, Zero};
 
-use crate::Config;
+use crate::{hashing::Hash, Config};
 
 /// This is the System Pallet.
 /// It handles low level state needed for your blockchain.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pallet<T: Config> {
 	/// The current block number.
 	block_number: T::BlockNumber,
 	/// A map from an account to their nonce.
 	nonce: BTreeMap<T::AccountId, T::Nonce>,
+	/// The state root at the end of the last executed block.
+	state_root: Hash,
+	/// The index of the extrinsic currently being executed in the block.
+	extrinsic_index: u32,
 }
 
 
@@ -19,7 +23,12 @@ pub struct Pallet<T: Config> {
 impl<T: Config> Pallet<T> {
 	/// Create a new instance of the System Pallet.
 	pub fn new() -> Self {
-		Self { block_number: T::BlockNumber::zero(), nonce: BTreeMap::new() }
+		Self {
+			block_number: T::BlockNumber::zero(),
+			nonce: BTreeMap::new(),
+			state_root: [0u8; 32],
+			extrinsic_index: 0,
+		}
 	}
 
 	/// Get the current block number.
@@ -45,6 +54,26 @@ impl<T: Config> Pallet<T> {
 		let new_nonce = nonce +  T::Nonce::one();
 		self.nonce.insert(who.clone(), new_nonce);
 	}
+
+	/// Get the state root at the end of the last executed block.
+	pub fn state_root(&self) -> Hash {
+		self.state_root
+	}
+
+	/// Get the index of the extrinsic currently being executed in the block.
+	pub fn extrinsic_index(&self) -> u32 {
+		self.extrinsic_index
+	}
+
+	// Record which extrinsic of the block is about to be executed.
+	pub fn set_extrinsic_index(&mut self, index: u32) {
+		self.extrinsic_index = index;
+	}
+
+	// Record the state root once all the extrinsics of a block have been executed.
+	pub fn set_state_root(&mut self, root: Hash) {
+		self.state_root = root;
+	}
 }
 
 #[cfg(test)]
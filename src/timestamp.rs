@@ -0,0 +1,149 @@
+use core::fmt::Debug;
+
+use num::{CheckedAdd, Zero};
+
+use crate::support::{DispatchResult, Encode, Hooks};
+
+pub trait Config: crate::types::Config {
+	/// The type used to represent a point in time, like a number of milliseconds since the epoch.
+	type Moment: Zero + Copy + CheckedAdd + Ord + Debug + Encode;
+	/// The minimum time between the timestamps of two consecutive blocks.
+	const MINIMUM_PERIOD: Self::Moment;
+}
+
+/// This is the Timestamp Module.
+/// It keeps track of the wall-clock time of the current block, as set by the block author.
+///
+/// The time is set with the `Set` inherent, which must appear exactly once in every block.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The time of the current block, or `None` before the first block.
+	now: Option<T::Moment>,
+	/// Whether the time has been set in the current block.
+	did_update: bool,
+}
+
+// A public enum which describes the inherents we want to expose to the dispatcher.
+// Inherents are put in the block by its author and have no caller.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	Set { now: T::Moment },
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::Set { now } => (0u8, now).encode_to(dest),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Timestamp Module.
+	pub fn new() -> Self {
+		Self { now: None, did_update: false }
+	}
+
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	pub fn dispatch(&mut self, call: EntryPoint<T>) -> DispatchResult {
+		match call {
+			EntryPoint::Set { now } => self.set(now)?,
+		}
+		Ok(())
+	}
+
+	/// Get the time of the current block, or of the last block if it has not been set yet. This is
+	/// zero before the first block.
+	pub fn now(&self) -> T::Moment {
+		self.now.unwrap_or_else(T::Moment::zero)
+	}
+
+	/// Check that `now` can be the time of the block after the one whose time was set last,
+	/// without setting it. This function will return an error if `now` is less than
+	/// `T::MINIMUM_PERIOD` after the time of that block.
+	pub fn check(&self, now: T::Moment) -> DispatchResult {
+		if let Some(last) = self.now {
+			let earliest = last.checked_add(&T::MINIMUM_PERIOD).ok_or("Overflow")?;
+			if now < earliest {
+				return Err("Timestamp must increment by at least the minimum period")
+			}
+		}
+		Ok(())
+	}
+
+	/// Set the time of the current block to `now`.
+	///
+	/// This function will return an error if the time was already set in this block, or if `now`
+	/// fails `check`.
+	pub fn set(&mut self, now: T::Moment) -> DispatchResult {
+		if self.did_update {
+			return Err("Timestamp must be updated only once in the block")
+		}
+		self.check(now)?;
+		self.now = Some(now);
+		self.did_update = true;
+		Ok(())
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, _n: T::BlockNumber) {
+		self.did_update = false;
+	}
+
+	fn on_finalize(&mut self, _n: T::BlockNumber) {
+		// A block without a timestamp is invalid, like a block with the wrong number.
+		assert!(self.did_update, "Timestamp must be updated once in the block");
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::support::Hooks;
+
+	pub struct Test;
+
+	impl super::Config for Test {
+		type Moment = u64;
+		const MINIMUM_PERIOD: u64 = 3_000;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	#[test]
+	fn set_timestamp() {
+		let mut timestamp = super::Pallet::<Test>::new();
+
+		// The first block can have any time, even zero.
+		timestamp.on_initialize(1);
+		assert_eq!(timestamp.set(0), Ok(()));
+		assert_eq!(timestamp.set(7_000), Err("Timestamp must be updated only once in the block"));
+		timestamp.on_finalize(1);
+		assert_eq!(timestamp.now(), 0);
+
+		timestamp.on_initialize(2);
+		assert_eq!(
+			timestamp.set(2_999),
+			Err("Timestamp must increment by at least the minimum period")
+		);
+		assert_eq!(timestamp.check(3_000), Ok(()));
+		assert_eq!(timestamp.now(), 0);
+		assert_eq!(timestamp.set(3_000), Ok(()));
+		timestamp.on_finalize(2);
+		assert_eq!(timestamp.now(), 3_000);
+	}
+
+	#[test]
+	#[should_panic(expected = "Timestamp must be updated once in the block")]
+	fn timestamp_is_required() {
+		let mut timestamp = super::Pallet::<Test>::new();
+		timestamp.on_initialize(1);
+		timestamp.on_finalize(1);
+	}
+}
@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+
+use num::{CheckedAdd, CheckedSub, Zero};
+
+use crate::{
+	hashing::{Hash, Hasher},
+	support::{
+		ensure_root, ensure_signed, percent_of, BalanceStatus, Convert, Currency, DispatchResult,
+		Encode, Hooks, RawOrigin, ReservableCurrency, WithdrawReasons,
+	},
+};
+
+/// The data the account of the treasury pot is derived from.
+pub const PALLET_ID: [u8; 8] = *b"treasury";
+
+/// The index of a spend proposal, in the order they were made.
+pub type ProposalIndex = u32;
+
+pub trait Config: crate::types::Config {
+	/// The hash function used to derive the account of the pot.
+	type Hashing: Hasher<Output = Hash>;
+	/// How to turn a hash into the account of the pot, which has no key.
+	type AccountIdFromHash: Convert<Hash, Self::AccountId>;
+	/// The currency held by the treasury.
+	type Currency: ReservableCurrency<Self::AccountId, Balance = Self::Balance>;
+	/// The percentage of the value of a proposal reserved from the proposer as a bond.
+	const PROPOSAL_BOND_PERCENT: u32;
+	/// The smallest bond of a proposal.
+	const PROPOSAL_BOND_MINIMUM: Self::Balance;
+	/// How many blocks pass between two payouts of the approved proposals.
+	const SPEND_PERIOD: Self::BlockNumber;
+	/// The percentage of the funds left in the pot after a payout which is burned.
+	const BURN_PERCENT: u32;
+	/// The maximum number of approved proposals waiting to be paid out.
+	const MAX_APPROVALS: usize;
+}
+
+/// A request to pay `value` from the treasury to `beneficiary`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proposal<AccountId, Balance> {
+	/// The account which made the proposal.
+	pub proposer: AccountId,
+	/// The amount to pay.
+	pub value: Balance,
+	/// The account to pay.
+	pub beneficiary: AccountId,
+	/// The bond reserved from the proposer, returned if the proposal is paid out.
+	pub bond: Balance,
+}
+
+/// The `Proposal` stored by a runtime configured with `T`.
+pub type ProposalOf<T> =
+	Proposal<<T as crate::types::Config>::AccountId, <T as crate::types::Config>::Balance>;
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// The spend proposal `index` was made.
+	Proposed { index: ProposalIndex },
+	/// The spend proposal `index` was approved, and will be paid out at a spend period.
+	Approved { index: ProposalIndex },
+	/// The spend proposal `index` was rejected, and its bond of `slashed` given to the pot.
+	Rejected { index: ProposalIndex, slashed: T::Balance },
+	/// `award` was paid out to `account` for the spend proposal `index`.
+	Awarded { index: ProposalIndex, award: T::Balance, account: T::AccountId },
+	/// `amount` of the funds left in the pot was burned.
+	Burnt { amount: T::Balance },
+	/// A spend period ended with `remaining` funds in the pot.
+	Rollover { remaining: T::Balance },
+}
+
+/// This is the Treasury Module.
+/// It holds funds in a pot, which no one has the key of, and pays them out to the spend
+/// proposals approved by `Root`.
+///
+/// Approved proposals are paid out every `T::SPEND_PERIOD` blocks, as long as there are enough
+/// funds in the pot, and then `T::BURN_PERCENT` of what is left is burned.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The number of proposals made so far, which is also the index of the next one.
+	pub proposal_count: ProposalIndex,
+	/// The proposals which were not paid out or rejected yet.
+	pub proposals: BTreeMap<ProposalIndex, ProposalOf<T>>,
+	/// The approved proposals, in the order they will be paid out.
+	pub approvals: Vec<ProposalIndex>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+	/// The block at which the next spend period ends.
+	next_spend: T::BlockNumber,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	ProposeSpend { value: T::Balance, beneficiary: T::AccountId },
+	RejectProposal(ProposalIndex),
+	ApproveProposal(ProposalIndex),
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::ProposeSpend { value, beneficiary } => {
+				(0u8, value, beneficiary).encode_to(dest)
+			},
+			EntryPoint::RejectProposal(index) => (1u8, index).encode_to(dest),
+			EntryPoint::ApproveProposal(index) => (2u8, index).encode_to(dest),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Treasury Module.
+	pub fn new() -> Self {
+		Self {
+			proposal_count: 0,
+			proposals: BTreeMap::new(),
+			approvals: Vec::new(),
+			events: Vec::new(),
+			next_spend: T::SPEND_PERIOD,
+		}
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
+	}
+
+	/// The account of the pot.
+	pub fn account_id() -> T::AccountId {
+		T::AccountIdFromHash::convert(T::Hashing::hash(&PALLET_ID))
+	}
+
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	///
+	/// Anyone can make a proposal, but only `Root` can approve or reject it.
+	pub fn dispatch(
+		&mut self,
+		currency: &mut T::Currency,
+		origin: RawOrigin<T::AccountId>,
+		call: EntryPoint<T>,
+	) -> DispatchResult {
+		match call {
+			EntryPoint::ProposeSpend { value, beneficiary } => {
+				self.propose_spend(currency, ensure_signed(origin)?, value, beneficiary)?
+			},
+			EntryPoint::RejectProposal(index) => {
+				ensure_root(origin)?;
+				self.reject_proposal(currency, index)?
+			},
+			EntryPoint::ApproveProposal(index) => {
+				ensure_root(origin)?;
+				self.approve_proposal(index)?
+			},
+		}
+		Ok(())
+	}
+
+	/// Propose to pay `value` from the pot to `beneficiary`. A bond of `T::PROPOSAL_BOND_PERCENT`
+	/// of `value`, and at least `T::PROPOSAL_BOND_MINIMUM`, is reserved from the `caller`.
+	pub fn propose_spend(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		value: T::Balance,
+		beneficiary: T::AccountId,
+	) -> DispatchResult {
+		let bond = percent_of(value, T::PROPOSAL_BOND_PERCENT)
+			.ok_or("Overflow")?
+			.max(T::PROPOSAL_BOND_MINIMUM);
+		let index = self.proposal_count;
+		let next_index = index.checked_add(1).ok_or("Overflow")?;
+		currency.reserve(&caller, bond)?;
+
+		self.proposals
+			.insert(index, Proposal { proposer: caller, value, beneficiary, bond });
+		self.proposal_count = next_index;
+		self.deposit_event(Event::Proposed { index });
+		Ok(())
+	}
+
+	/// Reject the proposal `index`, giving its bond to the pot.
+	pub fn reject_proposal(
+		&mut self,
+		currency: &mut T::Currency,
+		index: ProposalIndex,
+	) -> DispatchResult {
+		let proposal = self.proposals.remove(&index).ok_or("Proposal does not exist")?;
+		self.approvals.retain(|approved| *approved != index);
+		let pot = Self::account_id();
+		currency.repatriate_reserved(
+			&proposal.proposer,
+			&pot,
+			proposal.bond,
+			BalanceStatus::Free,
+		)?;
+		self.deposit_event(Event::Rejected { index, slashed: proposal.bond });
+		Ok(())
+	}
+
+	/// Approve the proposal `index`, to be paid out at the end of a spend period.
+	pub fn approve_proposal(&mut self, index: ProposalIndex) -> DispatchResult {
+		if !self.proposals.contains_key(&index) {
+			return Err("Proposal does not exist")
+		}
+		if self.approvals.contains(&index) {
+			return Err("Proposal is already approved")
+		}
+		if self.approvals.len() >= T::MAX_APPROVALS {
+			return Err("Too many approvals")
+		}
+		self.approvals.push(index);
+		self.deposit_event(Event::Approved { index });
+		Ok(())
+	}
+
+	/// At the end of a spend period, pay out the approved proposals the pot has enough funds for,
+	/// and burn `T::BURN_PERCENT` of what is left. The proposals which could not be paid out stay
+	/// approved for the next spend period. The runtime calls this at the start of every block.
+	pub fn spend_funds(&mut self, currency: &mut T::Currency, n: T::BlockNumber) {
+		if n < self.next_spend {
+			return
+		}
+		// If this overflows, there will be no more spend periods.
+		self.next_spend = n.checked_add(&T::SPEND_PERIOD).unwrap_or(n);
+
+		let pot = Self::account_id();
+		let mut budget = currency.free_balance(&pot);
+		let approvals = core::mem::take(&mut self.approvals);
+		for index in approvals {
+			let Some(proposal) = self.proposals.get(&index).cloned() else { continue };
+			let remaining = budget.checked_sub(&proposal.value);
+			let paid = remaining.is_some()
+				&& currency.transfer(&pot, &proposal.beneficiary, proposal.value).is_ok();
+			if !paid {
+				self.approvals.push(index);
+				continue
+			}
+			budget = remaining.unwrap_or(budget);
+			currency.unreserve(&proposal.proposer, proposal.bond);
+			self.proposals.remove(&index);
+			let Proposal { value: award, beneficiary: account, .. } = proposal;
+			self.deposit_event(Event::Awarded { index, award, account });
+		}
+
+		let burn = percent_of(budget, T::BURN_PERCENT).unwrap_or(T::Balance::zero());
+		if !burn.is_zero() {
+			if let Ok(imbalance) = currency.withdraw(&pot, burn, WithdrawReasons::ALL) {
+				currency.burn(imbalance);
+				budget = budget.checked_sub(&burn).unwrap_or(budget);
+				self.deposit_event(Event::Burnt { amount: burn });
+			}
+		}
+		self.deposit_event(Event::Rollover { remaining: budget });
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, _n: T::BlockNumber) {
+		self.events.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{EntryPoint, Event};
+	use crate::{
+		balances,
+		support::{Currency, RawOrigin, ReservableCurrency},
+	};
+
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub struct Test;
+
+	impl super::Config for Test {
+		type Hashing = crate::hashing::Sha256;
+		type AccountIdFromHash = crate::types::AccountIdFromHash;
+		type Currency = balances::Pallet<Test>;
+		const PROPOSAL_BOND_PERCENT: u32 = 5;
+		const PROPOSAL_BOND_MINIMUM: u128 = 10;
+		const SPEND_PERIOD: u32 = 10;
+		const BURN_PERCENT: u32 = 50;
+		const MAX_APPROVALS: usize = 4;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	impl balances::Config for Test {
+		const MAX_TRANSFERS_PER_BATCH: usize = 100;
+	}
+
+	type Treasury = super::Pallet<Test>;
+
+	fn new_test() -> (Treasury, balances::Pallet<Test>) {
+		let mut balances = balances::Pallet::new();
+		for who in ["alice", "charlie"] {
+			balances.set_balance(&who.to_string(), 1_000);
+		}
+		balances.set_balance(&Treasury::account_id(), 100);
+		(Treasury::new(), balances)
+	}
+
+	#[test]
+	fn approved_proposals_are_paid_at_spend_periods() {
+		let (mut treasury, mut balances) = new_test();
+		let (alice, bob, charlie) = ("alice".to_string(), "bob".to_string(), "charlie".to_string());
+		let pot = Treasury::account_id();
+
+		let propose = |value, beneficiary: &String| EntryPoint::ProposeSpend {
+			value,
+			beneficiary: beneficiary.clone(),
+		};
+		assert_eq!(
+			treasury.dispatch(&mut balances, RawOrigin::Signed(alice.clone()), propose(60, &bob)),
+			Ok(())
+		);
+		assert_eq!(
+			treasury.dispatch(
+				&mut balances,
+				RawOrigin::Signed(charlie.clone()),
+				propose(500, &bob)
+			),
+			Ok(())
+		);
+		// The bond is 5% of the value, but at least 10.
+		assert_eq!(balances.reserved_balance(&alice), 10);
+		assert_eq!(balances.reserved_balance(&charlie), 25);
+
+		let approve = |index| EntryPoint::ApproveProposal(index);
+		assert_eq!(
+			treasury.dispatch(&mut balances, RawOrigin::Signed(alice.clone()), approve(0)),
+			Err("Bad origin: expected root")
+		);
+		assert_eq!(treasury.dispatch(&mut balances, RawOrigin::Root, approve(0)), Ok(()));
+		assert_eq!(treasury.dispatch(&mut balances, RawOrigin::Root, approve(1)), Ok(()));
+
+		// Nothing happens before the end of the spend period.
+		treasury.spend_funds(&mut balances, 9);
+		assert_eq!(balances.free_balance(&bob), 0);
+
+		// The pot only has enough for the first proposal, and half of the rest is burned.
+		let issuance = balances.total_issuance();
+		treasury.spend_funds(&mut balances, 10);
+		assert_eq!(balances.free_balance(&bob), 60);
+		assert_eq!(balances.reserved_balance(&alice), 0);
+		assert_eq!(balances.free_balance(&pot), 20);
+		assert_eq!(balances.total_issuance(), issuance - 20);
+		assert_eq!(treasury.approvals, vec![1]);
+		assert_eq!(treasury.events.last(), Some(&Event::Rollover { remaining: 20 }));
+	}
+
+	#[test]
+	fn rejected_proposals_lose_their_bond() {
+		let (mut treasury, mut balances) = new_test();
+		let alice = "alice".to_string();
+		let pot = Treasury::account_id();
+
+		assert_eq!(treasury.propose_spend(&mut balances, alice.clone(), 60, "bob".into()), Ok(()));
+		let reject = || EntryPoint::RejectProposal(0);
+		assert_eq!(treasury.dispatch(&mut balances, RawOrigin::Root, reject()), Ok(()));
+		assert_eq!(balances.free_balance(&alice), 990);
+		assert_eq!(balances.reserved_balance(&alice), 0);
+		assert_eq!(balances.free_balance(&pot), 110);
+		assert_eq!(
+			treasury.dispatch(&mut balances, RawOrigin::Root, reject()),
+			Err("Proposal does not exist")
+		);
+	}
+}
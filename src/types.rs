@@ -1,25 +1,52 @@
 #![allow(dead_code)]
 use std::{fmt::Debug, ops::AddAssign};
 
-use num::{CheckedAdd, CheckedSub, One, Zero};
+use num::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, One, Zero};
+
+use crate::support::Encode;
 
 pub trait Config {
-	type AccountId: Ord + Clone + Debug + ToString;
-	type BlockNumber: Zero + One + AddAssign + Copy + CheckedAdd + CheckedSub + Debug;
+	type AccountId: Ord + Clone + Debug + ToString + Encode;
+	type BlockNumber:
+		Zero + One + AddAssign + Copy + CheckedAdd + CheckedSub + Ord + Debug + Encode;
 	type Nonce: Zero + One + Copy + CheckedAdd + CheckedSub + Debug;
-	type Balance: Zero + CheckedSub + CheckedAdd + Copy + AddAssign + One + Debug;
+	type Balance: Zero
+		+ CheckedSub
+		+ CheckedAdd
+		+ CheckedMul
+		+ CheckedDiv
+		+ Copy
+		+ AddAssign
+		+ One
+		+ Ord
+		+ Debug
+		+ Encode
+		+ From<u32>;
 }
 
 pub type AccountId = String;
 pub type Balance = u128;
 pub type BlockNumber = u32;
 pub type Nonce = u32;
+pub type Moment = u64;
+pub type RuntimeOrigin = crate::support::RawOrigin<AccountId>;
 pub type Extrinsic = crate::support::Extrinsic<AccountId, crate::RuntimeCall>;
 pub type Header = crate::support::Header<BlockNumber>;
-pub type Block = crate::support::Block<Header, Extrinsic>;
-pub type Content = String;
+pub type Block = crate::support::Block<Header, crate::RuntimeInherent, Extrinsic>;
+pub type Content = crate::hashing::Hash;
 pub type Signature<T> = Result<T, String>;
 
+/// Turns a hash into an account id, for accounts which are not controlled by a single key but
+/// derived from some data, like a multisig account.
+#[derive(Debug)]
+pub struct AccountIdFromHash;
+
+impl crate::support::Convert<crate::hashing::Hash, String> for AccountIdFromHash {
+	fn convert(hash: crate::hashing::Hash) -> String {
+		hash.iter().map(|b| format!("{:02x}", b)).collect()
+	}
+}
+
 pub struct TestConfig;
 
 impl super::Config for TestConfig {
@@ -0,0 +1,335 @@
+use core::fmt::Debug;
+
+use crate::support::{with_transaction, Dispatch, DispatchResult, Encode, Hooks, Transactional};
+
+pub trait Config: crate::types::Config {
+	/// The outer call type of the runtime, which any batch can contain.
+	type RuntimeCall: Clone + Debug;
+	/// The maximum number of calls in a single batch.
+	const MAX_CALLS: usize;
+	/// How many batches can be nested inside each other.
+	const MAX_NESTING_DEPTH: u32;
+}
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+	/// A call in a batch was dispatched successfully.
+	ItemCompleted,
+	/// A call in a batch failed with `error`.
+	ItemFailed { error: &'static str },
+	/// Every call in a batch was dispatched successfully.
+	BatchCompleted,
+	/// Every call in a `force_batch` was dispatched, but some of them failed.
+	BatchCompletedWithErrors,
+	/// A batch stopped at the call at `index`, which failed with `error`.
+	BatchInterrupted { index: u32, error: &'static str },
+}
+
+/// This is the Utility Module.
+/// It lets an account dispatch several calls, to any pallet, in a single extrinsic.
+///
+/// The calls in a batch are dispatched through the runtime itself, so the functions of this pallet
+/// take the whole runtime rather than `&mut self`. The runtime gives them access to this pallet
+/// through `AsMut`.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The events emitted during the current block.
+	pub events: Vec<Event>,
+	/// How many batches are being dispatched right now, one inside the other.
+	depth: u32,
+	_config: core::marker::PhantomData<T>,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	Batch(Vec<T::RuntimeCall>),
+	BatchAll(Vec<T::RuntimeCall>),
+	ForceBatch(Vec<T::RuntimeCall>),
+}
+
+impl<T: Config> Encode for EntryPoint<T>
+where
+	T::RuntimeCall: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::Batch(calls) => (0u8, calls).encode_to(dest),
+			EntryPoint::BatchAll(calls) => (1u8, calls).encode_to(dest),
+			EntryPoint::ForceBatch(calls) => (2u8, calls).encode_to(dest),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Create a new instance of the Utility Module.
+	pub fn new() -> Self {
+		Self { events: Vec::new(), depth: 0, _config: core::marker::PhantomData }
+	}
+
+	fn deposit_event(&mut self, event: Event) {
+		self.events.push(event);
+	}
+
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	pub fn dispatch<R>(runtime: &mut R, caller: R::Caller, call: EntryPoint<T>) -> DispatchResult
+	where
+		R: Dispatch<Call = T::RuntimeCall> + AsMut<Self> + Transactional,
+		R::Caller: Clone,
+	{
+		match call {
+			EntryPoint::Batch(calls) => Self::batch(runtime, caller, calls),
+			EntryPoint::BatchAll(calls) => Self::batch_all(runtime, caller, calls),
+			EntryPoint::ForceBatch(calls) => Self::force_batch(runtime, caller, calls),
+		}
+	}
+
+	/// Dispatch `calls` one after the other on behalf of the `caller`, stopping at the first one
+	/// which fails. The calls before it are not rolled back, and the batch itself still succeeds.
+	pub fn batch<R>(
+		runtime: &mut R,
+		caller: R::Caller,
+		calls: Vec<T::RuntimeCall>,
+	) -> DispatchResult
+	where
+		R: Dispatch<Call = T::RuntimeCall> + AsMut<Self>,
+		R::Caller: Clone,
+	{
+		Self::nested(runtime, calls.len(), |runtime| {
+			for (index, call) in calls.into_iter().enumerate() {
+				if let Err(error) = runtime.dispatch(caller.clone(), call) {
+					let index = index as u32;
+					runtime.as_mut().deposit_event(Event::BatchInterrupted { index, error });
+					return Ok(())
+				}
+				runtime.as_mut().deposit_event(Event::ItemCompleted);
+			}
+			runtime.as_mut().deposit_event(Event::BatchCompleted);
+			Ok(())
+		})
+	}
+
+	/// Dispatch `calls` one after the other on behalf of the `caller`. If any of them fails, every
+	/// change made by the batch is rolled back and the error is returned.
+	pub fn batch_all<R>(
+		runtime: &mut R,
+		caller: R::Caller,
+		calls: Vec<T::RuntimeCall>,
+	) -> DispatchResult
+	where
+		R: Dispatch<Call = T::RuntimeCall> + AsMut<Self> + Transactional,
+		R::Caller: Clone,
+	{
+		Self::nested(runtime, calls.len(), |runtime| {
+			with_transaction(runtime, |runtime| {
+				for call in calls {
+					runtime.dispatch(caller.clone(), call)?;
+					runtime.as_mut().deposit_event(Event::ItemCompleted);
+				}
+				runtime.as_mut().deposit_event(Event::BatchCompleted);
+				Ok(())
+			})
+		})
+	}
+
+	/// Dispatch every call in `calls` on behalf of the `caller`, whether or not the ones before it
+	/// failed.
+	pub fn force_batch<R>(
+		runtime: &mut R,
+		caller: R::Caller,
+		calls: Vec<T::RuntimeCall>,
+	) -> DispatchResult
+	where
+		R: Dispatch<Call = T::RuntimeCall> + AsMut<Self>,
+		R::Caller: Clone,
+	{
+		Self::nested(runtime, calls.len(), |runtime| {
+			let mut failed = false;
+			for call in calls {
+				let event = match runtime.dispatch(caller.clone(), call) {
+					Ok(()) => Event::ItemCompleted,
+					Err(error) => {
+						failed = true;
+						Event::ItemFailed { error }
+					},
+				};
+				runtime.as_mut().deposit_event(event);
+			}
+			let event =
+				if failed { Event::BatchCompletedWithErrors } else { Event::BatchCompleted };
+			runtime.as_mut().deposit_event(event);
+			Ok(())
+		})
+	}
+
+	/// Check the size and nesting depth of a batch of `len` calls, and run `f` one level deeper.
+	fn nested<R: AsMut<Self>>(
+		runtime: &mut R,
+		len: usize,
+		f: impl FnOnce(&mut R) -> DispatchResult,
+	) -> DispatchResult {
+		if len > T::MAX_CALLS {
+			return Err("Too many calls in batch")
+		}
+		if runtime.as_mut().depth >= T::MAX_NESTING_DEPTH {
+			return Err("Batches are nested too deeply")
+		}
+		runtime.as_mut().depth += 1;
+		let result = f(runtime);
+		runtime.as_mut().depth -= 1;
+		result
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, _n: T::BlockNumber) {
+		self.events.clear();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{EntryPoint, Event};
+	use crate::{
+		balances,
+		support::{Dispatch, Transactional},
+	};
+
+	#[derive(Debug, Clone)]
+	pub struct Test;
+
+	impl super::Config for Test {
+		type RuntimeCall = TestCall;
+		const MAX_CALLS: usize = 4;
+		const MAX_NESTING_DEPTH: u32 = 2;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	impl balances::Config for Test {
+		const MAX_TRANSFERS_PER_BATCH: usize = 100;
+	}
+
+	#[derive(Debug, Clone)]
+	pub enum TestCall {
+		Balances(balances::EntryPoint<Test>),
+		Utility(EntryPoint<Test>),
+	}
+
+	/// A runtime with just enough pallets to dispatch batches of transfers.
+	struct TestRuntime {
+		balances: balances::Pallet<Test>,
+		utility: super::Pallet<Test>,
+		/// A copy of both pallets for each open transaction, innermost last.
+		transactions: Vec<(balances::Pallet<Test>, super::Pallet<Test>)>,
+	}
+
+	impl Transactional for TestRuntime {
+		fn start_transaction(&mut self) {
+			self.transactions.push((self.balances.clone(), self.utility.clone()));
+		}
+
+		fn commit_transaction(&mut self) {
+			self.transactions.pop();
+		}
+
+		fn rollback_transaction(&mut self) {
+			if let Some((balances, utility)) = self.transactions.pop() {
+				self.balances = balances;
+				self.utility = utility;
+			}
+		}
+	}
+
+	impl AsMut<super::Pallet<Test>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Test> {
+			&mut self.utility
+		}
+	}
+
+	impl Dispatch for TestRuntime {
+		type Caller = String;
+		type Call = TestCall;
+
+		fn dispatch(&mut self, caller: String, call: TestCall) -> crate::support::DispatchResult {
+			match call {
+				TestCall::Balances(call) => self.balances.dispatch(caller, call),
+				TestCall::Utility(call) => super::Pallet::dispatch(self, caller, call),
+			}
+		}
+	}
+
+	fn new_runtime() -> TestRuntime {
+		let mut balances = balances::Pallet::new();
+		balances.set_balance(&"alice".to_string(), 100);
+		TestRuntime { balances, utility: super::Pallet::new(), transactions: Vec::new() }
+	}
+
+	fn transfer(amount: u128) -> TestCall {
+		TestCall::Balances(balances::EntryPoint::Transfer { to: "bob".to_string(), amount })
+	}
+
+	#[test]
+	fn batch_semantics() {
+		let alice = "alice".to_string();
+		let calls = || vec![transfer(10), transfer(200), transfer(20)];
+
+		// `batch` stops at the first error, but keeps what was done before it.
+		let mut runtime = new_runtime();
+		assert_eq!(
+			runtime.dispatch(alice.clone(), TestCall::Utility(EntryPoint::Batch(calls()))),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 10);
+		assert_eq!(
+			runtime.utility.events,
+			vec![
+				Event::ItemCompleted,
+				Event::BatchInterrupted { index: 1, error: "Not enough funds." }
+			]
+		);
+
+		// `batch_all` rolls everything back.
+		let mut runtime = new_runtime();
+		let call = TestCall::Utility(EntryPoint::BatchAll(calls()));
+		assert_eq!(runtime.dispatch(alice.clone(), call), Err("Not enough funds."));
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 0);
+		assert!(runtime.utility.events.is_empty());
+
+		// `force_batch` carries on after errors.
+		let mut runtime = new_runtime();
+		let call = TestCall::Utility(EntryPoint::ForceBatch(calls()));
+		assert_eq!(runtime.dispatch(alice.clone(), call), Ok(()));
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 30);
+		assert_eq!(runtime.utility.events.last(), Some(&Event::BatchCompletedWithErrors));
+	}
+
+	#[test]
+	fn nested_batches() {
+		let alice = "alice".to_string();
+		let mut runtime = new_runtime();
+		let too_many = vec![transfer(1), transfer(1), transfer(1), transfer(1), transfer(1)];
+		let call = TestCall::Utility(EntryPoint::BatchAll(too_many));
+		assert_eq!(runtime.dispatch(alice.clone(), call), Err("Too many calls in batch"));
+
+		let inner = TestCall::Utility(EntryPoint::BatchAll(vec![transfer(5)]));
+		let call = TestCall::Utility(EntryPoint::BatchAll(vec![transfer(1), inner]));
+		assert_eq!(runtime.dispatch(alice.clone(), call), Ok(()));
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 6);
+
+		let innermost = TestCall::Utility(EntryPoint::BatchAll(vec![transfer(5)]));
+		let inner = TestCall::Utility(EntryPoint::BatchAll(vec![innermost]));
+		let call = TestCall::Utility(EntryPoint::BatchAll(vec![transfer(1), inner]));
+		assert_eq!(runtime.dispatch(alice.clone(), call), Err("Batches are nested too deeply"));
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 6);
+	}
+}
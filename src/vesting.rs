@@ -0,0 +1,393 @@
+use core::fmt::Debug;
+use std::collections::BTreeMap;
+
+use num::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, One, Zero};
+
+use crate::support::{
+	Convert, Currency, DispatchResult, Encode, Hooks, LockIdentifier, LockableCurrency,
+	OnTimestampSet, WithdrawReasons,
+};
+
+/// The identifier of the balance lock holding the funds which have not vested yet.
+pub const VESTING_ID: LockIdentifier = *b"vesting ";
+
+pub trait Config: crate::types::Config {
+	/// The currency in which vested funds are granted.
+	type Currency: LockableCurrency<Self::AccountId, Balance = Self::Balance>;
+	/// How to turn a number of blocks into an amount of the currency.
+	type BlockNumberToBalance: Convert<Self::BlockNumber, Self::Balance>;
+	/// The smallest amount which can be granted with `vested_transfer`.
+	const MIN_VESTED_TRANSFER: Self::Balance;
+	/// The type used to represent a point in time, as given to us by the timestamp pallet.
+	type Moment: Zero + Copy + Debug + Eq;
+}
+
+/// A schedule releasing `locked` funds linearly, `per_block` every block from `starting_block`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VestingInfo<Balance, BlockNumber> {
+	/// The amount locked when the schedule was created.
+	pub locked: Balance,
+	/// The amount released at every block after `starting_block`.
+	pub per_block: Balance,
+	/// The cliff: no funds are released before this block.
+	pub starting_block: BlockNumber,
+}
+
+/// The `VestingInfo` stored by a runtime configured with `T`.
+pub type VestingInfoOf<T> =
+	VestingInfo<<T as crate::types::Config>::Balance, <T as crate::types::Config>::BlockNumber>;
+
+/// The events emitted by this pallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<T: Config> {
+	/// `account` was granted `locked` funds which vest over time, at time `at`.
+	Created { account: T::AccountId, locked: T::Balance, at: T::Moment },
+	/// The lock on the funds of `account` was updated, and `unvested` funds remain locked.
+	Updated { account: T::AccountId, unvested: T::Balance },
+	/// All the funds granted to `account` have vested, and were unlocked at time `at`.
+	Completed { account: T::AccountId, at: T::Moment },
+}
+
+/// This is the Vesting Module.
+/// It locks funds granted to an account, and releases them a little bit every block.
+#[derive(Debug, Clone)]
+pub struct Pallet<T: Config> {
+	/// The vesting schedule of each account which has one. An account has at most one schedule:
+	/// a new grant is merged into the existing one.
+	pub vesting: BTreeMap<T::AccountId, VestingInfoOf<T>>,
+	/// The events emitted during the current block.
+	pub events: Vec<Event<T>>,
+	/// The number of the block currently being executed, as given to us in `on_initialize`.
+	block_number: T::BlockNumber,
+	/// The time of the block currently being executed, as given to us in `on_timestamp_set`.
+	now: T::Moment,
+}
+
+// A public enum which describes the calls we want to expose to the dispatcher.
+// We should expect that the caller of each call will be provided by the dispatcher,
+// and not included as a parameter of the call.
+#[derive(Debug, Clone)]
+pub enum EntryPoint<T: Config> {
+	VestedTransfer { target: T::AccountId, schedule: VestingInfoOf<T> },
+	Vest,
+	VestOther(T::AccountId),
+}
+
+impl<T: Config> Encode for EntryPoint<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			EntryPoint::VestedTransfer { target, schedule } => {
+				let VestingInfo { locked, per_block, starting_block } = schedule;
+				(0u8, target, locked, per_block, starting_block).encode_to(dest)
+			},
+			EntryPoint::Vest => 1u8.encode_to(dest),
+			EntryPoint::VestOther(target) => (2u8, target).encode_to(dest),
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Implementation of the dispatch logic, mapping from `EntryPoint` to the appropriate
+	/// underlying function we want to execute.
+	pub fn dispatch(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		call: EntryPoint<T>,
+	) -> DispatchResult {
+		match call {
+			EntryPoint::VestedTransfer { target, schedule } => {
+				self.vested_transfer(currency, caller, target, schedule)?
+			},
+			EntryPoint::Vest => self.vest(currency, caller)?,
+			EntryPoint::VestOther(target) => self.vest_other(currency, target)?,
+		}
+		Ok(())
+	}
+
+	/// Create a new instance of the Vesting Module.
+	pub fn new() -> Self {
+		Self {
+			vesting: BTreeMap::new(),
+			events: Vec::new(),
+			block_number: T::BlockNumber::zero(),
+			now: T::Moment::zero(),
+		}
+	}
+
+	fn deposit_event(&mut self, event: Event<T>) {
+		self.events.push(event);
+	}
+
+	/// Get the amount of the funds of `schedule` which are still locked at block `n`.
+	pub fn locked_at(schedule: &VestingInfoOf<T>, n: T::BlockNumber) -> T::Balance {
+		let elapsed = n.checked_sub(&schedule.starting_block).unwrap_or(T::BlockNumber::zero());
+		// If this overflows, it is certainly more than what was locked.
+		let vested = T::BlockNumberToBalance::convert(elapsed)
+			.checked_mul(&schedule.per_block)
+			.unwrap_or(schedule.locked);
+		schedule.locked.checked_sub(&vested).unwrap_or(T::Balance::zero())
+	}
+
+	/// Get the block, as an amount of the currency, at which all the funds of `schedule` have
+	/// vested.
+	fn ending_block(schedule: &VestingInfoOf<T>) -> Result<T::Balance, &'static str> {
+		let duration = div_ceil(schedule.locked, schedule.per_block).ok_or("Overflow")?;
+		T::BlockNumberToBalance::convert(schedule.starting_block)
+			.checked_add(&duration)
+			.ok_or("Overflow")
+	}
+
+	/// Merge two schedules into one which releases what is left of both, starting from the
+	/// current block or the latest cliff, and ending when the later of the two would have ended.
+	fn merge_schedules(
+		&self,
+		a: &VestingInfoOf<T>,
+		b: &VestingInfoOf<T>,
+	) -> Result<VestingInfoOf<T>, &'static str> {
+		let now = self.block_number;
+		let locked = Self::locked_at(a, now)
+			.checked_add(&Self::locked_at(b, now))
+			.ok_or("Overflow")?;
+		let starting_block = now.max(a.starting_block).max(b.starting_block);
+		let ending_block = Self::ending_block(a)?.max(Self::ending_block(b)?);
+		let duration = ending_block
+			.checked_sub(&T::BlockNumberToBalance::convert(starting_block))
+			.unwrap_or(T::Balance::zero());
+		let per_block = if duration.is_zero() {
+			locked
+		} else {
+			div_ceil(locked, duration).ok_or("Overflow")?
+		};
+		Ok(VestingInfo { locked, per_block: per_block.max(T::Balance::one()), starting_block })
+	}
+
+	/// Get the amount of the funds granted to `who` which have not vested yet, if it has a
+	/// vesting schedule.
+	pub fn vesting_balance(&self, who: &T::AccountId) -> Option<T::Balance> {
+		self.vesting.get(who).map(|schedule| Self::locked_at(schedule, self.block_number))
+	}
+
+	/// Transfer `schedule.locked` from the `caller` to `target`, and lock it in the account of
+	/// `target` until it vests. If `target` already has a vesting schedule, the two are merged.
+	/// This function will return an error if the amount is below `T::MIN_VESTED_TRANSFER`.
+	pub fn vested_transfer(
+		&mut self,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		target: T::AccountId,
+		schedule: VestingInfoOf<T>,
+	) -> DispatchResult {
+		if schedule.locked < T::MIN_VESTED_TRANSFER {
+			return Err("Amount vested is too low")
+		}
+		if schedule.per_block.is_zero() {
+			return Err("Vesting schedule never releases any funds")
+		}
+		let merged = match self.vesting.get(&target) {
+			Some(existing) => self.merge_schedules(existing, &schedule)?,
+			None => schedule,
+		};
+		currency.transfer(&caller, &target, schedule.locked)?;
+
+		self.vesting.insert(target.clone(), merged);
+		let locked = schedule.locked;
+		self.deposit_event(Event::Created { account: target.clone(), locked, at: self.now });
+		self.update_lock(currency, target)
+	}
+
+	/// Unlock the funds of the `caller` which have vested so far.
+	pub fn vest(&mut self, currency: &mut T::Currency, caller: T::AccountId) -> DispatchResult {
+		self.update_lock(currency, caller)
+	}
+
+	/// Unlock the funds of `target` which have vested so far, on its behalf. Any signed account
+	/// can do this, since it only ever releases funds to their owner.
+	pub fn vest_other(
+		&mut self,
+		currency: &mut T::Currency,
+		target: T::AccountId,
+	) -> DispatchResult {
+		self.update_lock(currency, target)
+	}
+
+	/// Remove the schedules which have fully vested by block `n`, unlocking their funds.
+	///
+	/// The runtime calls this at the start of every block, once the timestamp is set, so that
+	/// nobody has to call `vest` to get the last of their funds. It is not part of `Hooks` since we
+	/// need the currency to remove the locks.
+	pub fn release_vested(&mut self, currency: &mut T::Currency, n: T::BlockNumber) {
+		let completed: Vec<_> = self
+			.vesting
+			.iter()
+			.filter(|(_, schedule)| Self::locked_at(schedule, n).is_zero())
+			.map(|(who, _)| who.clone())
+			.collect();
+		for who in completed {
+			currency.remove_lock(VESTING_ID, &who);
+			self.vesting.remove(&who);
+			self.deposit_event(Event::Completed { account: who, at: self.now });
+		}
+	}
+
+	/// Make the vesting lock on the funds of `who` match what has not vested yet, and remove the
+	/// schedule once everything has vested.
+	fn update_lock(&mut self, currency: &mut T::Currency, who: T::AccountId) -> DispatchResult {
+		let unvested = self.vesting_balance(&who).ok_or("Account has no vesting schedule")?;
+		if unvested.is_zero() {
+			currency.remove_lock(VESTING_ID, &who);
+			self.vesting.remove(&who);
+			self.deposit_event(Event::Completed { account: who, at: self.now });
+		} else {
+			let reasons = WithdrawReasons::TRANSFER.union(WithdrawReasons::RESERVE);
+			currency.set_lock(VESTING_ID, &who, unvested, reasons);
+			self.deposit_event(Event::Updated { account: who, unvested });
+		}
+		Ok(())
+	}
+}
+
+/// Divide `a` by `b`, rounding up, or `None` if `b` is zero.
+fn div_ceil<Balance: CheckedDiv + CheckedMul + CheckedAdd + Ord + One>(
+	a: Balance,
+	b: Balance,
+) -> Option<Balance> {
+	let quotient = a.checked_div(&b)?;
+	if quotient.checked_mul(&b)? < a {
+		quotient.checked_add(&Balance::one())
+	} else {
+		Some(quotient)
+	}
+}
+
+impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+	fn on_initialize(&mut self, n: T::BlockNumber) {
+		self.block_number = n;
+		self.events.clear();
+	}
+}
+
+impl<T: Config> OnTimestampSet<T::Moment> for Pallet<T> {
+	fn on_timestamp_set(&mut self, moment: T::Moment) {
+		self.now = moment;
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::support::{ConvertInto, Currency, Hooks, OnTimestampSet};
+
+	#[derive(Debug, Clone, PartialEq, Eq)]
+	pub struct Test;
+
+	impl super::Config for Test {
+		type Currency = crate::balances::Pallet<Test>;
+		type BlockNumberToBalance = ConvertInto;
+		const MIN_VESTED_TRANSFER: u128 = 10;
+		type Moment = u64;
+	}
+
+	impl crate::types::Config for Test {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type Balance = u128;
+	}
+
+	impl crate::balances::Config for Test {
+		const MAX_TRANSFERS_PER_BATCH: usize = 100;
+	}
+
+	#[test]
+	fn linear_vesting() {
+		let mut vesting = super::Pallet::<Test>::new();
+		let mut balances = crate::balances::Pallet::<Test>::new();
+		balances.set_balance(&"alice", 1_000);
+		vesting.on_initialize(1);
+
+		// 100 locked, released 10 per block from block 5.
+		let schedule = super::VestingInfo { locked: 100, per_block: 10, starting_block: 5 };
+		let too_small = super::VestingInfo { locked: 9, ..schedule };
+		assert_eq!(
+			vesting.vested_transfer(&mut balances, "alice", "bob", too_small),
+			Err("Amount vested is too low")
+		);
+		assert_eq!(vesting.vested_transfer(&mut balances, "alice", "bob", schedule), Ok(()));
+		assert_eq!(balances.free_balance(&"bob"), 100);
+		assert_eq!(balances.transfer(&"bob", &"charlie", 1), Err("Funds are locked."));
+
+		// Nothing is released before the cliff.
+		vesting.on_initialize(5);
+		assert_eq!(vesting.vest(&mut balances, "bob"), Ok(()));
+		assert_eq!(vesting.vesting_balance(&"bob"), Some(100));
+
+		// Anyone can unlock vested funds for someone else.
+		vesting.on_initialize(8);
+		assert_eq!(vesting.vest_other(&mut balances, "bob"), Ok(()));
+		assert_eq!(balances.transfer(&"bob", &"charlie", 31), Err("Funds are locked."));
+		assert_eq!(balances.transfer(&"bob", &"charlie", 30), Ok(()));
+
+		vesting.on_initialize(15);
+		assert_eq!(vesting.vest(&mut balances, "bob"), Ok(()));
+		assert_eq!(vesting.vesting_balance(&"bob"), None);
+		assert_eq!(balances.transfer(&"bob", &"charlie", 70), Ok(()));
+		assert_eq!(vesting.vest(&mut balances, "bob"), Err("Account has no vesting schedule"));
+	}
+
+	#[test]
+	fn schedules_are_merged() {
+		let mut vesting = super::Pallet::<Test>::new();
+		let mut balances = crate::balances::Pallet::<Test>::new();
+		balances.set_balance(&"alice", 1_000);
+		vesting.on_initialize(1);
+
+		// 100 released 10 per block from block 5, ending at block 15.
+		let first = super::VestingInfo { locked: 100, per_block: 10, starting_block: 5 };
+		vesting.vested_transfer(&mut balances, "alice", "bob", first).unwrap();
+
+		// At block 10, 50 are still locked. Another 60 released 2 per block from block 12 end at
+		// block 42, so the merged schedule releases 110 over the 30 blocks from block 12.
+		vesting.on_initialize(10);
+		let second = super::VestingInfo { locked: 60, per_block: 2, starting_block: 12 };
+		vesting.vested_transfer(&mut balances, "alice", "bob", second).unwrap();
+		let merged = super::VestingInfo { locked: 110, per_block: 4, starting_block: 12 };
+		assert_eq!(vesting.vesting.get(&"bob"), Some(&merged));
+		assert_eq!(vesting.vesting_balance(&"bob"), Some(110));
+		assert_eq!(balances.free_balance(&"bob"), 160);
+		assert_eq!(balances.transfer(&"bob", &"charlie", 51), Err("Funds are locked."));
+		assert_eq!(balances.transfer(&"bob", &"charlie", 50), Ok(()));
+
+		vesting.on_initialize(20);
+		assert_eq!(vesting.vesting_balance(&"bob"), Some(78));
+		vesting.on_initialize(40);
+		assert_eq!(vesting.vesting_balance(&"bob"), Some(0));
+	}
+
+	#[test]
+	fn completed_schedules_are_released() {
+		use super::Event;
+		let mut vesting = super::Pallet::<Test>::new();
+		let mut balances = crate::balances::Pallet::<Test>::new();
+		balances.set_balance(&"alice", 1_000);
+		vesting.on_initialize(1);
+		let short = super::VestingInfo { locked: 20, per_block: 10, starting_block: 2 };
+		let long = super::VestingInfo { locked: 20, per_block: 1, starting_block: 2 };
+		vesting.vested_transfer(&mut balances, "alice", "bob", short).unwrap();
+		vesting.vested_transfer(&mut balances, "alice", "charlie", long).unwrap();
+
+		vesting.on_initialize(3);
+		vesting.release_vested(&mut balances, 3);
+		assert!(vesting.events.is_empty());
+		assert_eq!(balances.transfer(&"bob", &"alice", 1), Err("Funds are locked."));
+
+		// bob's funds have all vested by block 4, and are unlocked without calling `vest`.
+		vesting.on_initialize(4);
+		vesting.on_timestamp_set(24_000);
+		vesting.release_vested(&mut balances, 4);
+		assert_eq!(vesting.events, vec![Event::Completed { account: "bob", at: 24_000 }]);
+		assert_eq!(vesting.vesting_balance(&"bob"), None);
+		assert_eq!(balances.transfer(&"bob", &"alice", 20), Ok(()));
+		assert_eq!(vesting.vesting_balance(&"charlie"), Some(18));
+		assert_eq!(balances.transfer(&"charlie", &"alice", 3), Err("Funds are locked."));
+	}
+}